@@ -0,0 +1,70 @@
+/*
+ * The C interface of hamcycle, built into libhamcycle with
+ * `cargo rustc --lib --release --features ffi --crate-type cdylib`.
+ *
+ * Graphs and tours are opaque handles, made by hamcycle_graph_new and hamcycle_solve and freed by
+ * hamcycle_graph_free and hamcycle_tour_free. Every function that can fail returns a
+ * hamcycle_status; a panic inside the solver is caught and returned as HAMCYCLE_PANIC, it never
+ * unwinds into the caller. Buffers are the caller's, and are filled only when the call returns
+ * HAMCYCLE_OK.
+ */
+#ifndef HAMCYCLE_H
+#define HAMCYCLE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum hamcycle_status {
+    HAMCYCLE_OK = 0,
+    /* a handle or buffer passed was null. */
+    HAMCYCLE_NULL = 1,
+    /* the discocubes start at level 1. */
+    HAMCYCLE_BAD_LEVEL = 2,
+    /* the buffer passed is too short to hold what was asked for. */
+    HAMCYCLE_SHORT_BUFFER = 3,
+    /* the tour visits a node the graph does not have. */
+    HAMCYCLE_OUT_OF_GRAPH = 4,
+    /* the solver panicked. */
+    HAMCYCLE_PANIC = 5,
+} hamcycle_status;
+
+/* the verdicts of hamcycle_certify. */
+#define HAMCYCLE_BROKEN 0
+#define HAMCYCLE_HAM_CHAIN 1
+#define HAMCYCLE_HAM_CYCLE 2
+
+typedef struct HamGraph HamGraph;
+typedef struct HamTour HamTour;
+
+/* makes the discocube of the level given. */
+hamcycle_status hamcycle_graph_new(uint32_t level, HamGraph **graph);
+void hamcycle_graph_free(HamGraph *graph);
+/* the number of vertices, 0 for null. */
+size_t hamcycle_graph_order(const HamGraph *graph);
+/* x, y and z of every vertex in node order; len is at least 3 * order. */
+hamcycle_status hamcycle_graph_coordinates(const HamGraph *graph, int16_t *buffer, size_t len);
+
+/* weaves a hamiltonian cycle on the graph. */
+hamcycle_status hamcycle_solve(const HamGraph *graph, HamTour **tour);
+void hamcycle_tour_free(HamTour *tour);
+/* the number of nodes of the tour, 0 for null. */
+size_t hamcycle_tour_len(const HamTour *tour);
+/* the nodes of the tour in order; len is at least the tour's length. */
+hamcycle_status hamcycle_tour_nodes(const HamTour *tour, uint32_t *buffer, size_t len);
+/* x, y and z of every vertex in the order the tour visits them; len is at least 3 * its length. */
+hamcycle_status hamcycle_tour_coordinates(const HamGraph *graph, const HamTour *tour,
+                                          int16_t *buffer, size_t len);
+
+/* certifies a tour of len nodes, writing one of the verdicts above to *verdict. */
+hamcycle_status hamcycle_certify(const HamGraph *graph, const uint32_t *tour, size_t len,
+                                 int32_t *verdict);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
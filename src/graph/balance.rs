@@ -0,0 +1,83 @@
+use super::{
+    darn::{Darner, Objective},
+    defs::{Adjacency, Point, Solution, VIMap, Verts, ZAdjacency, ZOrder},
+    weave::weave,
+};
+
+const SEED: u64 = 0;
+/// Darns allowed per node of the cycle, and at most in all. Balancing takes about order² / 300 darns,
+/// so this is enough up to about level 30 and gives up on the larger levels within a minute or two
+/// instead of running on for hours.
+const DARNS_PER_NODE: usize = 200;
+const MAX_DARNS: usize = 10_000_000;
+
+/// The loom is stacked layer on layer, so the woven cycle runs mostly along x and y and only seldom
+/// climbs in z. The darner flips squares across the tour, trading flat edges for climbing ones, until
+/// the spread between the three axis counts is within the tolerance, or until it has tried the darns
+/// allowed, `darns(order)` unless given.
+#[allow(clippy::too_many_arguments)]
+pub fn balance(
+    adj: &Adjacency,
+    vi_map: &VIMap,
+    verts: &Verts,
+    z_adj: ZAdjacency,
+    z_order: ZOrder,
+    min_xyz: Point,
+    tolerance: usize,
+    darns: Option<usize>,
+) -> Result<Solution, &'static str> {
+    let solution = weave(adj, vi_map, verts, z_adj, z_order, min_xyz);
+    let mut darner = Darner::new(solution, verts, vi_map, Objective::Balance(tolerance), SEED);
+    match darner.darn(0, darns.unwrap_or_else(|| self::darns(verts.len()))) {
+        0 => Ok(darner.tour()),
+        _ => Err(
+            "ran out of darns before the axes of the woven cycle were even within the tolerance",
+        ),
+    }
+}
+
+/// The darns balancing a cycle of the order given may try by default.
+pub fn darns(order: usize) -> usize {
+    order.saturating_mul(DARNS_PER_NODE).min(MAX_DARNS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{balance, darns};
+    use crate::graph::utils::{
+        certify::is_balanced,
+        info::{axis_counts, axis_spread, min_axis_spread},
+        make::make_graph,
+    };
+
+    #[test]
+    fn reaches_the_bound_on_small_levels() {
+        for level in 1..=8 {
+            let (_, order, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(level);
+            let bound = min_axis_spread(order);
+            let tour =
+                balance(&adj, &vi_map, &verts, z_adj, z_order, min_xyz, bound, None).unwrap();
+            assert!(is_balanced(&tour, &adj, &verts, bound));
+        }
+    }
+
+    #[test]
+    fn errs_when_the_darns_run_out() {
+        let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(10);
+        assert!(balance(&adj, &vi_map, &verts, z_adj, z_order, min_xyz, 0, Some(1)).is_err());
+    }
+
+    #[test]
+    fn does_not_call_the_woven_cycle_balanced() {
+        let (_, order, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(10);
+        let tour = crate::graph::weave::weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz);
+        assert!(axis_spread(&axis_counts(&tour, &verts)) > min_axis_spread(order));
+        assert!(!is_balanced(&tour, &adj, &verts, min_axis_spread(order)));
+    }
+
+    #[test]
+    fn scales_the_darns_with_the_order() {
+        assert_eq!(darns(1_000), 200_000);
+        assert!(darns(usize::MAX) <= 10_000_000);
+    }
+}
@@ -0,0 +1,270 @@
+use itertools::Itertools;
+use sha2::{Digest, Sha256};
+
+use super::{
+    defs::{Point, TourSlice, Vert, Verts},
+    utils::info::axis,
+};
+
+/// Directions a tour can step in, +x, -x, +y, -y, +z and -z.
+const DIRECTIONS: [u8; 6] = *b"xXyYzZ";
+
+/// A 256-bit fingerprint, the SHA-256 digest of the canonical direction string.
+pub type Fingerprint = [u8; 32];
+
+/// One of the 48 symmetries of the octahedron: axis i is carried to axis `axes[i]`, mirrored when
+/// `signs[i]` is -1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Symmetry {
+    pub axes: [usize; 3],
+    pub signs: [Point; 3],
+}
+
+impl Symmetry {
+    pub fn apply(&self, (x, y, z): Vert) -> Vert {
+        let mut image = [0; 3];
+        [x, y, z]
+            .into_iter()
+            .enumerate()
+            .for_each(|(i, coord)| image[self.axes[i]] = coord * self.signs[i]);
+        (image[0], image[1], image[2])
+    }
+
+    /// Where a step along `axis`, forwards or backwards, points after the symmetry.
+    fn carry(&self, (axis, forward): (usize, bool)) -> (usize, bool) {
+        (self.axes[axis], forward == (self.signs[axis] > 0))
+    }
+}
+
+/// The canonical form of a cycle: of every symmetry, starting node and direction, the one that spells
+/// the lexicographically smallest string of steps.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Canonical {
+    pub symmetry: Symmetry,
+    /// Index into the tour of the node the canonical walk starts from.
+    pub start: usize,
+    /// Whether the canonical walk runs against the tour.
+    pub reversed: bool,
+    /// One of `xXyYzZ` for every step of the walk, lowercase stepping up the axis.
+    pub directions: String,
+}
+
+impl Canonical {
+    pub fn fingerprint(&self) -> Fingerprint {
+        Sha256::digest(self.directions.as_bytes()).into()
+    }
+}
+
+/// The 48 symmetries of the octahedron, every permutation of the axes with every choice of signs, the
+/// identity first.
+pub fn symmetries() -> Vec<Symmetry> {
+    (0..3)
+        .permutations(3)
+        .cartesian_product(0..8)
+        .map(|(axes, mirror)| Symmetry {
+            axes: [axes[0], axes[1], axes[2]],
+            signs: [0, 1, 2].map(|i| if mirror >> i & 1 == 1 { -1 } else { 1 }),
+        })
+        .collect()
+}
+
+/// Brings the cycle into canonical form. Only the steps matter, so the cycle may lie anywhere, and
+/// cycles that are the same up to translation, symmetry, starting node and direction share it. A
+/// tour of fewer than two nodes, or one that steps from a node to itself or to a node `verts` does
+/// not have, has no steps to spell and is an error.
+pub fn canonical(tour: TourSlice, verts: &Verts) -> Result<Canonical, &'static str> {
+    if tour.len() < 2 {
+        return Err("a cycle has two nodes at least");
+    }
+    let steps: Vec<(usize, bool)> = tour
+        .iter()
+        .circular_tuple_windows()
+        .map(|(&a, &b)| {
+            let (from, to) = match (verts.get(a as usize), verts.get(b as usize)) {
+                (Some(&from), Some(&to)) => (from, to),
+                _ => return Err("the tour has a node the vertices do not"),
+            };
+            if from == to {
+                return Err("the tour steps from a node to itself");
+            }
+            let line = axis(&from, &to);
+            Ok((
+                line,
+                [to.0, to.1, to.2][line] > [from.0, from.1, from.2][line],
+            ))
+        })
+        .collect::<Result<_, _>>()?;
+    let order = steps.len();
+    let backwards: Vec<(usize, bool)> = steps
+        .iter()
+        .rev()
+        .map(|&(line, forward)| (line, !forward))
+        .collect();
+    symmetries()
+        .into_iter()
+        .cartesian_product([false, true])
+        .map(|(symmetry, reversed)| {
+            let word: Vec<u8> = match reversed {
+                false => &steps,
+                true => &backwards,
+            }
+            .iter()
+            .map(|&step| {
+                let (line, forward) = symmetry.carry(step);
+                DIRECTIONS[line * 2 + !forward as usize]
+            })
+            .collect();
+            let shift = least_rotation(&word);
+            let start = match reversed {
+                false => shift,
+                true => (order - shift) % order,
+            };
+            let directions: Vec<u8> = word[shift..]
+                .iter()
+                .chain(&word[..shift])
+                .copied()
+                .collect();
+            (directions, symmetry, start, reversed)
+        })
+        .min_by(|lhs, rhs| lhs.0.cmp(&rhs.0))
+        .map(|(directions, symmetry, start, reversed)| Canonical {
+            symmetry,
+            start,
+            reversed,
+            directions: String::from_utf8(directions).unwrap(),
+        })
+        .ok_or("a cycle has two nodes at least")
+}
+
+/// The fingerprint of the canonical form of the cycle.
+pub fn fingerprint(tour: TourSlice, verts: &Verts) -> Result<Fingerprint, &'static str> {
+    canonical(tour, verts).map(|form| form.fingerprint())
+}
+
+/// Where the lexicographically smallest rotation of the word starts, found in linear time by letting
+/// two candidate starts race and dropping the loser past where they first differ.
+fn least_rotation(word: &[u8]) -> usize {
+    let len = word.len();
+    let (mut lhs, mut rhs, mut matched) = (0, 1, 0);
+    while lhs < len && rhs < len && matched < len {
+        let (a, b) = (word[(lhs + matched) % len], word[(rhs + matched) % len]);
+        if a == b {
+            matched += 1;
+            continue;
+        }
+        match a > b {
+            true => lhs += matched + 1,
+            false => rhs += matched + 1,
+        }
+        if lhs == rhs {
+            rhs += 1;
+        }
+        matched = 0;
+    }
+    lhs.min(rhs)
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::{canonical, fingerprint, least_rotation, symmetries};
+    use crate::graph::{defs::VecVert, utils::make::make_graph, weave::weave};
+
+    #[test]
+    fn lists_the_48_symmetries_from_the_identity() {
+        let all = symmetries();
+        assert_eq!(all.len(), 48);
+        assert_eq!(
+            all.iter().unique_by(|sym| (sym.axes, sym.signs)).count(),
+            48
+        );
+        assert_eq!((all[0].axes, all[0].signs), ([0, 1, 2], [1, 1, 1]));
+    }
+
+    #[test]
+    fn finds_the_least_rotation() {
+        let rng = &mut StdRng::seed_from_u64(0);
+        for len in 1..40 {
+            let word: Vec<u8> = (0..len).map(|_| b"xXy"[rng.gen_range(0..3)]).collect();
+            let rotated = |shift: usize| [&word[shift..], &word[..shift]].concat();
+            let least = (0..len).map(rotated).min().unwrap();
+            assert_eq!(rotated(least_rotation(&word)), least);
+        }
+    }
+
+    #[test]
+    fn shares_the_fingerprint_across_every_symmetry_start_and_direction() {
+        let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(4);
+        let tour = weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz);
+        let print = fingerprint(&tour, &verts).unwrap();
+        for symmetry in symmetries() {
+            let turned: VecVert = verts.iter().map(|&vert| symmetry.apply(vert)).collect();
+            assert_eq!(fingerprint(&tour, &turned).unwrap(), print);
+        }
+        let mut moved = tour.clone();
+        moved.rotate_left(17);
+        moved.reverse();
+        assert_eq!(fingerprint(&moved, &verts).unwrap(), print);
+        let shifted: VecVert = verts.iter().map(|&(x, y, z)| (x + 8, y, z - 4)).collect();
+        assert_eq!(fingerprint(&tour, &shifted).unwrap(), print);
+    }
+
+    #[test]
+    fn spells_one_step_per_edge_from_the_start_it_names() {
+        let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(3);
+        let tour = weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz);
+        let form = canonical(&tour, &verts).unwrap();
+        assert_eq!(form.directions.len(), tour.len());
+        assert!(form
+            .directions
+            .bytes()
+            .all(|step| b"xXyYzZ".contains(&step)));
+        let order = tour.len();
+        let walk: Vec<u32> = (0..order)
+            .map(|step| match form.reversed {
+                false => tour[(form.start + step) % order],
+                true => tour[(form.start + order - step) % order],
+            })
+            .collect();
+        let turned: VecVert = verts
+            .iter()
+            .map(|&vert| form.symmetry.apply(vert))
+            .collect();
+        let spelled: String = walk
+            .iter()
+            .circular_tuple_windows()
+            .map(|(&a, &b)| {
+                let (from, to) = (turned[a as usize], turned[b as usize]);
+                match (to.0 - from.0, to.1 - from.1, to.2 - from.2) {
+                    (2, _, _) => 'x',
+                    (-2, _, _) => 'X',
+                    (_, 2, _) => 'y',
+                    (_, -2, _) => 'Y',
+                    (_, _, 2) => 'z',
+                    _ => 'Z',
+                }
+            })
+            .collect();
+        assert_eq!(spelled, form.directions);
+    }
+
+    #[test]
+    fn errs_on_a_tour_with_no_steps_to_spell() {
+        let verts: VecVert = vec![(1, 1, 1), (3, 1, 1)];
+        assert!(canonical(&[], &verts).is_err());
+        assert!(canonical(&[0], &verts).is_err());
+        assert!(fingerprint(&[1], &verts).is_err());
+        assert!(canonical(&[0, 0], &verts).is_err());
+        assert!(canonical(&[0, 2], &verts).is_err());
+    }
+
+    #[test]
+    fn spells_a_single_edge_there_and_back() {
+        let verts: VecVert = vec![(1, 1, 1), (1, 3, 1)];
+        let form = canonical(&[0, 1], &verts).unwrap();
+        assert_eq!(form.directions, "Xx");
+        assert_eq!(fingerprint(&[1, 0], &verts), Ok(form.fingerprint()));
+    }
+}
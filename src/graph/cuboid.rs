@@ -0,0 +1,253 @@
+use std::{collections::HashSet, fmt};
+
+use itertools::Itertools;
+
+use super::{
+    defs::{Adjacency, Node, Point, Solution, VIMap, Vert, Verts},
+    factor::{reach, UNSET},
+};
+
+/// Why a graph has no hamiltonian cycle, in a form that can be checked against the graph on its own.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Obstruction {
+    /// A vertex with fewer than two neighbours, which no cycle through every vertex can pass through.
+    Leaf { node: Node, degree: usize },
+    /// A proper two-colouring whose classes differ in size, while a cycle alternates between them.
+    Unbalanced { black: usize, white: usize },
+    /// A vertex that cannot be reached from vertex 0.
+    Disconnected { node: Node },
+    /// A vertex whose removal cuts the graph in two, a cycle would have to pass it twice.
+    CutVertex { node: Node },
+    /// Black vertices that cannot all have two edges to white ones: they need 2·|black| edges, but have
+    /// fewer to the white vertices outside `white` than 2·|black| - 2·|white|, and each white vertex
+    /// takes two.
+    Deficient { black: Vec<Node>, white: Vec<Node> },
+    /// Not a proof: merging the cycles of a 2-factor got stuck with this many left.
+    Stuck { cycles: usize },
+}
+
+impl Obstruction {
+    /// Whether the obstruction proves there is no cycle, which every one but `Stuck` does.
+    pub fn is_proof(&self) -> bool {
+        !matches!(self, Obstruction::Stuck { .. })
+    }
+
+    /// Checks the proof against the graph: the leaf has the degree claimed, every edge joins a black
+    /// and a white vertex and the classes have the sizes claimed, the vertex is out of reach of vertex
+    /// 0 or cuts off some other vertex, or the black vertices are short of edges as claimed.
+    pub fn certify(&self, adj: &Adjacency, verts: &Verts) -> bool {
+        let order = verts.len();
+        let neighbors: Vec<Vec<usize>> = (0..order as Node)
+            .map(|node| adj[&node].iter().map(|&other| other as usize).collect())
+            .collect();
+        let count = |seen: Vec<bool>| seen.into_iter().filter(|&seen| seen).count();
+        match *self {
+            Obstruction::Leaf { node, degree } => {
+                degree < 2 && adj.get(&node).map(|neighbors| neighbors.len()) == Some(degree)
+            }
+            Obstruction::Unbalanced { black, white } => {
+                let blacks = verts.iter().filter(|&&vert| is_black(vert)).count();
+                black != white
+                    && (blacks, verts.len() - blacks) == (black, white)
+                    && adj.iter().all(|(&node, neighbors)| {
+                        neighbors.iter().all(|&other| {
+                            is_black(verts[node as usize]) != is_black(verts[other as usize])
+                        })
+                    })
+            }
+            Obstruction::Disconnected { node } => {
+                (node as usize) < order && !reach(&neighbors, 0, UNSET)[node as usize]
+            }
+            Obstruction::CutVertex { node } => {
+                let node = node as usize;
+                let from = (node + 1) % order;
+                node < order && order > 2 && count(reach(&neighbors, from, node)) < order - 1
+            }
+            Obstruction::Deficient {
+                ref black,
+                ref white,
+            } => {
+                let is_member = |nodes: &[Node], colour: bool| {
+                    nodes.iter().all(|&node| {
+                        (node as usize) < order && is_black(verts[node as usize]) == colour
+                    }) && nodes.iter().unique().count() == nodes.len()
+                };
+                let full: HashSet<&Node> = white.iter().collect();
+                let outside = black
+                    .iter()
+                    .flat_map(|node| adj[node].iter())
+                    .filter(|node| !full.contains(node))
+                    .count();
+                is_member(black, true)
+                    && is_member(white, false)
+                    && outside + 2 * white.len() < 2 * black.len()
+            }
+            Obstruction::Stuck { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for Obstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Obstruction::Leaf { node, degree } => {
+                write!(
+                    f,
+                    "vertex {node} has {degree} neighbours, a cycle needs two"
+                )
+            }
+            Obstruction::Unbalanced { black, white } => {
+                write!(
+                    f,
+                    "{black} black and {white} white vertices, every edge joins the two"
+                )
+            }
+            Obstruction::Disconnected { node } => {
+                write!(f, "vertex {node} cannot be reached from vertex 0")
+            }
+            Obstruction::CutVertex { node } => {
+                write!(f, "removing vertex {node} cuts the graph in two")
+            }
+            Obstruction::Deficient { black, white } => write!(
+                f,
+                "{} black vertices need more edges than {} white vertices and the rest can take",
+                black.len(),
+                white.len()
+            ),
+            Obstruction::Stuck { cycles } => {
+                write!(f, "merging got stuck with {cycles} cycles left")
+            }
+        }
+    }
+}
+
+/// Solves the a × b × c grid graph made by `make::make_cuboid`. A cycle exists exactly when a·b·c is
+/// even and at most one side is 1: otherwise a vertex at the end of a line has a single neighbour, or
+/// the colour classes of the chessboard colouring differ by one. Else one even side is taken as rows
+/// and the other two sides are snaked through as columns, which makes an even by n grid of the
+/// cuboid, and that grid is wound row by row down the columns 1.. and back up column 0.
+pub fn cuboid(
+    dims: [usize; 3],
+    adj: &Adjacency,
+    verts: &Verts,
+    vi_map: &VIMap,
+) -> Result<Solution, Obstruction> {
+    if let Some((&node, neighbors)) = adj
+        .iter()
+        .filter(|(_, neighbors)| neighbors.len() < 2)
+        .min_by_key(|(&node, _)| node)
+    {
+        return Err(Obstruction::Leaf {
+            node,
+            degree: neighbors.len(),
+        });
+    }
+    let black = verts.iter().filter(|&&vert| is_black(vert)).count();
+    if black * 2 != verts.len() {
+        return Err(Obstruction::Unbalanced {
+            black,
+            white: verts.len() - black,
+        });
+    }
+    let rows = (0..3).find(|&axis| dims[axis].is_multiple_of(2)).unwrap();
+    let (across, along) = ((rows + 1) % 3, (rows + 2) % 3);
+    let columns: Vec<[usize; 2]> = (0..dims[along])
+        .flat_map(|j| {
+            (0..dims[across]).map(move |i| match j % 2 {
+                0 => [i, j],
+                _ => [dims[across] - 1 - i, j],
+            })
+        })
+        .collect();
+    let node = |row: usize, [i, j]: [usize; 2]| {
+        let mut coords = [0; 3];
+        (coords[rows], coords[across], coords[along]) = (row, i, j);
+        let [x, y, z] = coords.map(|coord| 2 * coord as Point + 1);
+        vi_map[&(x, y, z)]
+    };
+    let mut solution: Solution = Vec::with_capacity(verts.len());
+    for row in 0..dims[rows] {
+        match row % 2 {
+            0 => solution.extend(columns[1..].iter().map(|&column| node(row, column))),
+            _ => solution.extend(columns[1..].iter().rev().map(|&column| node(row, column))),
+        }
+    }
+    solution.extend((0..dims[rows]).rev().map(|row| node(row, columns[0])));
+    Ok(solution)
+}
+
+/// The chessboard colour of a vertex on coordinates two apart, odd or even: neighbours always differ.
+pub fn is_black((x, y, z): Vert) -> bool {
+    (x as i32 + y as i32 + z as i32 - 3)
+        .div_euclid(2)
+        .rem_euclid(2)
+        == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cuboid, Obstruction};
+    use crate::graph::utils::{
+        certify::{id_seq, SequenceID},
+        make::make_cuboid,
+    };
+
+    #[test]
+    fn winds_exactly_the_cuboids_that_have_a_cycle() {
+        for a in 1..=5 {
+            for b in 1..=5 {
+                for c in 1..=5 {
+                    let dims = [a, b, c];
+                    let (_, verts, vi_map, adj) = make_cuboid(dims);
+                    let ones = dims.iter().filter(|&&side| side == 1).count();
+                    match cuboid(dims, &adj, &verts, &vi_map) {
+                        Ok(tour) => {
+                            assert!((a * b * c) % 2 == 0 && ones <= 1, "{dims:?}");
+                            assert_eq!(id_seq(&tour, &adj), SequenceID::HamCycle, "{dims:?}");
+                        }
+                        Err(obstruction) => {
+                            assert!((a * b * c) % 2 == 1 || ones > 1, "{dims:?}");
+                            assert!(obstruction.certify(&adj, &verts), "{dims:?}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_false_proofs() {
+        let (_, verts, _, adj) = make_cuboid([2, 3, 4]);
+        let false_proofs = [
+            Obstruction::Leaf { node: 0, degree: 1 },
+            Obstruction::Unbalanced {
+                black: 11,
+                white: 13,
+            },
+            Obstruction::Disconnected { node: 5 },
+            Obstruction::CutVertex { node: 5 },
+            Obstruction::Deficient {
+                black: vec![0],
+                white: vec![],
+            },
+            Obstruction::Stuck { cycles: 2 },
+        ];
+        for proof in false_proofs {
+            assert!(!proof.certify(&adj, &verts), "{proof:?}");
+        }
+        assert!(!Obstruction::Stuck { cycles: 2 }.is_proof());
+        assert!(Obstruction::CutVertex { node: 5 }.is_proof());
+    }
+
+    #[test]
+    fn proves_the_odd_cuboids_unbalanced() {
+        let (_, verts, vi_map, adj) = make_cuboid([3, 3, 3]);
+        assert_eq!(
+            cuboid([3, 3, 3], &adj, &verts, &vi_map),
+            Err(Obstruction::Unbalanced {
+                black: 14,
+                white: 13
+            })
+        );
+    }
+}
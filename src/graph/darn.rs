@@ -0,0 +1,449 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::{
+    defs::{Node, Solution, Tour, VIMap, Vert, Verts},
+    skein::Skein,
+    utils::info::{axis, axis_counts, axis_spread},
+};
+
+/// Two parallel edges (a, b) and (c, d) of a unit square, (a, c) and (b, d) being its rungs.
+pub type Patch = (Node, Node, Node, Node);
+
+const UNSET: usize = usize::MAX;
+const NONE: Node = Node::MAX;
+const RADIUS: usize = 4;
+const STALL: usize = 2_000;
+const COOLING: f64 = 0.9999;
+const FLOOR: f64 = 0.05;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Objective {
+    Turns,
+    /// Evens out the x, y and z edge counts until their spread is within the tolerance.
+    Balance(usize),
+    /// Counts the straight runs, stretches of two or more edges along one axis.
+    Runs,
+    /// Counts the edges by which straight runs overshoot the given length.
+    MaxRun(usize),
+    /// Costs nothing, every darn is taken: a random walk over the hamiltonian cycles.
+    Wander,
+}
+
+/// Mends a woven tour with square flips: either a single flip of two parallel edges, or a flip that
+/// splits the tour in two followed by a flip that joins the halves again. Every accepted darn leaves a
+/// hamiltonian cycle behind.
+pub struct Darner<'a> {
+    skein: Skein,
+    verts: &'a Verts,
+    lattice: Vec<[Node; 6]>,
+    links: Vec<[Node; 2]>,
+    counts: [usize; 3],
+    frayed: Frayed,
+    objective: Objective,
+    rng: StdRng,
+}
+
+/// The nodes a darn should start from, kept so that one can be drawn at random in constant time.
+struct Frayed {
+    nodes: Vec<Node>,
+    slots: Vec<usize>,
+}
+
+impl Frayed {
+    fn new(order: usize) -> Frayed {
+        Frayed {
+            nodes: Vec::new(),
+            slots: vec![UNSET; order],
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn get(&self, idx: usize) -> Node {
+        self.nodes[idx]
+    }
+
+    fn set(&mut self, node: Node, frayed: bool) {
+        let slot = self.slots[node as usize];
+        match (frayed, slot) {
+            (true, UNSET) => {
+                self.slots[node as usize] = self.nodes.len();
+                self.nodes.push(node);
+            }
+            (false, slot) if slot != UNSET => {
+                let last = self.nodes.pop().unwrap();
+                if last != node {
+                    self.nodes[slot] = last;
+                    self.slots[last as usize] = slot;
+                }
+                self.slots[node as usize] = UNSET;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<'a> Darner<'a> {
+    pub fn new(
+        tour: Solution,
+        verts: &'a Verts,
+        vi_map: &'a VIMap,
+        objective: Objective,
+        seed: u64,
+    ) -> Darner<'a> {
+        let order = tour.len();
+        let mut links = vec![[0, 0]; order];
+        tour.iter().enumerate().for_each(|(idx, &node)| {
+            links[node as usize] = [tour[(idx + order - 1) % order], tour[(idx + 1) % order]];
+        });
+        let mut darner = Darner {
+            skein: Skein::new(&tour, seed),
+            verts,
+            lattice: lattice(verts, vi_map),
+            links,
+            counts: axis_counts(&tour, verts),
+            frayed: Frayed::new(order),
+            objective,
+            rng: StdRng::seed_from_u64(seed),
+        };
+        (0..order as Node).for_each(|node| darner.frayed.set(node, darner.is_frayed(node)));
+        darner
+    }
+
+    pub fn darn(&mut self, target: i64, max_iters: usize) -> i64 {
+        let mut cost = self.cost();
+        let (mut best, mut stall, mut temperature) = (cost, 0, 1.0_f64);
+        for _ in 0..max_iters {
+            if cost <= target {
+                break;
+            }
+            let Some(patches) = self.propose(RADIUS + stall / STALL) else {
+                stall += 1;
+                continue;
+            };
+            patches.iter().for_each(|&patch| self.flip(patch));
+            let touched = self.touched(&patches);
+            patches.iter().rev().for_each(|&patch| self.unflip(patch));
+            let before = self.local_cost(&touched) + self.global_cost();
+            patches.iter().for_each(|&patch| self.flip(patch));
+            let delta = self.local_cost(&touched) + self.global_cost() - before;
+            if delta > 0 && self.rng.gen::<f64>() >= (-(delta as f64) / temperature).exp() {
+                patches.iter().rev().for_each(|&patch| self.unflip(patch));
+                stall += 1;
+                continue;
+            }
+            cost += delta;
+            match patches[..] {
+                [patch] => self.skein.flip(patch),
+                [split, join] => self.skein.splice(split, join),
+                _ => unreachable!(),
+            }
+            touched
+                .iter()
+                .for_each(|&node| self.frayed.set(node, self.is_frayed(node)));
+            temperature = (temperature * COOLING).max(FLOOR);
+            match cost < best {
+                true => (best, stall) = (cost, 0),
+                false => stall += 1,
+            }
+        }
+        cost
+    }
+
+    /// Takes the given number of darns at random, whatever they do to the tour.
+    pub fn wander(&mut self, steps: usize) {
+        self.darn(-1, steps);
+    }
+
+    pub fn cost(&self) -> i64 {
+        let nodes: Vec<Node> = (0..self.links.len() as Node).collect();
+        self.local_cost(&nodes) + self.global_cost()
+    }
+
+    pub fn tour(&mut self) -> Tour {
+        self.skein.to_vec()
+    }
+
+    pub fn is_straight(&self, node: Node) -> bool {
+        let [prev, next] = self.links[node as usize];
+        let vert = &self.verts[node as usize];
+        axis(&self.verts[prev as usize], vert) == axis(vert, &self.verts[next as usize])
+    }
+
+    /// How far the edge counts are from an even third each, once their spread exceeds the tolerance.
+    fn global_cost(&self) -> i64 {
+        match self.objective {
+            Objective::Turns | Objective::Runs | Objective::MaxRun(_) | Objective::Wander => 0,
+            Objective::Balance(tolerance) if axis_spread(&self.counts) <= tolerance => 0,
+            Objective::Balance(_) => {
+                let order = self.links.len() as i64;
+                self.counts
+                    .iter()
+                    .map(|&count| (3 * count as i64 - order).abs())
+                    .sum()
+            }
+        }
+    }
+
+    /// The cost carried by the touched nodes, or for the run objectives by every run through them: a
+    /// flip only relinks nodes it touches, so any run it lengthens, shortens or cuts passes through one.
+    fn local_cost(&self, touched: &[Node]) -> i64 {
+        let overshoot = |len: usize| match self.objective {
+            Objective::Runs => (len > 1) as i64,
+            Objective::MaxRun(limit) => len.saturating_sub(limit) as i64,
+            _ => 0,
+        };
+        match self.objective {
+            Objective::Turns => touched
+                .iter()
+                .map(|&node| self.is_straight(node) as i64)
+                .sum(),
+            Objective::Balance(_) | Objective::Wander => 0,
+            Objective::Runs | Objective::MaxRun(_) => {
+                let mut runs: Vec<(Node, Node, usize)> = touched
+                    .iter()
+                    .flat_map(|&node| self.links[node as usize].map(|next| self.run(node, next)))
+                    .collect();
+                runs.sort_unstable();
+                runs.dedup();
+                runs.into_iter().map(|(_, _, len)| overshoot(len)).sum()
+            }
+        }
+    }
+
+    /// The straight run through the edge (a, b): its two ends, lowest first, and its length in edges.
+    fn run(&self, a: Node, b: Node) -> (Node, Node, usize) {
+        let (ahead, back) = (self.stretch(a, b), self.stretch(b, a));
+        let (start, end) = (back.0.min(ahead.0), back.0.max(ahead.0));
+        (start, end, 1 + ahead.1 + back.1)
+    }
+
+    /// Follows the tour from a through b for as long as it keeps to the axis of (a, b).
+    fn stretch(&self, a: Node, b: Node) -> (Node, usize) {
+        let line = axis(&self.verts[a as usize], &self.verts[b as usize]);
+        let (mut prev, mut node, mut len) = (a, b, 0);
+        loop {
+            let [left, right] = self.links[node as usize];
+            let next = if left == prev { right } else { left };
+            if axis(&self.verts[node as usize], &self.verts[next as usize]) != line {
+                return (node, len);
+            }
+            (prev, node, len) = (node, next, len + 1);
+        }
+    }
+
+    /// Where darns start from: the straight nodes when turning or shortening runs. Balancing has no such
+    /// spots, a short axis can only be made up anywhere on the tour, and wandering goes anywhere anyway.
+    fn is_frayed(&self, node: Node) -> bool {
+        match self.objective {
+            Objective::Turns | Objective::Runs | Objective::MaxRun(_) => self.is_straight(node),
+            Objective::Balance(_) | Objective::Wander => false,
+        }
+    }
+
+    /// The nodes together with their neighbours in the lattice.
+    fn around(&self, nodes: &[Node]) -> Vec<Node> {
+        let mut around: Vec<Node> = nodes
+            .iter()
+            .flat_map(|&node| self.lattice[node as usize].into_iter().chain([node]))
+            .filter(|&node| node != NONE)
+            .collect();
+        around.sort_unstable();
+        around.dedup();
+        around
+    }
+
+    fn touched(&self, patches: &[Patch]) -> Vec<Node> {
+        let mut touched: Vec<Node> = patches
+            .iter()
+            .flat_map(|&(a, b, c, d)| [a, b, c, d])
+            .flat_map(|node| {
+                let [prev, next] = self.links[node as usize];
+                [node, prev, next]
+            })
+            .collect();
+        touched.sort_unstable();
+        touched.dedup();
+        touched
+    }
+
+    fn propose(&mut self, radius: usize) -> Option<Vec<Patch>> {
+        let (mut node, radius) = match self.frayed.len() {
+            0 => (self.rng.gen_range(0..self.links.len()) as Node, 0),
+            len => (self.frayed.get(self.rng.gen_range(0..len)), radius),
+        };
+        for _ in 0..self.rng.gen_range(0..=radius) {
+            let (axis, step) = (
+                self.rng.gen_range(0..3),
+                if self.rng.gen() { 2 } else { -2 },
+            );
+            node = self.neighbor(node, axis, step).unwrap_or(node);
+        }
+        let patches: Vec<Patch> = self.patches(node).collect();
+        if patches.is_empty() {
+            return None;
+        }
+        let (a, b, c, d) = patches[self.rng.gen_range(0..patches.len())];
+        let (a, b, c, d) = match self.is_forward(a, b) {
+            true => (a, b, c, d),
+            false => (b, a, d, c),
+        };
+        if self.is_forward(c, d) {
+            return Some(vec![(a, b, c, d)]);
+        }
+        // a -> b .. d -> c: flipping splits the run b..=d off the tour, a second flip joins it back.
+        let order = self.links.len();
+        let (start, end) = (self.skein.position(b), self.skein.position(d));
+        let span = (end + order - start) % order;
+        let before = self.global_cost();
+        self.flip((a, b, c, d));
+        let candidates: Vec<Patch> = self
+            .around(&self.touched(&[(a, b, c, d)]))
+            .into_iter()
+            .flat_map(|node| self.patches(node))
+            .collect();
+        let mut seen: Vec<(Node, bool)> = Vec::new();
+        let mut joins: Vec<Patch> = Vec::new();
+        for join in candidates {
+            self.flip(join);
+            let after = self.global_cost();
+            self.unflip(join);
+            if after > before {
+                continue;
+            }
+            let (m, _, o, _) = join;
+            if self.side(m, &mut seen, (start, span)) != self.side(o, &mut seen, (start, span)) {
+                joins.push(join);
+            }
+        }
+        self.unflip((a, b, c, d));
+        match joins.is_empty() {
+            true => None,
+            false => Some(vec![
+                (a, b, c, d),
+                joins[self.rng.gen_range(0..joins.len())],
+            ]),
+        }
+    }
+
+    /// Which of the two loops a split left behind holds the node: whether it lay on the run of `span`
+    /// steps from `start` before the split.
+    fn side(
+        &mut self,
+        node: Node,
+        seen: &mut Vec<(Node, bool)>,
+        (start, span): (usize, usize),
+    ) -> bool {
+        if let Some(&(_, side)) = seen.iter().find(|(seen, _)| *seen == node) {
+            return side;
+        }
+        let order = self.links.len();
+        let side = (self.skein.position(node) + order - start) % order <= span;
+        seen.push((node, side));
+        side
+    }
+
+    fn patches(&self, a: Node) -> impl Iterator<Item = Patch> + '_ {
+        self.links[a as usize].into_iter().flat_map(move |b| {
+            let edge_axis = axis(&self.verts[a as usize], &self.verts[b as usize]);
+            (0..3)
+                .filter(move |&rung| rung != edge_axis)
+                .flat_map(|rung| [(rung, 2), (rung, -2)])
+                .filter_map(move |(rung, step)| {
+                    match (self.neighbor(a, rung, step), self.neighbor(b, rung, step)) {
+                        (Some(c), Some(d)) if self.links[c as usize].contains(&d) => {
+                            Some((a, b, c, d))
+                        }
+                        _ => None,
+                    }
+                })
+        })
+    }
+
+    fn neighbor(&self, node: Node, axis: usize, step: i16) -> Option<Node> {
+        match self.lattice[node as usize][axis * 2 + (step < 0) as usize] {
+            NONE => None,
+            node => Some(node),
+        }
+    }
+
+    fn is_forward(&mut self, a: Node, b: Node) -> bool {
+        self.skein.is_forward(a, b)
+    }
+
+    fn flip(&mut self, (a, b, c, d): Patch) {
+        self.counts[axis(&self.verts[a as usize], &self.verts[b as usize])] -= 2;
+        self.counts[axis(&self.verts[a as usize], &self.verts[c as usize])] += 2;
+        self.relink(a, b, c);
+        self.relink(b, a, d);
+        self.relink(c, d, a);
+        self.relink(d, c, b);
+    }
+
+    fn unflip(&mut self, (a, b, c, d): Patch) {
+        self.flip((a, c, b, d))
+    }
+
+    fn relink(&mut self, node: Node, old: Node, new: Node) {
+        let links = &mut self.links[node as usize];
+        links[(links[1] == old) as usize] = new;
+    }
+}
+
+/// The lattice neighbours of every node, two steps up and down each axis, NONE off the graph.
+fn lattice(verts: &Verts, vi_map: &VIMap) -> Vec<[Node; 6]> {
+    verts
+        .iter()
+        .map(|&(x, y, z)| {
+            let steps: [Vert; 6] = [
+                (x + 2, y, z),
+                (x - 2, y, z),
+                (x, y + 2, z),
+                (x, y - 2, z),
+                (x, y, z + 2),
+                (x, y, z - 2),
+            ];
+            steps.map(|vert| vi_map.get(&vert).copied().unwrap_or(NONE))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Darner, Objective};
+    use crate::graph::{
+        utils::{
+            certify::{id_seq, SequenceID},
+            make::make_graph,
+        },
+        weave::weave,
+    };
+
+    #[test]
+    fn leaves_a_hamiltonian_cycle_after_every_wander() {
+        let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(6);
+        let woven = weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz);
+        let mut darner = Darner::new(woven.clone(), &verts, &vi_map, Objective::Wander, 7);
+        for _ in 0..20 {
+            darner.wander(50);
+            assert_eq!(id_seq(&darner.tour(), &adj), SequenceID::HamCycle);
+        }
+        assert_ne!(darner.tour(), woven);
+    }
+
+    #[test]
+    fn costs_the_straight_nodes_when_turning() {
+        let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(5);
+        let woven = weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz);
+        let mut darner = Darner::new(woven, &verts, &vi_map, Objective::Turns, 0);
+        let straight = (0..verts.len() as u32)
+            .filter(|&node| darner.is_straight(node))
+            .count();
+        assert!(straight > 0);
+        assert_eq!(darner.cost(), straight as i64);
+        assert_eq!(darner.darn(0, 1_000_000), 0);
+        assert_eq!(darner.cost(), 0);
+    }
+}
@@ -35,9 +35,22 @@ pub type Yarn = Array2<Point>;
 pub type ZlevelNodesMap = HashMap<Point, Nodes>;
 pub type ZOrder = Vec<(Point, usize)>;
 
+/// A graph as the crate makes it, the coordinates of every vertex by its node and the neighbours of
+/// every node, to hand to other crates and take back from them.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GridGraph {
+    pub verts: VecVert,
+    pub adj: Adjacency,
+}
+
+/// A thread of the cycle being woven. It is serialized without the vertices it borrows, and read
+/// back as a `Hank`, which `Hank::rethread` winds onto them again.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Weaver<'a> {
     pub data: Tour,
+    #[cfg_attr(feature = "serde", serde(skip))]
     verts: &'a Verts,
     lead: bool,
     min_xyz: Point,
@@ -146,3 +159,44 @@ impl<'a> Weaver<'a> {
             .collect()
     }
 }
+
+/// A weaver wound off the vertices it borrows, as it is serialized and read back.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hank {
+    pub data: Tour,
+    lead: bool,
+    min_xyz: Point,
+    order: u32,
+}
+
+impl Hank {
+    /// The weaver again, on vertices as many as those it was woven on, every node of its thread
+    /// among them.
+    pub fn rethread(self, verts: &Verts) -> Result<Weaver<'_>, &'static str> {
+        if verts.len() != self.order as usize {
+            return Err("the weaver was woven on another number of vertices");
+        }
+        if self.data.iter().any(|&node| node >= self.order) {
+            return Err("the weaver's thread runs past its vertices");
+        }
+        Ok(Weaver {
+            data: self.data,
+            verts,
+            lead: self.lead,
+            min_xyz: self.min_xyz,
+            order: self.order,
+        })
+    }
+}
+
+impl From<&Weaver<'_>> for Hank {
+    fn from(weaver: &Weaver) -> Self {
+        Hank {
+            data: weaver.data.clone(),
+            lead: weaver.lead,
+            min_xyz: weaver.min_xyz,
+            order: weaver.order,
+        }
+    }
+}
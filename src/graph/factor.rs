@@ -0,0 +1,445 @@
+use std::collections::VecDeque;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+pub const UNSET: usize = usize::MAX;
+/// How many times a stuck 2-factor is shaken before giving up on merging.
+const SHAKES: usize = 2000;
+
+/// Two links for every node of a bipartite graph, found as a flow of two units from every black node
+/// to two white ones. When there is none, the nodes the flow reaches from the source, black ones it
+/// could not get out of and the white ones they fill up, are returned instead.
+pub fn two_factor(adj: &[Vec<usize>], black: &[bool]) -> Result<Vec<[usize; 2]>, Vec<usize>> {
+    let mut links = vec![[UNSET; 2]; adj.len()];
+    complete(adj, black, &mut links)?;
+    Ok(links)
+}
+
+/// Fills the empty link slots of a partial 2-factor by the same flow. The links already laid are
+/// taken as flow under way, so that the shortest augmenting paths swap only the few of them between
+/// the nodes short of links. When the slots cannot all be filled, the nodes the flow reaches from the
+/// source are returned and the links are left untouched.
+pub fn complete(
+    adj: &[Vec<usize>],
+    black: &[bool],
+    links: &mut [[usize; 2]],
+) -> Result<(), Vec<usize>> {
+    let order = adj.len();
+    let (source, sink) = (order, order + 1);
+    let mut flow = Flow::new(order + 2);
+    let (mut supply, mut demand) = (0, 0);
+    for i in 0..order {
+        let held = links[i].iter().filter(|&&j| j != UNSET).count();
+        match black[i] {
+            true => {
+                flow.add(source, i, 2, held);
+                supply += 2 - held;
+                adj[i]
+                    .iter()
+                    .for_each(|&j| flow.add(i, j, 1, links[i].contains(&j) as usize));
+            }
+            false => {
+                flow.add(i, sink, 2, held);
+                demand += 2 - held;
+            }
+        }
+    }
+    if flow.max_flow(source, sink) < supply || supply != demand {
+        let reached = flow.reached(source);
+        return Err((0..order).filter(|&i| reached[i]).collect());
+    }
+    let mut laid = vec![[UNSET; 2]; order];
+    let mut link = |i: usize, j: usize| {
+        let slot = (laid[i][0] != UNSET) as usize;
+        laid[i][slot] = j;
+    };
+    for (from, to) in flow.saturated(order) {
+        link(from, to);
+        link(to, from);
+    }
+    links.copy_from_slice(&laid);
+    Ok(())
+}
+
+/// Merges the cycles of a 2-factor by flipping unit squares, each given by its corners [a, b, c, d]
+/// with a next to b and c and d next to both, that hold an edge of two different cycles on opposite
+/// sides, for as long as there are any. When it gets stuck with cycles left, the 2-factor is shaken: a
+/// square with two edges of one cycle on opposite sides is flipped, which keeps every node on a cycle,
+/// and merging starts over, the shake kept unless it leaves more cycles than before. Returns the
+/// nodes in the order of the cycle if one is left, or else how many there are.
+pub fn merge(links: &mut [[usize; 2]], squares: &[[usize; 4]]) -> Result<Vec<usize>, usize> {
+    let order = links.len();
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut cycles = knot(links, squares);
+    for _ in 0..SHAKES {
+        if cycles == 1 || squares.is_empty() {
+            break;
+        }
+        let Some(square) = pair(links, squares[rng.gen_range(0..squares.len())]) else {
+            continue;
+        };
+        let before = links.to_vec();
+        flip(links, square);
+        match knot(links, squares) {
+            after if after <= cycles => cycles = after,
+            _ => links.copy_from_slice(&before),
+        }
+    }
+    if cycles > 1 {
+        return Err(cycles);
+    }
+    let mut cycle = Vec::with_capacity(order);
+    cycle.push(0);
+    let (mut prev, mut node) = (0, links[0][0]);
+    while node != 0 {
+        cycle.push(node);
+        (prev, node) = (node, next(links, node, prev));
+    }
+    Ok(cycle)
+}
+
+/// Merges cycles of different roots across unit squares until no square is left to merge by, and
+/// returns how many cycles are left.
+pub fn knot(links: &mut [[usize; 2]], squares: &[[usize; 4]]) -> usize {
+    let order = links.len();
+    let mut roots: Vec<usize> = vec![UNSET; order];
+    let mut cycles = 0;
+    for i in 0..order {
+        if roots[i] != UNSET {
+            continue;
+        }
+        roots[i] = i;
+        cycles += 1;
+        let (mut prev, mut node) = (i, links[i][0]);
+        while node != i {
+            roots[node] = i;
+            (prev, node) = (node, next(links, node, prev));
+        }
+    }
+    let mut merged = true;
+    while merged {
+        merged = false;
+        for &square in squares {
+            let Some([p, q, r, s]) = pair(links, square) else {
+                continue;
+            };
+            if find(&mut roots, p) != find(&mut roots, r) {
+                flip(links, [p, q, r, s]);
+                let (root_p, root_r) = (find(&mut roots, p), find(&mut roots, r));
+                roots[root_p] = root_r;
+                cycles -= 1;
+                merged = true;
+            }
+        }
+    }
+    cycles
+}
+
+/// The square as edges (p, q) and (r, s) of the 2-factor on opposite sides of it, if it has such a
+/// pair and neither of the other sides is an edge already, which flipping would double.
+fn pair(links: &[[usize; 2]], [a, b, c, d]: [usize; 4]) -> Option<[usize; 4]> {
+    let linked = |m: usize, n: usize| links[m].contains(&n);
+    [[a, b, c, d], [a, c, b, d]]
+        .into_iter()
+        .find(|&[p, q, r, s]| linked(p, q) && linked(r, s) && !linked(p, r) && !linked(q, s))
+}
+
+/// A cut vertex of a connected graph, found by a depth first search keeping the lowest discovery time
+/// each subtree reaches back to.
+pub fn cut_vertex(adj: &[Vec<usize>]) -> Option<usize> {
+    let order = adj.len();
+    let (mut found, mut low) = (vec![UNSET; order], vec![0; order]);
+    let mut stack: Vec<(usize, usize, usize)> = vec![(0, UNSET, 0)];
+    let (mut time, mut root_children) = (0, 0);
+    found[0] = 0;
+    while let Some(&mut (i, parent, ref mut next)) = stack.last_mut() {
+        if let Some(&j) = adj[i].get(*next) {
+            *next += 1;
+            if found[j] == UNSET {
+                time += 1;
+                (found[j], low[j]) = (time, time);
+                stack.push((j, i, 0));
+            } else if j != parent {
+                low[i] = low[i].min(found[j]);
+            }
+            continue;
+        }
+        stack.pop();
+        if parent == UNSET {
+            continue;
+        }
+        low[parent] = low[parent].min(low[i]);
+        match parent {
+            0 => root_children += 1,
+            _ if low[i] >= found[parent] => return Some(parent),
+            _ => {}
+        }
+    }
+    (root_children > 1).then_some(0)
+}
+
+/// Nodes reached from `from` without passing `avoid`.
+pub fn reach(adj: &[Vec<usize>], from: usize, avoid: usize) -> Vec<bool> {
+    let mut seen = vec![false; adj.len()];
+    seen[from] = true;
+    let mut queue = VecDeque::from([from]);
+    while let Some(i) = queue.pop_front() {
+        for &j in &adj[i] {
+            if !seen[j] && j != avoid {
+                seen[j] = true;
+                queue.push_back(j);
+            }
+        }
+    }
+    seen
+}
+
+/// A flow network with unit and double capacities, maxed out by Dinic's blocking flows.
+struct Flow {
+    heads: Vec<Vec<usize>>,
+    to: Vec<usize>,
+    room: Vec<usize>,
+}
+
+impl Flow {
+    fn new(order: usize) -> Flow {
+        Flow {
+            heads: vec![Vec::new(); order],
+            to: Vec::new(),
+            room: Vec::new(),
+        }
+    }
+
+    /// Adds an edge of capacity `room` already carrying `held` of it.
+    fn add(&mut self, from: usize, to: usize, room: usize, held: usize) {
+        self.heads[from].push(self.to.len());
+        self.to.push(to);
+        self.room.push(room - held);
+        self.heads[to].push(self.to.len());
+        self.to.push(from);
+        self.room.push(held);
+    }
+
+    fn levels(&self, source: usize) -> Vec<usize> {
+        let mut level = vec![UNSET; self.heads.len()];
+        level[source] = 0;
+        let mut queue = VecDeque::from([source]);
+        while let Some(node) = queue.pop_front() {
+            for &edge in &self.heads[node] {
+                let to = self.to[edge];
+                if self.room[edge] > 0 && level[to] == UNSET {
+                    level[to] = level[node] + 1;
+                    queue.push_back(to);
+                }
+            }
+        }
+        level
+    }
+
+    fn max_flow(&mut self, source: usize, sink: usize) -> usize {
+        let mut total = 0;
+        loop {
+            let level = self.levels(source);
+            if level[sink] == UNSET {
+                return total;
+            }
+            let mut next = vec![0; self.heads.len()];
+            loop {
+                let pushed = self.augment(source, sink, &level, &mut next);
+                if pushed == 0 {
+                    break;
+                }
+                total += pushed;
+            }
+        }
+    }
+
+    /// Pushes one unit along a shortest path with room left, or nothing when there is none.
+    fn augment(
+        &mut self,
+        source: usize,
+        sink: usize,
+        level: &[usize],
+        next: &mut [usize],
+    ) -> usize {
+        let mut path: Vec<usize> = Vec::new();
+        let mut node = source;
+        while node != sink {
+            match self.heads[node].get(next[node]) {
+                Some(&edge) => {
+                    let to = self.to[edge];
+                    if self.room[edge] > 0 && level[to] == level[node] + 1 {
+                        path.push(edge);
+                        node = to;
+                    } else {
+                        next[node] += 1;
+                    }
+                }
+                None if node == source => return 0,
+                None => {
+                    let edge = path.pop().unwrap();
+                    node = self.to[edge ^ 1];
+                    next[node] += 1;
+                }
+            }
+        }
+        path.into_iter().for_each(|edge| {
+            self.room[edge] -= 1;
+            self.room[edge ^ 1] += 1;
+        });
+        1
+    }
+
+    fn reached(&self, source: usize) -> Vec<bool> {
+        self.levels(source)
+            .into_iter()
+            .map(|level| level != UNSET)
+            .collect()
+    }
+
+    /// The edges between cells, below `order`, that carry flow.
+    fn saturated(&self, order: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.to.len()).step_by(2).filter_map(move |edge| {
+            let (from, to) = (self.to[edge ^ 1], self.to[edge]);
+            (from < order && to < order && self.room[edge] == 0).then_some((from, to))
+        })
+    }
+}
+
+fn next(links: &[[usize; 2]], node: usize, prev: usize) -> usize {
+    match links[node][0] == prev {
+        true => links[node][1],
+        false => links[node][0],
+    }
+}
+
+/// Flips a unit square: the edges (p, q) and (r, s) on opposite sides become (p, r) and (q, s).
+fn flip(links: &mut [[usize; 2]], [p, q, r, s]: [usize; 4]) {
+    relink(links, p, q, r);
+    relink(links, q, p, s);
+    relink(links, r, s, p);
+    relink(links, s, r, q);
+}
+
+fn relink(links: &mut [[usize; 2]], node: usize, old: usize, new: usize) {
+    let slot = (links[node][1] == old) as usize;
+    links[node][slot] = new;
+}
+
+fn find(roots: &mut [usize], node: usize) -> usize {
+    let mut root = node;
+    while roots[root] != root {
+        root = roots[root];
+    }
+    let mut node = node;
+    while roots[node] != root {
+        (roots[node], node) = (root, roots[node]);
+    }
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{complete, cut_vertex, merge, pair, reach, two_factor, UNSET};
+
+    /// A ladder two nodes high, 0 1 2 3 over 4 5 6 7, covered by the unit squares at either end.
+    fn two_squares() -> Vec<[usize; 2]> {
+        vec![
+            [1, 4],
+            [0, 5],
+            [3, 6],
+            [2, 7],
+            [5, 0],
+            [1, 4],
+            [7, 2],
+            [3, 6],
+        ]
+    }
+
+    /// Whether every node has two different links and is linked back by both.
+    fn is_simple(links: &[[usize; 2]]) -> bool {
+        links
+            .iter()
+            .enumerate()
+            .all(|(node, &[m, n])| m != n && links[m].contains(&node) && links[n].contains(&node))
+    }
+
+    #[test]
+    fn pairs_no_square_that_is_a_cycle_of_its_own() {
+        let links = two_squares();
+        assert_eq!(pair(&links, [0, 1, 4, 5]), None);
+        assert_eq!(pair(&links, [2, 3, 6, 7]), None);
+        assert_eq!(pair(&links, [1, 2, 5, 6]), Some([1, 5, 2, 6]));
+    }
+
+    #[test]
+    fn shakes_without_doubling_an_edge() {
+        let mut links = two_squares();
+        assert_eq!(merge(&mut links, &[[0, 1, 4, 5], [2, 3, 6, 7]]), Err(2));
+        assert!(is_simple(&links));
+        assert_eq!(links, two_squares());
+    }
+
+    #[test]
+    fn merges_the_squares_across_the_middle() {
+        let mut links = two_squares();
+        let squares = [[0, 1, 4, 5], [1, 2, 5, 6], [2, 3, 6, 7]];
+        let cycle = merge(&mut links, &squares).unwrap();
+        assert!(is_simple(&links));
+        assert_eq!(cycle.len(), 8);
+    }
+
+    #[test]
+    fn lays_a_two_factor_by_flow() {
+        // the ladder again, black where row and column add up even.
+        let adj = vec![
+            vec![1, 4],
+            vec![0, 2, 5],
+            vec![1, 3, 6],
+            vec![2, 7],
+            vec![0, 5],
+            vec![1, 4, 6],
+            vec![2, 5, 7],
+            vec![3, 6],
+        ];
+        let black = [true, false, true, false, false, true, false, true];
+        let links = two_factor(&adj, &black).unwrap();
+        assert!(is_simple(&links));
+        assert!((0..8).all(|node| links[node].iter().all(|other| adj[node].contains(other))));
+    }
+
+    #[test]
+    fn returns_what_the_flow_reached_when_there_is_no_two_factor() {
+        // black 0 and 1 both hang off white 3 alone, while 2 has the other whites to itself.
+        let adj = vec![vec![3], vec![3], vec![4, 5], vec![0, 1], vec![2], vec![2]];
+        let black = [true, true, true, false, false, false];
+        assert_eq!(two_factor(&adj, &black), Err(vec![0, 1]));
+    }
+
+    #[test]
+    fn completes_a_partial_two_factor_keeping_what_it_can() {
+        let adj = vec![vec![1, 3], vec![0, 2], vec![1, 3], vec![2, 0]];
+        let black = [true, false, true, false];
+        let mut links = vec![[1, UNSET], [0, UNSET], [UNSET; 2], [UNSET; 2]];
+        complete(&adj, &black, &mut links).unwrap();
+        assert!(is_simple(&links));
+        assert!(links[0].contains(&1));
+    }
+
+    #[test]
+    fn finds_cut_vertices_and_what_they_cut_off() {
+        let path = vec![vec![1], vec![0, 2], vec![1]];
+        assert_eq!(cut_vertex(&path), Some(1));
+        assert_eq!(reach(&path, 0, 1), vec![true, false, false]);
+        let ring = vec![vec![1, 3], vec![0, 2], vec![1, 3], vec![2, 0]];
+        assert_eq!(cut_vertex(&ring), None);
+        let bow = vec![
+            vec![1, 2],
+            vec![0, 2],
+            vec![0, 1, 3, 4],
+            vec![2, 4],
+            vec![2, 3],
+        ];
+        assert_eq!(cut_vertex(&bow), Some(2));
+    }
+}
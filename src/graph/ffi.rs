@@ -0,0 +1,416 @@
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    slice,
+};
+
+use super::{
+    defs::{Adjacency, Point, Solution, VIMap, VecVert, Vert, ZAdjacency, ZOrder},
+    utils::{
+        certify::{id_seq, SequenceID},
+        make::make_graph,
+    },
+    weave::weave,
+};
+
+/// What every function of the C interface comes to, as `hamcycle.h` numbers it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Ok = 0,
+    /// A handle or buffer passed was null.
+    Null = 1,
+    /// The discocubes start at level 1.
+    BadLevel = 2,
+    /// The buffer passed is too short to hold what was asked for.
+    ShortBuffer = 3,
+    /// The tour visits a node the graph does not have.
+    OutOfGraph = 4,
+    /// The solver panicked; the panic was caught before it crossed into C.
+    Panic = 5,
+}
+
+/// The discocube of a level and all the weaver needs of it, behind an opaque handle.
+pub struct HamGraph {
+    verts: VecVert,
+    vi_map: VIMap,
+    adj: Adjacency,
+    z_adj: ZAdjacency,
+    z_order: ZOrder,
+    min_xyz: Point,
+}
+
+/// A cycle solved on a graph, behind an opaque handle.
+pub struct HamTour(Solution);
+
+/// Runs the body, turning a panic into a status so that it never unwinds into C.
+fn guard(body: impl FnOnce() -> Status) -> Status {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or(Status::Panic)
+}
+
+/// Makes the discocube of the level given and hands it to `*graph`, to be freed with
+/// `hamcycle_graph_free`.
+///
+/// # Safety
+/// `graph` must be null or valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn hamcycle_graph_new(level: u32, graph: *mut *mut HamGraph) -> Status {
+    guard(|| {
+        if graph.is_null() {
+            return Status::Null;
+        }
+        if level == 0 {
+            return Status::BadLevel;
+        }
+        let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(level);
+        let made = HamGraph {
+            verts,
+            vi_map,
+            adj,
+            z_adj,
+            z_order,
+            min_xyz,
+        };
+        *graph = Box::into_raw(Box::new(made));
+        Status::Ok
+    })
+}
+
+/// Frees a graph made by `hamcycle_graph_new`. Null is ignored.
+///
+/// # Safety
+/// `graph` must be null or a handle from `hamcycle_graph_new` not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn hamcycle_graph_free(graph: *mut HamGraph) {
+    if !graph.is_null() {
+        drop(Box::from_raw(graph));
+    }
+}
+
+/// The number of vertices of the graph, 0 for null.
+///
+/// # Safety
+/// `graph` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn hamcycle_graph_order(graph: *const HamGraph) -> usize {
+    graph.as_ref().map_or(0, |graph| graph.verts.len())
+}
+
+/// Copies the coordinates of the graph's vertices, x, y and z of each in node order, into the
+/// buffer, which holds three times the graph's order at least.
+///
+/// # Safety
+/// `graph` must be null or a live handle, `buffer` null or valid for `len` writes.
+#[no_mangle]
+pub unsafe extern "C" fn hamcycle_graph_coordinates(
+    graph: *const HamGraph,
+    buffer: *mut i16,
+    len: usize,
+) -> Status {
+    guard(|| {
+        let Some(graph) = graph.as_ref() else {
+            return Status::Null;
+        };
+        write_coordinates(graph.verts.iter().copied(), graph.verts.len(), buffer, len)
+    })
+}
+
+/// Weaves a hamiltonian cycle on the graph and hands it to `*tour`, to be freed with
+/// `hamcycle_tour_free`.
+///
+/// # Safety
+/// `graph` must be null or a live handle, `tour` null or valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn hamcycle_solve(graph: *const HamGraph, tour: *mut *mut HamTour) -> Status {
+    guard(|| {
+        let (Some(graph), false) = (graph.as_ref(), tour.is_null()) else {
+            return Status::Null;
+        };
+        let solution = weave(
+            &graph.adj,
+            &graph.vi_map,
+            &graph.verts,
+            graph.z_adj.clone(),
+            graph.z_order.clone(),
+            graph.min_xyz,
+        );
+        *tour = Box::into_raw(Box::new(HamTour(solution)));
+        Status::Ok
+    })
+}
+
+/// Frees a tour solved by `hamcycle_solve`. Null is ignored.
+///
+/// # Safety
+/// `tour` must be null or a handle from `hamcycle_solve` not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn hamcycle_tour_free(tour: *mut HamTour) {
+    if !tour.is_null() {
+        drop(Box::from_raw(tour));
+    }
+}
+
+/// The number of nodes of the tour, 0 for null.
+///
+/// # Safety
+/// `tour` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn hamcycle_tour_len(tour: *const HamTour) -> usize {
+    tour.as_ref().map_or(0, |tour| tour.0.len())
+}
+
+/// Copies the nodes of the tour in order into the buffer, which holds the tour's length at least.
+///
+/// # Safety
+/// `tour` must be null or a live handle, `buffer` null or valid for `len` writes.
+#[no_mangle]
+pub unsafe extern "C" fn hamcycle_tour_nodes(
+    tour: *const HamTour,
+    buffer: *mut u32,
+    len: usize,
+) -> Status {
+    guard(|| {
+        let (Some(tour), false) = (tour.as_ref(), buffer.is_null()) else {
+            return Status::Null;
+        };
+        if len < tour.0.len() {
+            return Status::ShortBuffer;
+        }
+        slice::from_raw_parts_mut(buffer, tour.0.len()).copy_from_slice(&tour.0);
+        Status::Ok
+    })
+}
+
+/// Copies the coordinates of the graph's vertices in the order the tour visits them, x, y and z of
+/// each, into the buffer, which holds three times the tour's length at least.
+///
+/// # Safety
+/// `graph` and `tour` must be null or live handles, `buffer` null or valid for `len` writes.
+#[no_mangle]
+pub unsafe extern "C" fn hamcycle_tour_coordinates(
+    graph: *const HamGraph,
+    tour: *const HamTour,
+    buffer: *mut i16,
+    len: usize,
+) -> Status {
+    guard(|| {
+        let (Some(graph), Some(tour)) = (graph.as_ref(), tour.as_ref()) else {
+            return Status::Null;
+        };
+        if tour
+            .0
+            .iter()
+            .any(|&node| node as usize >= graph.verts.len())
+        {
+            return Status::OutOfGraph;
+        }
+        let verts = tour.0.iter().map(|&node| graph.verts[node as usize]);
+        write_coordinates(verts, tour.0.len(), buffer, len)
+    })
+}
+
+/// Certifies the tour of `len` nodes against the graph, writing to `*verdict` 2 for a hamiltonian
+/// cycle, 1 for a hamiltonian path and 0 for anything else.
+///
+/// # Safety
+/// `graph` must be null or a live handle, `tour` null or valid for `len` reads and `verdict` null or
+/// valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn hamcycle_certify(
+    graph: *const HamGraph,
+    tour: *const u32,
+    len: usize,
+    verdict: *mut i32,
+) -> Status {
+    guard(|| {
+        let (Some(graph), false, false) = (graph.as_ref(), tour.is_null(), verdict.is_null())
+        else {
+            return Status::Null;
+        };
+        let tour: Solution = slice::from_raw_parts(tour, len).to_vec();
+        if tour.iter().any(|node| !graph.adj.contains_key(node)) {
+            return Status::OutOfGraph;
+        }
+        *verdict = match id_seq(&tour, &graph.adj) {
+            SequenceID::Broken => 0,
+            SequenceID::HamChain => 1,
+            SequenceID::HamCycle => 2,
+        };
+        Status::Ok
+    })
+}
+
+/// Writes the coordinates given, `count` of them, flat into the buffer of `len`.
+unsafe fn write_coordinates(
+    verts: impl Iterator<Item = Vert>,
+    count: usize,
+    buffer: *mut i16,
+    len: usize,
+) -> Status {
+    if buffer.is_null() {
+        return Status::Null;
+    }
+    if len < count * 3 {
+        return Status::ShortBuffer;
+    }
+    let buffer = slice::from_raw_parts_mut(buffer, count * 3);
+    for (chunk, (x, y, z)) in buffer.chunks_exact_mut(3).zip(verts) {
+        chunk.copy_from_slice(&[x, y, z]);
+    }
+    Status::Ok
+}
+
+/// Reserves a buffer of the bytes given in the module's memory, for JavaScript to pass to the
+/// functions above, null when there is no room.
+#[cfg(target_arch = "wasm32")]
+#[no_mangle]
+pub extern "C" fn hamcycle_alloc(bytes: usize) -> *mut u8 {
+    std::alloc::Layout::from_size_align(bytes.max(1), 8)
+        .map_or(std::ptr::null_mut(), |layout| unsafe {
+            std::alloc::alloc(layout)
+        })
+}
+
+/// Frees a buffer reserved by `hamcycle_alloc`, given back with the same count of bytes.
+///
+/// # Safety
+/// `buffer` must be null or from `hamcycle_alloc(bytes)` and not freed yet.
+#[cfg(target_arch = "wasm32")]
+#[no_mangle]
+pub unsafe extern "C" fn hamcycle_dealloc(buffer: *mut u8, bytes: usize) {
+    if let (false, Ok(layout)) = (
+        buffer.is_null(),
+        std::alloc::Layout::from_size_align(bytes.max(1), 8),
+    ) {
+        std::alloc::dealloc(buffer, layout);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr::{null, null_mut};
+
+    use super::*;
+
+    /// The graph and its woven tour, through the C interface.
+    unsafe fn solved(level: u32) -> (*mut HamGraph, *mut HamTour) {
+        let (mut graph, mut tour) = (null_mut(), null_mut());
+        assert_eq!(hamcycle_graph_new(level, &mut graph), Status::Ok);
+        assert_eq!(hamcycle_solve(graph, &mut tour), Status::Ok);
+        (graph, tour)
+    }
+
+    #[test]
+    fn solves_and_certifies_through_handles() {
+        unsafe {
+            let (graph, tour) = solved(3);
+            let order = hamcycle_graph_order(graph);
+            assert_eq!(order, 80);
+            assert_eq!(hamcycle_tour_len(tour), order);
+            let mut nodes = vec![0; order];
+            assert_eq!(
+                hamcycle_tour_nodes(tour, nodes.as_mut_ptr(), order),
+                Status::Ok
+            );
+            let mut verdict = -1;
+            let status = hamcycle_certify(graph, nodes.as_ptr(), order, &mut verdict);
+            assert_eq!((status, verdict), (Status::Ok, 2));
+            let status = hamcycle_certify(graph, nodes.as_ptr(), order - 1, &mut verdict);
+            assert_eq!((status, verdict), (Status::Ok, 0));
+            let mut coords = vec![0; 3 * order];
+            let status = hamcycle_tour_coordinates(graph, tour, coords.as_mut_ptr(), 3 * order);
+            assert_eq!(status, Status::Ok);
+            assert!(coords.iter().all(|coord| coord % 2 != 0));
+            assert_eq!(
+                hamcycle_graph_coordinates(graph, coords.as_mut_ptr(), 3 * order),
+                Status::Ok
+            );
+            let (x, y, z) = (&(*graph).verts)[order - 1];
+            assert_eq!(&coords[3 * order - 3..], &[x, y, z]);
+            hamcycle_tour_free(tour);
+            hamcycle_graph_free(graph);
+        }
+    }
+
+    #[test]
+    fn refuses_null_handles_and_buffers() {
+        unsafe {
+            assert_eq!(hamcycle_graph_new(1, null_mut()), Status::Null);
+            assert_eq!(hamcycle_graph_order(null()), 0);
+            assert_eq!(hamcycle_tour_len(null()), 0);
+            let (graph, tour) = solved(1);
+            let mut verdict = -1;
+            let mut buffer = [0; 24];
+            assert_eq!(hamcycle_solve(null(), &mut null_mut()), Status::Null);
+            assert_eq!(hamcycle_solve(graph, null_mut()), Status::Null);
+            assert_eq!(hamcycle_tour_nodes(tour, null_mut(), 8), Status::Null);
+            assert_eq!(
+                hamcycle_tour_nodes(null(), buffer.as_mut_ptr(), 8),
+                Status::Null
+            );
+            assert_eq!(
+                hamcycle_graph_coordinates(graph, null_mut(), 24),
+                Status::Null
+            );
+            assert_eq!(
+                hamcycle_tour_coordinates(graph, null(), null_mut(), 24),
+                Status::Null
+            );
+            assert_eq!(
+                hamcycle_certify(graph, null(), 8, &mut verdict),
+                Status::Null
+            );
+            assert_eq!(
+                hamcycle_certify(graph, buffer.as_ptr(), 8, null_mut()),
+                Status::Null
+            );
+            assert_eq!(
+                hamcycle_certify(null(), buffer.as_ptr(), 8, &mut verdict),
+                Status::Null
+            );
+            assert_eq!(verdict, -1);
+            hamcycle_tour_free(null_mut());
+            hamcycle_graph_free(null_mut());
+            hamcycle_tour_free(tour);
+            hamcycle_graph_free(graph);
+        }
+    }
+
+    #[test]
+    fn refuses_short_buffers_level_0_and_nodes_out_of_the_graph() {
+        unsafe {
+            let mut graph = null_mut();
+            assert_eq!(hamcycle_graph_new(0, &mut graph), Status::BadLevel);
+            assert!(graph.is_null());
+            let (graph, tour) = solved(1);
+            let mut nodes = [0; 7];
+            let mut coords = [0; 23];
+            assert_eq!(
+                hamcycle_tour_nodes(tour, nodes.as_mut_ptr(), 7),
+                Status::ShortBuffer
+            );
+            assert_eq!(
+                hamcycle_graph_coordinates(graph, coords.as_mut_ptr(), 23),
+                Status::ShortBuffer
+            );
+            assert_eq!(
+                hamcycle_tour_coordinates(graph, tour, coords.as_mut_ptr(), 23),
+                Status::ShortBuffer
+            );
+            assert_eq!(coords, [0; 23]);
+            let mut verdict = -1;
+            let far = [0, 1, 2, 3, 4, 5, 6, 8];
+            let status = hamcycle_certify(graph, far.as_ptr(), 8, &mut verdict);
+            assert_eq!((status, verdict), (Status::OutOfGraph, -1));
+            let status = hamcycle_certify(graph, far.as_ptr(), 0, &mut verdict);
+            assert_eq!((status, verdict), (Status::Ok, 0));
+            let (big, big_tour) = solved(2);
+            let mut coords = [0; 96];
+            let status = hamcycle_tour_coordinates(graph, big_tour, coords.as_mut_ptr(), 96);
+            assert_eq!(status, Status::OutOfGraph);
+            hamcycle_tour_free(big_tour);
+            hamcycle_graph_free(big);
+            hamcycle_tour_free(tour);
+            hamcycle_graph_free(graph);
+        }
+    }
+}
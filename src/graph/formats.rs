@@ -0,0 +1,365 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+};
+
+use itertools::Itertools;
+
+use super::defs::{Adjacency, Node, Solution, Vert, Verts};
+
+/// How the vertices are laid flat for Graphviz.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Projection {
+    /// The isometric view down the diagonal, every axis foreshortened alike.
+    Isometric,
+    /// Every z-layer drawn apart from the others, in a row from the lowest.
+    Layers,
+}
+
+impl FromStr for Projection {
+    type Err = &'static str;
+
+    fn from_str(projection: &str) -> Result<Self, Self::Err> {
+        match projection {
+            "isometric" => Ok(Projection::Isometric),
+            "layers" => Ok(Projection::Layers),
+            _ => Err("unknown projection, expected one of: isometric, layers"),
+        }
+    }
+}
+
+impl fmt::Display for Projection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Projection::Isometric => write!(f, "isometric"),
+            Projection::Layers => write!(f, "layers"),
+        }
+    }
+}
+
+/// The edges of the graph in order, each once with its lesser end first, or both ways round, as
+/// every format written here and petgraph's sparse rows take them.
+pub(crate) fn edges(adj: &Adjacency, both_ways: bool) -> Vec<(Node, Node)> {
+    let mut edges: Vec<(Node, Node)> = adj
+        .iter()
+        .flat_map(|(&node, neighbors)| neighbors.iter().map(move |&next| (node, next)))
+        .filter(|(node, next)| both_ways || node < next)
+        .collect();
+    edges.sort_unstable();
+    edges
+}
+
+/// What a writer below writes, written into memory, which cannot fail.
+pub(crate) fn written(write: impl FnOnce(&mut dyn Write) -> io::Result<()>) -> String {
+    let mut bytes = Vec::new();
+    write(&mut bytes).expect("writing into memory does not fail");
+    String::from_utf8(bytes).expect("every format is written as UTF-8")
+}
+
+/// Links the two vertices both ways, adding either that is not in the graph yet.
+fn link(adj: &mut Adjacency, a: Node, b: Node) {
+    adj.entry(a).or_default().insert(b);
+    adj.entry(b).or_default().insert(a);
+}
+
+/// The graph in the DIMACS format of the graph colouring challenge, `.col`: a problem line with the
+/// counts of vertices and edges, then an `e` line per edge, the vertices numbered from 1.
+pub fn to_col(name: &str, adj: &Adjacency) -> String {
+    written(|out| write_col(out, name, adj))
+}
+
+/// Writes the graph as `to_col` has it, a line at a time.
+pub fn write_col(out: &mut dyn Write, name: &str, adj: &Adjacency) -> io::Result<()> {
+    let edges = edges(adj, false);
+    writeln!(out, "c {name}\np edge {} {}", adj.len(), edges.len())?;
+    for (node, next) in edges {
+        writeln!(out, "e {} {}", node + 1, next + 1)?;
+    }
+    Ok(())
+}
+
+/// Reads a graph from the DIMACS `.col` format, skipping `c` comment lines.
+pub fn from_col(text: &str) -> Result<Adjacency, &'static str> {
+    let mut adj: Option<Adjacency> = None;
+    for line in text.lines().map(str::trim) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            [] | ["c", ..] => {}
+            ["p", _, order, _] => {
+                let order: Node = order
+                    .parse()
+                    .or(Err("the problem line has no vertex count"))?;
+                adj = Some((0..order).map(|node| (node, Default::default())).collect());
+            }
+            ["e", a, b] => {
+                let adj = adj
+                    .as_mut()
+                    .ok_or("an edge comes before the problem line")?;
+                let [a, b] = [a, b].map(|node| {
+                    node.parse::<Node>().or(Err(
+                        "an edge of the .col file joins more than vertex numbers",
+                    ))
+                });
+                let (a, b) = (a?, b?);
+                if !(1..=adj.len() as Node).contains(&a) || !(1..=adj.len() as Node).contains(&b) {
+                    return Err("an edge of the .col file is out of the graph");
+                }
+                link(adj, a - 1, b - 1);
+            }
+            _ => return Err("a .col file holds only c, p and e lines"),
+        }
+    }
+    adj.ok_or("the .col file has no problem line")
+}
+
+/// The graph as a plain edge list: a line of two vertices per edge, split by a space, the vertices
+/// numbered from 0 as they are here.
+pub fn to_edges(adj: &Adjacency) -> String {
+    written(|out| write_edges(out, adj))
+}
+
+/// Writes the graph as `to_edges` has it, a line at a time.
+pub fn write_edges(out: &mut dyn Write, adj: &Adjacency) -> io::Result<()> {
+    for (node, next) in edges(adj, false) {
+        writeln!(out, "{node} {next}")?;
+    }
+    Ok(())
+}
+
+/// Reads a graph from a plain edge list, the ends of every edge split by spaces, tabs or a comma,
+/// skipping blank lines and those starting with `#`. Vertices on no edge are not in the list and so
+/// not in the graph.
+pub fn from_edges(text: &str) -> Result<Adjacency, &'static str> {
+    let mut adj = Adjacency::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let ends: Vec<Node> = line
+            .split(|char: char| char == ',' || char.is_whitespace())
+            .filter(|end| !end.is_empty())
+            .map(|end| {
+                end.parse()
+                    .or(Err("an edge list holds two vertex numbers a line"))
+            })
+            .collect::<Result<_, _>>()?;
+        match ends[..] {
+            [a, b] if a != b => link(&mut adj, a, b),
+            _ => return Err("an edge list holds two distinct vertex numbers a line"),
+        }
+    }
+    Ok(adj)
+}
+
+/// The graph in GraphML, every vertex with its coordinates as the attributes x, y and z.
+pub fn to_graphml(name: &str, verts: &Verts, adj: &Adjacency) -> String {
+    written(|out| write_graphml(out, name, verts, adj))
+}
+
+/// Writes the graph as `to_graphml` has it, a line at a time.
+pub fn write_graphml(
+    out: &mut dyn Write,
+    name: &str,
+    verts: &Verts,
+    adj: &Adjacency,
+) -> io::Result<()> {
+    write!(
+        out,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n"
+    )?;
+    for axis in ["x", "y", "z"] {
+        writeln!(
+            out,
+            "  <key id=\"{axis}\" for=\"node\" attr.name=\"{axis}\" attr.type=\"int\"/>"
+        )?;
+    }
+    writeln!(out, "  <graph id=\"{name}\" edgedefault=\"undirected\">")?;
+    for (node, (x, y, z)) in verts.iter().enumerate() {
+        writeln!(
+            out,
+            "    <node id=\"n{node}\"><data key=\"x\">{x}</data><data key=\"y\">{y}</data>\
+             <data key=\"z\">{z}</data></node>"
+        )?;
+    }
+    for (node, next) in edges(adj, false) {
+        writeln!(out, "    <edge source=\"n{node}\" target=\"n{next}\"/>")?;
+    }
+    write!(out, "  </graph>\n</graphml>\n")
+}
+
+/// Reads a graph from GraphML, numbering its nodes in the order they are declared, whatever their
+/// ids, which must differ. Only the nodes and edges are read, not their data.
+pub fn from_graphml(text: &str) -> Result<Adjacency, &'static str> {
+    let mut ids: HashMap<&str, Node> = HashMap::new();
+    let mut adj = Adjacency::new();
+    for tag in text.split('<').skip(1) {
+        let element = tag
+            .split(|char: char| char.is_whitespace() || char == '/' || char == '>')
+            .next();
+        if element == Some("node") {
+            let id = attr(tag, "id").ok_or("a GraphML node has no id")?;
+            let node = ids.len() as Node;
+            if ids.insert(id, node).is_some() {
+                return Err("two GraphML nodes have the same id");
+            }
+            adj.insert(node, Default::default());
+        } else if element == Some("edge") {
+            let [source, target] =
+                ["source", "target"].map(|end| attr(tag, end).and_then(|id| ids.get(id)));
+            match (source, target) {
+                (Some(&a), Some(&b)) if a != b => link(&mut adj, a, b),
+                _ => return Err("a GraphML edge joins nodes not declared before it"),
+            }
+        }
+    }
+    Ok(adj)
+}
+
+/// The value of an attribute of an XML tag, in double quotes or single ones.
+fn attr<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag.split('>').next()?;
+    while let Some(at) = rest.find(&format!("{name}=")) {
+        let (before, after) = (&rest[..at], &rest[at + name.len() + 1..]);
+        if before.ends_with(char::is_whitespace) {
+            let quote = after
+                .chars()
+                .next()
+                .filter(|&quote| quote == '"' || quote == '\'')?;
+            return after[1..].split(quote).next();
+        }
+        rest = after;
+    }
+    None
+}
+
+/// The graph in Graphviz's DOT language, every vertex pinned where the projection puts it, for
+/// `neato -n` to draw as it is. The edges of the tour are bold and coloured round the hue circle by
+/// their place along it, the rest are grey.
+pub fn to_dot(
+    name: &str,
+    verts: &Verts,
+    adj: &Adjacency,
+    tour: &Solution,
+    projection: Projection,
+) -> String {
+    written(|out| write_dot(out, name, verts, adj, tour, projection))
+}
+
+/// Writes the graph and tour as `to_dot` has them, a line at a time.
+pub fn write_dot(
+    out: &mut dyn Write,
+    name: &str,
+    verts: &Verts,
+    adj: &Adjacency,
+    tour: &Solution,
+    projection: Projection,
+) -> io::Result<()> {
+    // points to a unit of the lattice, neighbours being two units apart; a layer is as wide as the
+    // graph with a gap of two neighbours on either side.
+    const SCALE: f32 = 18.0;
+    let width = verts.iter().map(|&(x, ..)| x.abs()).max().unwrap_or(0) as f32 * 2.0 + 4.0;
+    let place = |&(x, y, z): &Vert| {
+        let [x, y, z] = [x, y, z].map(f32::from);
+        match projection {
+            Projection::Isometric => ((x - y) * 0.866, z + (x + y) * 0.5),
+            Projection::Layers => (x + (z + width / 2.0 - 2.0) / 2.0 * width, y),
+        }
+    };
+    let order: HashMap<(Node, Node), usize> = tour
+        .iter()
+        .circular_tuple_windows()
+        .enumerate()
+        .map(|(idx, (&node, &next))| ((node.min(next), node.max(next)), idx))
+        .collect();
+    write!(
+        out,
+        "graph {name} {{\n  node [shape=point, width=0.08];\n  edge [color=grey80];\n"
+    )?;
+    for (node, vert) in verts.iter().enumerate() {
+        let (x, y) = place(vert);
+        writeln!(
+            out,
+            "  {node} [pos=\"{:.1},{:.1}!\"];",
+            x * SCALE,
+            y * SCALE
+        )?;
+    }
+    for (node, next) in edges(adj, false) {
+        match order.get(&(node, next)) {
+            Some(&idx) => writeln!(
+                out,
+                "  {node} -- {next} [color=\"{:.3} 0.9 0.8\", style=bold, penwidth=3];",
+                idx as f32 / tour.len() as f32
+            )?,
+            None => writeln!(out, "  {node} -- {next};")?,
+        }
+    }
+    writeln!(out, "}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_col, from_edges, from_graphml, to_col, to_dot, to_edges, to_graphml};
+    use crate::graph::{formats::Projection, utils::make::make_graph, weave::weave};
+
+    #[test]
+    fn reads_back_the_graph_it_writes() {
+        let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(3);
+        assert_eq!(from_col(&to_col("discocube_3", &adj)), Ok(adj.clone()));
+        assert_eq!(from_edges(&to_edges(&adj)), Ok(adj.clone()));
+        assert_eq!(
+            from_graphml(&to_graphml("discocube_3", &verts, &adj)),
+            Ok(adj.clone())
+        );
+        let tour = weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz);
+        let dot = to_dot("discocube_3", &verts, &adj, &tour, Projection::Layers);
+        assert_eq!(dot.matches("style=bold").count(), tour.len());
+    }
+
+    #[test]
+    fn rejects_col_edges_that_are_not_vertex_numbers() {
+        assert!(from_col("p edge 3 2\ne 1 2\ne 2 3\n").is_ok());
+        assert!(from_col("p edge 3 1\ne 1 x\n").is_err());
+        assert!(from_col("p edge 3 1\ne 0 1\n").is_err());
+        assert!(from_col("p edge 3 1\ne 1 4\n").is_err());
+        assert!(from_col("e 1 2\n").is_err());
+        assert!(from_col("c no problem line\n").is_err());
+    }
+
+    #[test]
+    fn reads_graphml_in_either_quotes() {
+        let text = "<graphml><graph edgedefault='undirected'>\n\
+                    <node id='a'/><node\tid=\"b\"/><node id='c'></node>\n\
+                    <edge source='a' target=\"b\"/><edge id='e' source='b' target='c'/>\n\
+                    </graph></graphml>";
+        let adj = from_graphml(text).unwrap();
+        assert_eq!(adj.len(), 3);
+        assert_eq!(adj[&1].len(), 2);
+    }
+
+    #[test]
+    fn rejects_graphml_nodes_of_the_same_id() {
+        let text = "<graph><node id=\"a\"/><node id=\"a\"/></graph>";
+        assert!(from_graphml(text).is_err());
+        assert!(
+            from_graphml("<graph><node id='a'/><edge source='a' target='b'/></graph>").is_err()
+        );
+        assert!(from_graphml("<graph><node/></graph>").is_err());
+    }
+
+    #[test]
+    fn rejects_edge_lists_of_loops_or_words() {
+        assert_eq!(
+            from_edges("# square\n0 1\n1,2\n2\t3\n\n3 0\n")
+                .unwrap()
+                .len(),
+            4
+        );
+        assert!(from_edges("1 1\n").is_err());
+        assert!(from_edges("1 2 3\n").is_err());
+        assert!(from_edges("1 b\n").is_err());
+    }
+}
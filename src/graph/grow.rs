@@ -0,0 +1,472 @@
+use super::{
+    defs::{Node, Point, Solution, VIMap, VecVert, Vert},
+    factor::UNSET,
+    utils::make::make_graph,
+    weave::weave,
+};
+
+/// The discocube grown a shell at a time around its cycle, from level 1 up. Its vertices are
+/// numbered shell by shell from the centre, in the order `make::make_graph` gives them, so that the
+/// vertices of a level come before those of the next and the cycle of one level is laid over the
+/// next as it stands.
+pub struct Growth {
+    level: u32,
+    verts: VecVert,
+    vi_map: VIMap,
+    neighbors: Vec<Vec<usize>>,
+    /// The first vertex of every shell, the innermost cube being the first.
+    shells: Vec<usize>,
+    links: Vec<[usize; 2]>,
+    /// The links of the cycle of the level below, which the next level is grown from.
+    below: Vec<[usize; 2]>,
+}
+
+impl Default for Growth {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Growth {
+    /// The discocube of level 1 with its woven cycle, to grow from.
+    pub fn new() -> Growth {
+        let (_, order, verts, vi_map, adj, ..) = make_graph(1);
+        let links = woven_links(1);
+        let neighbors = (0..order)
+            .map(|node| adj[&node].iter().map(|&next| next as usize).collect())
+            .collect();
+        let shells = (0..verts.len())
+            .filter(|&i| i == 0 || shell(verts[i]) != shell(verts[i - 1]))
+            .collect();
+        Growth {
+            level: 1,
+            verts,
+            vi_map,
+            neighbors,
+            shells,
+            links,
+            below: Vec::new(),
+        }
+    }
+
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    pub fn order(&self) -> usize {
+        self.verts.len()
+    }
+
+    pub fn verts(&self) -> &VecVert {
+        &self.verts
+    }
+
+    /// Grows the discocube by a shell and returns how many edges of the cycle of the level two
+    /// below the new one it kept, or None for level 2, which is woven. The cycle of the level n + 1
+    /// is not spliced into the one of level n but into the one of level n - 1: the two outer shells,
+    /// the skin, are wound into a cycle of their own by `wind` and joined to that cycle across a
+    /// unit square by `join`. Splicing a single shell into the cycle of level n cannot keep most of
+    /// it. A vertex of the outer shell s of level s is linked along the shell only where a coordinate
+    /// of ±1 changes sign, 12·s edges at most, so the cycle takes all but 8·s ends of the vertices
+    /// of shell s - 1 to link them with shell s. Shell s - 1 then has next to no links left for shell
+    /// s - 2, which therefore takes all but a few ends of shell s - 3 and so on down to the centre.
+    /// The cycle pairs the shells from the outside in, and one more shell shifts every pair, so that
+    /// the cycles of consecutive levels share only a few edges whatever they are.
+    pub fn grow(&mut self) -> Result<Option<usize>, &'static str> {
+        let below = std::mem::take(&mut self.below);
+        self.below = std::mem::replace(&mut self.links, below);
+        let before = self.links.clone();
+        self.add_shell();
+        if self.level == 2 {
+            self.links = woven_links(2);
+            return Ok(None);
+        }
+        self.wind()?;
+        self.join()?;
+        Ok(Some(self.shared(&before)))
+    }
+
+    /// How many edges of the cycle are also edges of the one given by its links, over vertices of
+    /// the same numbering.
+    pub fn shared(&self, links: &[[usize; 2]]) -> usize {
+        let shared = links
+            .iter()
+            .enumerate()
+            .map(|(i, pair)| {
+                pair.iter()
+                    .filter(|&j| self.links.get(i).is_some_and(|links| links.contains(j)))
+                    .count()
+            })
+            .sum::<usize>();
+        shared / 2
+    }
+
+    pub fn links(&self) -> &[[usize; 2]] {
+        &self.links
+    }
+
+    /// The links of the cycle of the level below.
+    pub fn below(&self) -> &[[usize; 2]] {
+        &self.below
+    }
+
+    /// The cycle, from the first vertex.
+    pub fn cycle(&self) -> Solution {
+        let mut cycle = Vec::with_capacity(self.order());
+        cycle.push(0);
+        let (mut prev, mut node) = (0, self.links[0][0]);
+        while node != 0 {
+            cycle.push(node as Node);
+            let next = match self.links[node][0] == prev {
+                true => self.links[node][1],
+                false => self.links[node][0],
+            };
+            (prev, node) = (node, next);
+        }
+        cycle
+    }
+
+    /// Adds the vertices at the next distance from the centre, in the order of `make::make_graph`:
+    /// by x, then y, then z.
+    fn add_shell(&mut self) {
+        self.level += 1;
+        let first = self.order();
+        let reach = 2 * self.level as Point + 1;
+        for x in (-reach + 2..=reach - 2).step_by(2) {
+            let rest = reach - x.abs();
+            for y in (-rest + 1..=rest - 1).step_by(2) {
+                let z = rest - y.abs();
+                for vert in [(x, y, -z), (x, y, z)] {
+                    self.vi_map.insert(vert, self.verts.len() as Node);
+                    self.verts.push(vert);
+                }
+            }
+        }
+        self.neighbors.resize(self.order(), Vec::new());
+        self.links.resize(self.order(), [UNSET; 2]);
+        for node in first..self.order() {
+            let (x, y, z) = self.verts[node];
+            for next in [
+                (x - 2, y, z),
+                (x + 2, y, z),
+                (x, y - 2, z),
+                (x, y + 2, z),
+                (x, y, z - 2),
+                (x, y, z + 2),
+            ] {
+                let Some(&next) = self.vi_map.get(&next) else {
+                    continue;
+                };
+                self.neighbors[node].push(next as usize);
+                if (next as usize) < first {
+                    self.neighbors[next as usize].push(node);
+                }
+            }
+        }
+        self.shells.push(first);
+    }
+
+    /// Winds the skin into a cycle as a double helix. Cut by a plane z = c, the skin is a ring
+    /// that zigzags between its lower shell and its upper one, but for where it crosses an axis on
+    /// the upper one. Every ring above the equator up to the one below the top is cut into two arcs,
+    /// each from a vertex on the upper shell round to one on the lower, whose vertex above is on the
+    /// upper shell of the next ring: the one helix climbs through the rings by its arcs and the
+    /// other comes down through them. At the top, the last ring and the tip square are threaded by
+    /// a search from where the one helix comes up to where the other goes down. The half below the
+    /// equator is the mirror image of the half above, and the helices cross to it where they start.
+    /// Starting them a quarter turn apart brings them to the top where a thread is found between
+    /// them; the other starts are tried should it not be.
+    fn wind(&mut self) -> Result<(), &'static str> {
+        let first = self.shells[self.shells.len() - 2];
+        let reach = 2 * self.level as Point + 1;
+        let rings: Vec<Vec<usize>> = (1..reach - 4)
+            .step_by(2)
+            .map(|z| self.ring(first, reach, z))
+            .collect();
+        let mut at = vec![0; self.order() - first];
+        for ring in &rings {
+            ring.iter()
+                .enumerate()
+                .for_each(|(i, &node)| at[node - first] = i);
+        }
+        let outer: Vec<usize> = rings[0]
+            .iter()
+            .copied()
+            .filter(|&node| shell(self.verts[node]) == reach)
+            .collect();
+        let starts = (0..outer.len()).map(|k| outer[(outer.len() / 4 + k) % outer.len()]);
+        let (cuts, thread) = starts
+            .filter(|&start| start != outer[0])
+            .find_map(|start| {
+                let cuts = self.cut(&rings, &at, first, (outer[0], start))?;
+                let &(_, (climb, descend), _) = cuts.last()?;
+                let ends = [climb, descend].map(|node| self.above(node));
+                Some((cuts, self.thread(first, reach, ends)?))
+            })
+            .ok_or("the skin could not be wound into a cycle")?;
+        for (ring, &(dir, (climb, descend), (enter, leave))) in rings.iter().zip(&cuts) {
+            let len = ring.len();
+            for (mut i, end) in [(at[enter - first], climb), (at[leave - first], descend)] {
+                while ring[i] != end {
+                    let next = (i + len).wrapping_add_signed(dir) % len;
+                    self.tie(ring[i], ring[next]);
+                    i = next;
+                }
+                self.tie(end, self.above(end));
+            }
+        }
+        thread
+            .windows(2)
+            .for_each(|pair| self.tie(pair[0], pair[1]));
+        for node in first..self.order() {
+            if self.verts[node].2 > 0 {
+                let links = self.links[node].map(|next| match next {
+                    UNSET => UNSET,
+                    next => self.mirror(next),
+                });
+                let mirror = self.mirror(node);
+                self.links[mirror] = links;
+            }
+        }
+        let (enter, leave) = cuts[0].2;
+        for node in [enter, leave] {
+            self.tie(node, self.mirror(node));
+        }
+        Ok(())
+    }
+
+    /// Joins the skin to the cycle inside across the equator, where a vertex just above it on the
+    /// outer shell of the cycle inside is linked to the one just below it, and the skin runs round
+    /// the corner of the square the vertex makes with its neighbours on the lower shell of the
+    /// skin. Being its own mirror image, the skin crosses the equator twice, so that it falls apart
+    /// into two halves along the corner whichever two edges of it are cut, unless one is cut above
+    /// the equator and the other below. The edge across the equator inside is cut too and the ends
+    /// are tied: the vertex to its neighbour, its image to the image of its other neighbour and the
+    /// corner to its own image.
+    fn join(&mut self) -> Result<(), &'static str> {
+        let skin = self.shells[self.shells.len() - 2];
+        for a in self.shells[self.shells.len() - 3]..skin {
+            let b = self.mirror(a);
+            if self.verts[a].2 != 1 || !self.links[a].contains(&b) {
+                continue;
+            }
+            let out: Vec<usize> = self.neighbors[a]
+                .iter()
+                .copied()
+                .filter(|&next| next >= skin && self.verts[next].2 == 1)
+                .collect();
+            let &[p, q] = &out[..] else {
+                continue;
+            };
+            let [(ax, ay, _), (px, py, _), (qx, qy, _)] = [a, p, q].map(|node| self.verts[node]);
+            let r = self.vi_map[&(px + qx - ax, py + qy - ay, 1)] as usize;
+            if !self.links[r].contains(&p)
+                || !self.links[r].contains(&q)
+                || self.links[r].contains(&self.mirror(r))
+            {
+                continue;
+            }
+            let [mq, mr] = [q, r].map(|node| self.mirror(node));
+            for (node, from, to) in [
+                (a, b, p),
+                (b, a, mq),
+                (p, r, a),
+                (mq, mr, b),
+                (r, p, mr),
+                (mr, mq, r),
+            ] {
+                self.relink(node, from, to);
+            }
+            return Ok(());
+        }
+        Err("the skin could not be joined to the cycle inside")
+    }
+
+    /// Links the node to another in place of the one it was linked to.
+    fn relink(&mut self, node: usize, from: usize, to: usize) {
+        let slot = (self.links[node][1] == from) as usize;
+        self.links[node][slot] = to;
+    }
+
+    /// Where the helices cut every ring above the equator up to the one below the top: the way
+    /// round the ring the arcs run, the inner vertices they leave it by, climbing and descending,
+    /// and the outer ones they come in by. None when a ring has no way round that leaves both arcs
+    /// on inner vertices.
+    #[allow(clippy::type_complexity)]
+    fn cut(
+        &self,
+        rings: &[Vec<usize>],
+        at: &[usize],
+        first: usize,
+        (mut enter, mut leave): (usize, usize),
+    ) -> Option<Vec<(isize, (usize, usize), (usize, usize))>> {
+        let inner = shell(self.verts[first]);
+        let mut cuts = Vec::with_capacity(rings.len());
+        for ring in rings {
+            let len = ring.len();
+            let back = |node: usize, dir: isize| {
+                ring[(at[node - first] + len).wrapping_add_signed(-dir) % len]
+            };
+            let dir = [1, -1].into_iter().find(|&dir| {
+                [back(enter, dir), back(leave, dir)]
+                    .iter()
+                    .all(|&node| shell(self.verts[node]) == inner)
+            })?;
+            let (climb, descend) = (back(leave, dir), back(enter, dir));
+            cuts.push((dir, (climb, descend), (enter, leave)));
+            (enter, leave) = (self.above(climb), self.above(descend));
+        }
+        Some(cuts)
+    }
+
+    /// The ring of the skin at height z, in order round it from the outer vertex on the y axis.
+    fn ring(&self, first: usize, reach: Point, z: Point) -> Vec<usize> {
+        let start = self.vi_map[&(1, reach - z - 1, z)] as usize;
+        let mut ring = vec![start];
+        let (mut prev, mut node) = (UNSET, start);
+        loop {
+            let (x, y, _) = self.verts[node];
+            let next = [(x + 2, y), (x - 2, y), (x, y + 2), (x, y - 2)]
+                .into_iter()
+                .filter_map(|(x, y)| self.vi_map.get(&(x, y, z)).map(|&next| next as usize))
+                .find(|&next| {
+                    next >= first
+                        && next != prev
+                        && (shell(self.verts[next]) != shell(self.verts[node])
+                            || shell(self.verts[node]) == reach)
+                })
+                .unwrap();
+            if next == start {
+                return ring;
+            }
+            ring.push(next);
+            (prev, node) = (node, next);
+        }
+    }
+
+    /// A path through the last ring below the top and the tip square, between the given ends.
+    fn thread(&self, first: usize, reach: Point, [from, to]: [usize; 2]) -> Option<Vec<usize>> {
+        let cap: Vec<usize> = (first..self.order())
+            .filter(|&node| self.verts[node].2 >= reach - 4)
+            .collect();
+        let mut path = vec![from];
+        let mut seen = vec![false; self.order() - first];
+        seen[from - first] = true;
+        let mut tried = vec![0];
+        while let Some(&node) = path.last() {
+            if path.len() == cap.len() && node == to {
+                return Some(path);
+            }
+            let last = tried.len() - 1;
+            let next = self.neighbors[node][tried[last]..]
+                .iter()
+                .position(|&next| next >= first && cap.contains(&next) && !seen[next - first]);
+            match next {
+                Some(k) if path.len() < cap.len() => {
+                    let next = self.neighbors[node][tried[last] + k];
+                    tried[last] += k + 1;
+                    if next == to && path.len() + 1 < cap.len() {
+                        continue;
+                    }
+                    seen[next - first] = true;
+                    path.push(next);
+                    tried.push(0);
+                }
+                _ => {
+                    seen[node - first] = false;
+                    path.pop();
+                    tried.pop();
+                }
+            }
+        }
+        None
+    }
+
+    fn above(&self, node: usize) -> usize {
+        let (x, y, z) = self.verts[node];
+        self.vi_map[&(x, y, z + 2)] as usize
+    }
+
+    fn mirror(&self, node: usize) -> usize {
+        let (x, y, z) = self.verts[node];
+        self.vi_map[&(x, y, -z)] as usize
+    }
+
+    fn tie(&mut self, a: usize, b: usize) {
+        for (node, next) in [(a, b), (b, a)] {
+            let slot = (self.links[node][0] != UNSET) as usize;
+            self.links[node][slot] = next;
+        }
+    }
+}
+
+/// The links of the woven cycle of the level given, both neighbours of every vertex.
+fn woven_links(n: u32) -> Vec<[usize; 2]> {
+    let (_, order, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(n);
+    let woven = weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz);
+    let mut links = vec![[UNSET; 2]; order as usize];
+    for (i, &node) in woven.iter().enumerate() {
+        let after = woven[(i + 1) % woven.len()] as usize;
+        let before = woven[(i + woven.len() - 1) % woven.len()] as usize;
+        links[node as usize] = [after, before];
+    }
+    links
+}
+
+fn shell((x, y, z): Vert) -> Point {
+    x.abs() + y.abs() + z.abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Growth;
+    use crate::graph::utils::{
+        certify::{id_seq, SequenceID},
+        make::make_graph,
+    };
+
+    #[test]
+    fn grows_a_hamiltonian_cycle_on_every_level() {
+        let mut growth = Growth::new();
+        while growth.level() < 16 {
+            growth.grow().unwrap();
+            let (.., adj, _, _, _) = make_graph(growth.level());
+            assert_eq!(growth.order(), adj.len());
+            assert_eq!(id_seq(&growth.cycle(), &adj), SequenceID::HamCycle);
+        }
+    }
+
+    #[test]
+    fn weaves_only_the_first_two_levels() {
+        let mut growth = Growth::new();
+        assert_eq!((growth.level(), growth.order()), (1, 8));
+        assert_eq!(growth.grow(), Ok(None));
+        assert_eq!((growth.level(), growth.order()), (2, 32));
+        assert_eq!(growth.below().len(), 8);
+        assert!(growth.grow().unwrap().is_some());
+    }
+
+    #[test]
+    fn keeps_every_edge_but_one_of_the_level_two_below() {
+        let mut growth = Growth::new();
+        growth.grow().unwrap();
+        for _ in 0..10 {
+            let order = growth.below().len();
+            assert_eq!(growth.grow().unwrap(), Some(order - 1));
+        }
+    }
+
+    /// Consecutive levels pair their shells the other way round, so that the edges between the
+    /// shells of a pair in one are almost all missing from the other, and a cycle can only be grown
+    /// from the level two below.
+    #[test]
+    fn shares_fewer_edges_than_a_shell_with_the_level_below() {
+        let mut growth = Growth::new();
+        growth.grow().unwrap();
+        for level in 3..=16 {
+            growth.grow().unwrap();
+            let shell = 4 * level * (level + 1);
+            assert!(growth.shared(growth.below()) < shell, "level {level}");
+        }
+    }
+}
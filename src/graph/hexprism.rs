@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use super::defs::{Adjacency, Node, Point, Solution, Tour, VIMap, Verts};
+
+/// Steps between neighbouring hexagons on axial coordinates, in order around a ring.
+const DIRECTIONS: [[Point; 2]; 6] = [[1, 0], [1, -1], [0, -1], [-1, 0], [-1, 1], [0, 1]];
+
+/// Weaves the hexprism honeycomb diamond made by `make::make_hexprism`. The lower half is spun as nested
+/// cylinders, one per ring of hexagons, each wound into a loop of its own: around the ring on the top
+/// layer, back and forth over the rest of the ring on every layer below, and back up the cell left
+/// out, which the ring closing on itself makes possible whatever the number of layers. The pith, the
+/// column of centre cells, is threaded into the innermost loop a layer at a time. The loops are joined
+/// into one, and the cycle is reflected onto the upper half and joined to its mirror image.
+pub fn hexprism(
+    n: u32,
+    adj: &Adjacency,
+    verts: &Verts,
+    vi_map: &VIMap,
+) -> Result<Solution, &'static str> {
+    let n = n as Point;
+    let mut loops: Vec<Tour> = (1..=n)
+        .map(|radius| wind(radius, n - radius + 1, vi_map))
+        .collect();
+    loops[0] = thread_pith(&loops[0], verts, vi_map);
+    let mut lower = loops.remove(0);
+    for other in loops {
+        lower = join(lower, other, adj)?;
+    }
+    let upper: Tour = lower
+        .iter()
+        .map(|&node| {
+            let (q, r, z) = verts[node as usize];
+            vi_map[&(q, r, -z)]
+        })
+        .collect();
+    join(lower, upper, adj)
+}
+
+/// The cells of the ring of hexagons at `radius` around (0, 0), in order around it.
+fn ring(radius: Point) -> Vec<[Point; 2]> {
+    let mut cell = [-radius, radius];
+    DIRECTIONS
+        .iter()
+        .flat_map(|&step| std::iter::repeat_n(step, radius as usize))
+        .map(|[dq, dr]| {
+            let here = cell;
+            cell = [cell[0] + dq, cell[1] + dr];
+            here
+        })
+        .collect()
+}
+
+/// Winds the cylinder of the ring at `radius` over the top `height` layers of the lower half.
+fn wind(radius: Point, height: Point, vi_map: &VIMap) -> Tour {
+    let ring = ring(radius);
+    let cell = |[q, r]: [Point; 2], layer: Point| vi_map[&(q, r, -(2 * layer + 1))];
+    let mut tour: Tour = ring.iter().map(|&at| cell(at, 0)).collect();
+    for layer in 1..height {
+        match layer % 2 {
+            1 => tour.extend(ring[1..].iter().rev().map(|&at| cell(at, layer))),
+            _ => tour.extend(ring[1..].iter().map(|&at| cell(at, layer))),
+        }
+    }
+    tour.extend((1..height).rev().map(|layer| cell(ring[0], layer)));
+    tour
+}
+
+/// Threads the centre cell of every layer between two cells of the innermost ring the loop steps
+/// between on that layer, the centre neighbouring every cell of the ring.
+fn thread_pith(tour: &Tour, verts: &Verts, vi_map: &VIMap) -> Tour {
+    let mut threaded: Vec<Point> = Vec::new();
+    let mut pith: Tour = Vec::with_capacity(tour.len() + tour.len() / 6);
+    for (&a, &b) in tour.iter().circular_tuple_windows() {
+        pith.push(a);
+        let (z, other) = (verts[a as usize].2, verts[b as usize].2);
+        if z == other && !threaded.contains(&z) {
+            pith.push(vi_map[&(0, 0, z)]);
+            threaded.push(z);
+        }
+    }
+    pith
+}
+
+/// Joins two disjoint loops into one across a pair of neighbouring edges: an edge (a, b) of the tour
+/// and an edge (c, d) of the other loop with a next to c and b next to d. Both edges are cut, and a
+/// joined to c and d to b.
+fn join(tour: Tour, other: Tour, adj: &Adjacency) -> Result<Tour, &'static str> {
+    let len = tour.len();
+    let at: HashMap<Node, usize> = tour.iter().enumerate().map(|(i, &a)| (a, i)).collect();
+    for (idx, (&c, &d)) in other.iter().circular_tuple_windows().enumerate() {
+        for (&a, &i) in adj[&c].iter().filter_map(|a| Some((a, at.get(a)?))) {
+            for (j, forward) in [((i + 1) % len, true), ((i + len - 1) % len, false)] {
+                if !adj[&d].contains(&tour[j]) {
+                    continue;
+                }
+                let mut first = tour;
+                match forward {
+                    true => first.rotate_left(j),
+                    false => {
+                        first.rotate_left(i);
+                        first.reverse()
+                    }
+                }
+                debug_assert_eq!(first[len - 1], a);
+                let shift = (idx + 1) % other.len();
+                let mut second = other;
+                second.rotate_left(shift);
+                second.reverse();
+                first.append(&mut second);
+                return Ok(first);
+            }
+        }
+    }
+    Err("found no pair of neighbouring edges to join the loops across")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hexprism;
+    use crate::graph::utils::{
+        certify::{id_seq, SequenceID},
+        info::get_hexprism_order_from_n,
+        make::make_hexprism,
+    };
+
+    #[test]
+    fn weaves_every_small_level() {
+        for level in 1..=20 {
+            let (n, order, verts, vi_map, adj) = make_hexprism(level);
+            assert_eq!(order, get_hexprism_order_from_n(level));
+            let tour = hexprism(n, &adj, &verts, &vi_map).unwrap();
+            assert_eq!(id_seq(&tour, &adj), SequenceID::HamCycle);
+        }
+    }
+}
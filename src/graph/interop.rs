@@ -0,0 +1,152 @@
+use petgraph::{
+    csr::Csr,
+    visit::{EdgeRef, IntoNeighbors, IntoNodeIdentifiers, NodeIndexable},
+    EdgeType, Graph,
+};
+
+use super::{
+    defs::{Adjacency, GridGraph, Node, Solution, Vert},
+    formats::edges,
+    utils::certify::{self, SequenceID},
+};
+
+/// Every vertex weighed with its coordinates and every edge once, or both ways round for a directed
+/// graph, so that a cycle can be walked in either direction.
+impl<Ty: EdgeType> From<&GridGraph> for Graph<Vert, (), Ty> {
+    fn from(grid: &GridGraph) -> Self {
+        let mut graph = Graph::with_capacity(grid.verts.len(), grid.adj.len() * 3);
+        grid.verts.iter().for_each(|&vert| {
+            graph.add_node(vert);
+        });
+        graph.extend_with_edges(edges(&grid.adj, Ty::is_directed()));
+        graph
+    }
+}
+
+/// The vertices by their index and the edges both ways, whatever the graph's direction and weights.
+impl<E, Ty: EdgeType> From<&Graph<Vert, E, Ty>> for GridGraph {
+    fn from(graph: &Graph<Vert, E, Ty>) -> Self {
+        let mut adj: Adjacency = (0..graph.node_count() as Node)
+            .map(|node| (node, Default::default()))
+            .collect();
+        for edge in graph.edge_references() {
+            let [a, b] = [edge.source(), edge.target()].map(|node| node.index() as Node);
+            if a != b {
+                adj.get_mut(&a).unwrap().insert(b);
+                adj.get_mut(&b).unwrap().insert(a);
+            }
+        }
+        GridGraph {
+            verts: graph.node_weights().copied().collect(),
+            adj,
+        }
+    }
+}
+
+/// The compressed sparse rows petgraph keeps both ways round for undirected and directed graphs
+/// alike, read straight from the sorted edges.
+impl<Ty: EdgeType> From<&GridGraph> for Csr<Vert, (), Ty> {
+    fn from(grid: &GridGraph) -> Self {
+        let mut csr =
+            Csr::from_sorted_edges(&edges(&grid.adj, true)).expect("the edges are sorted");
+        // a vertex on no edge past the last that has one leaves no row yet.
+        while csr.node_count() < grid.verts.len() {
+            csr.add_node(Default::default());
+        }
+        for (node, &vert) in grid.verts.iter().enumerate() {
+            csr[node as Node] = vert;
+        }
+        csr
+    }
+}
+
+impl<E, Ty: EdgeType> From<&Csr<Vert, E, Ty>> for GridGraph {
+    fn from(csr: &Csr<Vert, E, Ty>) -> Self {
+        let mut adj: Adjacency = (0..csr.node_count() as Node)
+            .map(|node| (node, Default::default()))
+            .collect();
+        for node in 0..csr.node_count() as Node {
+            for &next in csr
+                .neighbors_slice(node)
+                .iter()
+                .filter(|&&next| next != node)
+            {
+                adj.get_mut(&node).unwrap().insert(next);
+                adj.get_mut(&next).unwrap().insert(node);
+            }
+        }
+        GridGraph {
+            verts: (0..csr.node_count() as Node)
+                .map(|node| csr[node])
+                .collect(),
+            adj,
+        }
+    }
+}
+
+/// Certifies a tour of any petgraph graph with the crate's certifier, the tour given by the graph's
+/// own node ids. A directed graph's tour has to follow its arcs.
+pub fn certify<G>(graph: G, tour: &[G::NodeId]) -> SequenceID
+where
+    G: IntoNodeIdentifiers + IntoNeighbors + NodeIndexable,
+{
+    let adj: Adjacency = graph
+        .node_identifiers()
+        .map(|node| {
+            let neighbors = graph.neighbors(node).filter(|&next| next != node);
+            let index = |node| graph.to_index(node) as Node;
+            (index(node), neighbors.map(index).collect())
+        })
+        .collect();
+    let seq: Solution = tour
+        .iter()
+        .map(|&node| graph.to_index(node) as Node)
+        .collect();
+    match seq.iter().all(|node| adj.contains_key(node)) {
+        true => certify::id_seq(&seq, &adj),
+        false => SequenceID::Broken,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::{csr::Csr, graph::NodeIndex, Directed, Graph, Undirected};
+
+    use super::certify;
+    use crate::graph::{
+        defs::{GridGraph, Vert},
+        utils::certify::SequenceID,
+        utils::make::make_graph,
+        weave::weave,
+    };
+
+    #[test]
+    fn takes_back_the_graph_it_hands_over() {
+        let grid = GridGraph::discocube(3);
+        let graph: Graph<Vert, (), Undirected> = (&grid).into();
+        assert_eq!(
+            graph.edge_count(),
+            grid.adj.values().map(|next| next.len()).sum::<usize>() / 2
+        );
+        assert_eq!(GridGraph::from(&graph), grid);
+        let directed: Graph<Vert, (), Directed> = (&grid).into();
+        assert_eq!(directed.edge_count(), 2 * graph.edge_count());
+        assert_eq!(GridGraph::from(&directed), grid);
+        let csr: Csr<Vert, (), Undirected> = (&grid).into();
+        assert_eq!(GridGraph::from(&csr), grid);
+    }
+
+    #[test]
+    fn certifies_a_tour_by_the_graphs_own_ids() {
+        let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(3);
+        let tour = weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz);
+        let graph: Graph<Vert, (), Undirected> = (&GridGraph { verts, adj }).into();
+        let ids: Vec<NodeIndex> = tour
+            .iter()
+            .map(|&node| NodeIndex::new(node as usize))
+            .collect();
+        assert_eq!(certify(&graph, &ids), SequenceID::HamCycle);
+        assert_eq!(certify(&graph, &ids[1..]), SequenceID::Broken);
+        assert_eq!(certify(&graph, &[NodeIndex::new(999)]), SequenceID::Broken);
+    }
+}
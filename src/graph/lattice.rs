@@ -0,0 +1,439 @@
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    defs::{Adjacency, Node, Point},
+    utils::make::make_graph,
+    weave::weave,
+};
+
+/// A point of the lattice in D dimensions, as `defs::Vert` is one in three.
+pub type Vert<const D: usize> = [Point; D];
+
+type Edge<const D: usize> = [Vert<D>; 2];
+
+/// A cycle on the cube of level 1, through both edges `DISCOCUBE_2` has in it.
+const CUBE: [[Point; 3]; 8] = [
+    [1, 1, 1],
+    [-1, 1, 1],
+    [-1, 1, -1],
+    [1, 1, -1],
+    [1, -1, -1],
+    [-1, -1, -1],
+    [-1, -1, 1],
+    [1, -1, 1],
+];
+
+/// A cycle on the discocube of level 2 that crosses the cube inside it twice.
+const DISCOCUBE_2: [[Point; 3]; 32] = [
+    [1, 1, 1],
+    [3, 1, 1],
+    [3, -1, 1],
+    [3, -1, -1],
+    [3, 1, -1],
+    [1, 1, -1],
+    [-1, 1, -1],
+    [-3, 1, -1],
+    [-3, 1, 1],
+    [-3, -1, 1],
+    [-3, -1, -1],
+    [-1, -1, -1],
+    [-1, -1, -3],
+    [-1, 1, -3],
+    [1, 1, -3],
+    [1, -1, -3],
+    [1, -1, -1],
+    [1, -3, -1],
+    [-1, -3, -1],
+    [-1, -3, 1],
+    [1, -3, 1],
+    [1, -1, 1],
+    [-1, -1, 1],
+    [-1, -1, 3],
+    [1, -1, 3],
+    [1, 1, 3],
+    [-1, 1, 3],
+    [-1, 1, 1],
+    [-1, 3, 1],
+    [-1, 3, -1],
+    [1, 3, -1],
+    [1, 3, 1],
+];
+
+/// The 2·D moves between neighbours, 2 either way along every axis, as `shift_xyz` has them in three.
+pub fn shifts<const D: usize>() -> Vec<Vert<D>> {
+    (0..2 * D)
+        .map(|i| {
+            let mut shift = [0; D];
+            shift[i / 2] = if i % 2 == 0 { 2 } else { -2 };
+            shift
+        })
+        .collect()
+}
+
+/// The largest sum of absolute coordinates in the ball of level n: 2n + D - 2, which makes level 1 the
+/// cube 2 across and the ball of three dimensions the discocube.
+pub fn reach<const D: usize>(n: u32) -> Point {
+    2 * n as Point + D as Point - 2
+}
+
+pub fn absumv<const D: usize>(vert: Vert<D>) -> Point {
+    vert.iter().map(|coord| coord.abs()).sum()
+}
+
+/// The points of the ball of level n, the odd points within its reach, in the order of their
+/// coordinates.
+pub fn ball<const D: usize>(n: u32) -> Vec<Vert<D>> {
+    let reach = reach::<D>(n);
+    let mut points = vec![[0; D]];
+    for axis in 0..D {
+        // every coordinate after this one takes at least 1 of the reach.
+        let left = (D - axis - 1) as Point;
+        points = points
+            .into_iter()
+            .flat_map(|point| {
+                let rest = reach - absumv(point) - left;
+                (-rest..=rest)
+                    .filter(|coord| coord % 2 != 0)
+                    .map(move |coord| {
+                        let mut point = point;
+                        point[axis] = coord;
+                        point
+                    })
+            })
+            .collect();
+    }
+    points
+}
+
+/// The ball of level n as a graph: its points, the number of every point and their neighbours.
+pub fn make_ball<const D: usize>(n: u32) -> (Vec<Vert<D>>, HashMap<Vert<D>, Node>, Adjacency) {
+    let points = ball::<D>(n);
+    let vi_map: HashMap<Vert<D>, Node> = points
+        .iter()
+        .enumerate()
+        .map(|(i, &point)| (point, i as Node))
+        .collect();
+    let shifts = shifts::<D>();
+    let adj = points
+        .iter()
+        .enumerate()
+        .map(|(i, &point)| {
+            let neighbors = shifts
+                .iter()
+                .filter_map(|shift| vi_map.get(&step(point, shift)).copied())
+                .collect();
+            (i as Node, neighbors)
+        })
+        .collect();
+    (points, vi_map, adj)
+}
+
+/// A hamiltonian cycle on the ball of level n, plaited a dimension at a time from the discocubes. Cut
+/// across its last axis at 1, 3, .. and -1, -3, .., the ball of level n falls into balls of one
+/// dimension fewer, of levels 1, 2, .. n, n, .. 2, 1. Their cycles are laid in their slices and tied
+/// together by `Tie`s, the middle two, which are alike, across a unit square both have an edge along.
+///
+/// The plane does not weave: in the ball of level n the outermost points have their two neighbours
+/// on the ring inside them and the rings close on themselves, leaving a cycle only up to level 2.
+pub fn plait<const D: usize>(n: u32) -> Result<Vec<Vert<D>>, &'static str> {
+    match D {
+        0 | 1 => Err("a ball of fewer than two dimensions has no cycle"),
+        2 => plane(n),
+        _ => plait_all::<D>(n, D).map(|mut cycles| cycles.pop().unwrap()),
+    }
+}
+
+/// The cycle on the ball of level n in the plane of the first two dimensions of D, the square at
+/// level 1 and the ring at level 2.
+fn plane<const D: usize>(n: u32) -> Result<Vec<Vert<D>>, &'static str> {
+    let ring: &[[Point; 2]] = match n {
+        1 => &[[1, 1], [-1, 1], [-1, -1], [1, -1]],
+        2 => &[
+            [1, 3],
+            [1, 1],
+            [3, 1],
+            [3, -1],
+            [1, -1],
+            [1, -3],
+            [-1, -3],
+            [-1, -1],
+            [-3, -1],
+            [-3, 1],
+            [-1, 1],
+            [-1, 3],
+        ],
+        _ => return Err("the outer two rings of the plane close on themselves"),
+    };
+    Ok(ring.iter().map(|point| lift(point)).collect())
+}
+
+/// How the cycles of two slices of levels m and m + 1 next to each other are tied into one, across
+/// a unit square (u, v, x, w). The cycle of level m gives up the edge (u, v) for the edges across from
+/// u and v to the other slice, and the cycle of level m + 1 gives up (u, v) too when it has it. Cycles
+/// of levels one apart seldom share an edge though, the one of level m + 1 pairing its shells the
+/// other way round, so the cycle of level m more often gives up (w, x) as well, and the cycle of level
+/// m + 1 gives up (u, w) and (v, x), run opposite ways round it, for the edges across from all four.
+#[derive(Clone, Copy)]
+struct Tie<const D: usize> {
+    edge: Edge<D>,
+    across: Option<Edge<D>>,
+}
+
+/// The cycles on the balls of levels 1 to n in the first dims dimensions of D, the discocubes in
+/// three. The discocubes of levels 1 and 2 are drawn, for the woven cycle of level 2 has no edge in
+/// the cube of level 1 to tie it by, and the rest are woven.
+fn plait_all<const D: usize>(n: u32, dims: usize) -> Result<Vec<Vec<Vert<D>>>, &'static str> {
+    if dims == 3 {
+        return Ok((1..=n)
+            .map(|level| match level {
+                1 => CUBE.iter().map(|point| lift(point)).collect(),
+                2 => DISCOCUBE_2.iter().map(|point| lift(point)).collect(),
+                _ => {
+                    let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(level);
+                    let woven = weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz);
+                    woven
+                        .iter()
+                        .map(|&node| {
+                            let (x, y, z) = verts[node as usize];
+                            lift(&[x, y, z])
+                        })
+                        .collect()
+                }
+            })
+            .collect());
+    }
+    let axis = dims - 1;
+    let slices = plait_all::<D>(n, axis)?;
+    // the edges every slice is left with once tied to the slices of the levels below and above.
+    let mut left: Vec<HashSet<Edge<D>>> = slices.iter().map(|cycle| edges(cycle)).collect();
+    // ties[m] ties the slices of levels m + 1 and m + 2.
+    let mut ties: Vec<Tie<D>> = Vec::with_capacity(n as usize);
+    for m in 1..n as usize {
+        let tie = tie(&left[m - 1], &slices[m], axis)
+            .ok_or("two cycles a dimension down could not be tied")?;
+        let [u, v] = tie.edge;
+        left[m - 1].remove(&tie.edge);
+        match tie.across {
+            None => {
+                left[m].remove(&tie.edge);
+            }
+            Some([w, x]) => {
+                left[m - 1].remove(&[w, x]);
+                left[m].remove(&[u.min(w), u.max(w)]);
+                left[m].remove(&[v.min(x), v.max(x)]);
+            }
+        }
+        ties.push(tie);
+    }
+    Ok((1..=n as usize)
+        .map(|level| {
+            let middle = *left[level - 1].iter().min().unwrap();
+            layer(&slices, &ties, middle, level, axis)
+        })
+        .collect())
+}
+
+/// A tie between the edges left in the slice below and the cycle of the slice above, taking the
+/// first edge left that will do.
+fn tie<const D: usize>(left: &HashSet<Edge<D>>, cycle: &[Vert<D>], dims: usize) -> Option<Tie<D>> {
+    let at: HashMap<Vert<D>, usize> = cycle
+        .iter()
+        .enumerate()
+        .map(|(i, &point)| (point, i))
+        .collect();
+    let len = cycle.len();
+    // the point after the one given, the way round the cycle given.
+    let next = |point: &Vert<D>, way: usize| at.get(point).map(|&i| cycle[(i + way) % len]);
+    let shifts = shifts::<D>();
+    left.iter().copied().sorted().find_map(|[u, v]| {
+        if [1, len - 1].into_iter().any(|way| next(&u, way) == Some(v)) {
+            return Some(Tie {
+                edge: [u, v],
+                across: None,
+            });
+        }
+        shifts[..2 * dims]
+            .iter()
+            .map(|shift| [step(u, shift), step(v, shift)])
+            .find(|&[w, x]| {
+                left.contains(&[w, x])
+                    && [1, len - 1]
+                        .into_iter()
+                        .any(|way| next(&u, way) == Some(w) && next(&x, way) == Some(v))
+            })
+            .map(|across| Tie {
+                edge: [u, v],
+                across: Some(across),
+            })
+    })
+}
+
+/// Lays the cycles of the slices of the ball of the level given across the axis and ties them into
+/// one.
+fn layer<const D: usize>(
+    slices: &[Vec<Vert<D>>],
+    ties: &[Tie<D>],
+    middle: Edge<D>,
+    level: usize,
+    axis: usize,
+) -> Vec<Vert<D>> {
+    let heights: Vec<Point> = (-(2 * level as Point - 1)..2 * level as Point)
+        .step_by(2)
+        .collect();
+    let slice_level = |height: Point| level - (height.unsigned_abs() as usize - 1) / 2;
+    let at = |mut point: Vert<D>, height: Point| {
+        point[axis] = height;
+        point
+    };
+    let mut links: HashMap<Vert<D>, [Vert<D>; 2]> = HashMap::new();
+    for &height in &heights {
+        let cycle = &slices[slice_level(height) - 1];
+        for (i, &point) in cycle.iter().enumerate() {
+            let after = cycle[(i + 1) % cycle.len()];
+            let before = cycle[(i + cycle.len() - 1) % cycle.len()];
+            links.insert(at(point, height), [at(after, height), at(before, height)]);
+        }
+    }
+    let mut relink = |point: Vert<D>, from: Vert<D>, to: Vert<D>| {
+        let links = links.get_mut(&point).unwrap();
+        let slot = (links[1] == from) as usize;
+        links[slot] = to;
+    };
+    for pair in heights.windows(2) {
+        let (low, high) = (slice_level(pair[0]), slice_level(pair[1]));
+        // the slice of the lower level, and the one of the higher.
+        let (small, large) = match low <= high {
+            true => (pair[0], pair[1]),
+            false => (pair[1], pair[0]),
+        };
+        let Tie {
+            edge: [u, v],
+            across,
+        } = match low == high {
+            true => Tie {
+                edge: middle,
+                across: None,
+            },
+            false => ties[low.min(high) - 1],
+        };
+        let [us, vs, ul, vl] = [at(u, small), at(v, small), at(u, large), at(v, large)];
+        relink(us, vs, ul);
+        relink(vs, us, vl);
+        match across {
+            None => {
+                relink(ul, vl, us);
+                relink(vl, ul, vs);
+            }
+            Some([w, x]) => {
+                let [ws, xs, wl, xl] = [at(w, small), at(x, small), at(w, large), at(x, large)];
+                relink(ws, xs, wl);
+                relink(xs, ws, xl);
+                relink(ul, wl, us);
+                relink(vl, xl, vs);
+                relink(wl, ul, ws);
+                relink(xl, vl, xs);
+            }
+        }
+    }
+    let start = at(slices[0][0], heights[0]);
+    let mut cycle = vec![start];
+    let (mut prev, mut point) = (start, links[&start][0]);
+    while point != start {
+        cycle.push(point);
+        let next = match links[&point][0] == prev {
+            true => links[&point][1],
+            false => links[&point][0],
+        };
+        (prev, point) = (point, next);
+    }
+    cycle
+}
+
+/// The edges of a cycle, each with its lesser end first.
+fn edges<const D: usize>(cycle: &[Vert<D>]) -> HashSet<Edge<D>> {
+    cycle
+        .iter()
+        .enumerate()
+        .map(|(i, &point)| {
+            let next = cycle[(i + 1) % cycle.len()];
+            [point.min(next), point.max(next)]
+        })
+        .collect()
+}
+
+fn step<const D: usize>(mut point: Vert<D>, shift: &Vert<D>) -> Vert<D> {
+    point
+        .iter_mut()
+        .zip(shift)
+        .for_each(|(coord, by)| *coord += by);
+    point
+}
+
+/// The point of D dimensions with the coordinates given first and 0 for the rest.
+fn lift<const D: usize>(coords: &[Point]) -> Vert<D> {
+    let mut point = [0; D];
+    point[..coords.len()].copy_from_slice(coords);
+    point
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{absumv, ball, make_ball, plait, reach, shifts, Vert};
+    use crate::graph::utils::{
+        certify::{id_seq, SequenceID},
+        make::make_graph,
+    };
+
+    /// Checks the cycle plaited on the ball of level n against the ball.
+    fn plaits<const D: usize>(n: u32) -> bool {
+        let cycle: Vec<Vert<D>> = plait::<D>(n).unwrap();
+        let (_, vi_map, adj) = make_ball::<D>(n);
+        let seq = cycle.iter().map(|point| vi_map[point]).collect();
+        id_seq(&seq, &adj) == SequenceID::HamCycle
+    }
+
+    #[test]
+    fn makes_the_discocube_in_three_dimensions() {
+        for n in 1..=8 {
+            let (_, order, verts, ..) = make_graph(n);
+            let points = ball::<3>(n);
+            assert_eq!(points.len(), order as usize);
+            assert!(points.iter().all(|&[x, y, z]| verts.contains(&(x, y, z))));
+        }
+    }
+
+    #[test]
+    fn keeps_the_ball_within_its_reach_on_odd_points() {
+        for n in 1..=4 {
+            let points = ball::<4>(n);
+            assert!(points
+                .iter()
+                .all(|point| point.iter().all(|coord| coord % 2 != 0)));
+            assert!(points.iter().all(|&point| absumv(point) <= reach::<4>(n)));
+        }
+        assert_eq!(ball::<4>(1).len(), 16);
+        assert_eq!(shifts::<5>().len(), 10);
+    }
+
+    #[test]
+    fn closes_the_plane_only_up_to_level_two() {
+        assert!(plaits::<2>(1));
+        assert!(plaits::<2>(2));
+        assert!(plait::<2>(3).is_err());
+        assert!(plait::<1>(1).is_err());
+    }
+
+    #[test]
+    fn plaits_a_hamiltonian_cycle_in_three_to_five_dimensions() {
+        for n in 1..=6 {
+            assert!(plaits::<3>(n), "3D level {n}");
+        }
+        for n in 1..=6 {
+            assert!(plaits::<4>(n), "4D level {n}");
+        }
+        for n in 1..=5 {
+            assert!(plaits::<5>(n), "5D level {n}");
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::{
+    cuboid::{is_black, Obstruction},
+    defs::{Adjacency, Node, Solution, VIMap, Verts},
+    factor::{complete, knot, UNSET},
+    polycube::{deficient, neighbors, rule_out, squares},
+    skein::Skein,
+    utils::make::make_graph,
+    weave::weave,
+};
+
+/// How many rotations a try at splicing cycles in is given, and how many tries there are at least.
+const TURNS: usize = 1 << 6;
+const TRIES: usize = 1 << 12;
+
+/// Mends the woven cycle of the discocube of level n around its holes, the graph given being the
+/// discocube made by `make::make_holed`. The cheap reasons for there being no cycle are ruled out
+/// first. The discocube is woven whole and the holes are cut out of its cycle, which leaves the cells
+/// next to them short of links. The links are laid anew by a flow that starts from the woven ones,
+/// so that only those along the shortest paths between the frayed cells are swapped, the cycles
+/// this cuts the woven one into are knotted across unit squares, and whichever are left are spliced
+/// into the largest by `splice`. A failing flow proves there is no 2-factor. The weave and every try
+/// at splicing are seeded alike on every run, so the same holes always give the same answer.
+pub fn mend(
+    n: u32,
+    adj: &Adjacency,
+    verts: &Verts,
+    vi_map: &VIMap,
+) -> Result<Solution, Obstruction> {
+    let neighbors = neighbors(adj);
+    rule_out(&neighbors, verts)?;
+    let order = verts.len();
+    let black: Vec<bool> = verts.iter().map(|&vert| is_black(vert)).collect();
+    let (_, _, whole_verts, whole_map, whole_adj, z_adj, z_order, min_xyz) = make_graph(n);
+    let woven: Vec<Option<usize>> = weave(
+        &whole_adj,
+        &whole_map,
+        &whole_verts,
+        z_adj,
+        z_order,
+        min_xyz,
+    )
+    .into_iter()
+    .map(|node| {
+        vi_map
+            .get(&whole_verts[node as usize])
+            .map(|&node| node as usize)
+    })
+    .collect();
+    let mut links = vec![[UNSET; 2]; order];
+    for (i, &node) in woven.iter().enumerate() {
+        let Some(node) = node else { continue };
+        let after = woven[(i + 1) % woven.len()];
+        let before = woven[(i + woven.len() - 1) % woven.len()];
+        links[node] = [after.unwrap_or(UNSET), before.unwrap_or(UNSET)];
+    }
+    if let Err(reached) = complete(&neighbors, &black, &mut links) {
+        return Err(deficient(reached, verts));
+    }
+    knot(&mut links, &squares(verts, vi_map));
+    match splice(&neighbors, verts, cycles(&links)) {
+        Ok(cycle) => Ok(cycle.into_iter().map(|node| node as Node).collect()),
+        Err(cycles) => Err(Obstruction::Stuck { cycles }),
+    }
+}
+
+/// The cycles of a 2-factor, each in the order of its nodes.
+fn cycles(links: &[[usize; 2]]) -> Vec<Vec<usize>> {
+    let mut seen = vec![false; links.len()];
+    let mut cycles = Vec::new();
+    for start in 0..links.len() {
+        if seen[start] {
+            continue;
+        }
+        let mut cycle = vec![start];
+        seen[start] = true;
+        let (mut prev, mut node) = (start, links[start][0]);
+        while node != start {
+            seen[node] = true;
+            cycle.push(node);
+            let next = match links[node][0] == prev {
+                true => links[node][1],
+                false => links[node][0],
+            };
+            (prev, node) = (node, next);
+        }
+        cycles.push(cycle);
+    }
+    cycles
+}
+
+/// Splices the cycles of a 2-factor into the largest, one at a time by `absorb`, from each node of
+/// the largest next to another cycle in turn until one of them takes. The cycles are wound on one
+/// skein, the largest ahead of the rest, so that every try takes time logarithmic in the order.
+/// When every try fails, returns how many cycles are left.
+fn splice(
+    neighbors: &[Vec<usize>],
+    verts: &Verts,
+    mut cycles: Vec<Vec<usize>>,
+) -> Result<Vec<usize>, usize> {
+    let order = neighbors.len();
+    cycles.sort_by_key(|cycle| std::cmp::Reverse(cycle.len()));
+    let (mut of, mut at) = (vec![0; order], vec![0; order]);
+    for (id, cycle) in cycles.iter().enumerate() {
+        for (i, &node) in cycle.iter().enumerate() {
+            (of[node], at[node]) = (id, i);
+        }
+    }
+    let tour: Vec<Node> = cycles.iter().flatten().map(|&node| node as Node).collect();
+    let mut skein = Skein::new(&tour, 0);
+    let (mut len, mut tries) = (cycles[0].len(), 0);
+    while len < order {
+        let mut openings: Vec<usize> = (0..order)
+            .filter(|&node| of[node] != 0)
+            .flat_map(|node| neighbors[node].iter().copied())
+            .filter(|&next| of[next] == 0)
+            .collect();
+        openings.sort_unstable();
+        openings.dedup();
+        let taken = openings
+            .iter()
+            .cycle()
+            .take(openings.len().max(TRIES))
+            .find_map(|&open| {
+                tries += 1;
+                let mut rng = StdRng::seed_from_u64(tries);
+                absorb(
+                    neighbors,
+                    verts,
+                    (&mut skein, len),
+                    open,
+                    (&of, &at),
+                    &cycles,
+                    &mut rng,
+                )
+            });
+        let Some(taken) = taken else {
+            let mut left: Vec<usize> = of.iter().copied().filter(|&id| id != 0).collect();
+            left.sort_unstable();
+            left.dedup();
+            return Err(left.len() + 1);
+        };
+        for id in taken {
+            cycles[id].iter().for_each(|&node| of[node] = 0);
+            len += cycles[id].len();
+        }
+    }
+    Ok(skein
+        .to_vec()
+        .into_iter()
+        .map(|node| node as usize)
+        .collect())
+}
+
+/// Absorbs the cycles next to `open`, a node of the largest cycle, which is the first `len` nodes of
+/// the skein, by Pósa's rotations and extensions, and returns the cycles it took in. The largest is
+/// turned into a path ending at `open`, and the path is grown from its end: onto the whole of
+/// another cycle, when the end is next to one, or else by rotating, linking the end to a neighbour
+/// further back on the path and unlinking that neighbour from the node after it, which becomes the
+/// end. Once it has grown, it is rotated until the end is next to the start, closing it again. Every
+/// step swaps an edge next to the end, which stays near the start, the rotation drawn half the time
+/// among those that bring the end nearest to it. The other cycles stay whole behind the path, and
+/// the skein is wound back when the `TURNS` run out. `of` and `at` give the cycle of every node and
+/// where on it the node is, 0 being the largest.
+fn absorb(
+    neighbors: &[Vec<usize>],
+    verts: &Verts,
+    (skein, mut len): (&mut Skein, usize),
+    open: usize,
+    (of, at): (&[usize], &[usize]),
+    cycles: &[Vec<usize>],
+    rng: &mut StdRng,
+) -> Option<Vec<usize>> {
+    let closed = len;
+    let split = skein.position(open as Node);
+    if split + 1 < len {
+        skein.reverse(0, split);
+        skein.reverse(split + 1, len - 1);
+        skein.reverse(0, len - 1);
+    }
+    let (start, mut end) = (skein.node_at(0) as usize, open);
+    let (mut taken, mut wound): (Vec<usize>, Vec<(usize, usize)>) = (Vec::new(), Vec::new());
+    let mut reverse = |skein: &mut Skein, from: usize, to: usize| {
+        skein.reverse(from, to);
+        wound.push((from, to));
+    };
+    let apart = |a: usize, b: usize| {
+        let ((ax, ay, az), (bx, by, bz)) = (verts[a], verts[b]);
+        (ax - bx).abs() + (ay - by).abs() + (az - bz).abs()
+    };
+    for _ in 0..TURNS {
+        if len > closed && neighbors[end].contains(&start) {
+            return Some(taken);
+        }
+        let next = neighbors[end]
+            .iter()
+            .find(|&&node| of[node] != 0 && !taken.contains(&of[node]));
+        if let Some(&next) = next {
+            let cycle = &cycles[of[next]];
+            let (m, k) = (cycle.len(), at[next]);
+            let first = skein.position(cycle[0] as Node);
+            if k > 0 {
+                reverse(skein, first, first + k - 1);
+                reverse(skein, first + k, first + m - 1);
+                reverse(skein, first, first + m - 1);
+            }
+            if first > len {
+                reverse(skein, len, first - 1);
+                reverse(skein, first, first + m - 1);
+                reverse(skein, len, first + m - 1);
+            }
+            taken.push(of[next]);
+            (end, len) = (cycle[(k + m - 1) % m], len + m);
+            continue;
+        }
+        let turns: Vec<usize> = neighbors[end]
+            .iter()
+            .map(|&node| skein.position(node as Node) + 1)
+            .filter(|&i| i + 1 < len)
+            .collect();
+        let near: Vec<i16> = turns
+            .iter()
+            .map(|&i| apart(skein.node_at(i) as usize, start))
+            .collect();
+        let Some(&closest) = near.iter().min() else {
+            break;
+        };
+        let turn = match rng.gen_bool(0.5) {
+            true => turns[rng.gen_range(0..turns.len())],
+            false => turns[near.iter().position(|&near| near == closest).unwrap()],
+        };
+        reverse(skein, turn, len - 1);
+        end = skein.node_at(len - 1) as usize;
+    }
+    wound
+        .into_iter()
+        .rev()
+        .for_each(|(from, to)| skein.reverse(from, to));
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cycles, mend};
+    use crate::graph::{
+        cuboid::Obstruction,
+        utils::{
+            certify::{id_seq, SequenceID},
+            make::make_holed,
+        },
+    };
+
+    #[test]
+    fn mends_the_cycle_around_two_holes() {
+        for level in 3..=8 {
+            let (_, verts, vi_map, adj) = make_holed(level, &[(1, 1, 1), (1, 1, 3)]);
+            let cycle = mend(level, &adj, &verts, &vi_map).unwrap();
+            assert_eq!(id_seq(&cycle, &adj), SequenceID::HamCycle, "level {level}");
+        }
+    }
+
+    #[test]
+    fn mends_the_same_cycle_every_time() {
+        let holes = [(5, 7, 9), (5, 7, 11), (-3, 1, 1), (-1, 1, 1)];
+        let (_, verts, vi_map, adj) = make_holed(12, &holes);
+        let first = mend(12, &adj, &verts, &vi_map);
+        assert!(first.is_ok());
+        assert_eq!(first, mend(12, &adj, &verts, &vi_map));
+    }
+
+    #[test]
+    fn proves_a_single_hole_unbalanced() {
+        let (_, verts, vi_map, adj) = make_holed(4, &[(1, 1, 1)]);
+        let obstruction = mend(4, &adj, &verts, &vi_map).unwrap_err();
+        assert!(matches!(obstruction, Obstruction::Unbalanced { .. }));
+        assert!(obstruction.is_proof());
+        assert!(obstruction.certify(&adj, &verts));
+    }
+
+    #[test]
+    fn follows_every_cycle_of_a_2_factor() {
+        let links = [[1, 2], [2, 0], [0, 1], [4, 6], [5, 3], [6, 4], [3, 5]];
+        let found = cycles(&links);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].len(), 3);
+        assert_eq!(found[1].len(), 4);
+        assert_eq!(found.concat().len(), links.len());
+    }
+}
@@ -1,3 +1,30 @@
+pub mod balance;
+pub mod canonical;
+pub mod cuboid;
+pub mod darn;
 pub mod defs;
+pub mod factor;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod formats;
+pub mod grow;
+pub mod hexprism;
+#[cfg(feature = "petgraph")]
+pub mod interop;
+pub mod lattice;
+pub mod mend;
+pub mod npy;
+pub mod par;
+pub mod polish;
+pub mod polycube;
+pub mod polyomino;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod report;
+pub mod sample;
+pub mod shape;
+pub mod skein;
+pub mod tsplib;
+pub mod turn;
 pub mod utils;
 pub mod weave;
@@ -0,0 +1,285 @@
+use std::io::{self, Write};
+
+use ndarray::{Array1, Array2, ArrayBase, Data, Dimension};
+
+use super::{
+    defs::{Adjacency, Node, Solution, Verts},
+    formats::edges,
+};
+
+/// A number NumPy can hold, written as it is in little-endian bytes.
+pub trait Element: Copy {
+    /// NumPy's description of the type, as in `<i2`.
+    const DESCR: &'static str;
+
+    fn put(self, bytes: &mut Vec<u8>);
+}
+
+impl Element for i16 {
+    const DESCR: &'static str = "<i2";
+
+    fn put(self, bytes: &mut Vec<u8>) {
+        bytes.extend(self.to_le_bytes());
+    }
+}
+
+impl Element for u32 {
+    const DESCR: &'static str = "<u4";
+
+    fn put(self, bytes: &mut Vec<u8>) {
+        bytes.extend(self.to_le_bytes());
+    }
+}
+
+impl Element for u64 {
+    const DESCR: &'static str = "<u8";
+
+    fn put(self, bytes: &mut Vec<u8>) {
+        bytes.extend(self.to_le_bytes());
+    }
+}
+
+/// The array in NumPy's `.npy` format, version 1.0: the magic string, a header naming the type and
+/// shape padded with spaces to a multiple of 64 bytes, then the elements in C order.
+pub fn to_npy<T, S, D>(array: &ArrayBase<S, D>) -> Vec<u8>
+where
+    T: Element,
+    S: Data<Elem = T>,
+    D: Dimension,
+{
+    let shape = match array.shape() {
+        [len] => format!("({len},)"),
+        shape => format!(
+            "({})",
+            shape
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {shape}, }}",
+        T::DESCR
+    );
+    // the magic string, the version and the header's length take 10 bytes, the header ends in \n.
+    header += &" ".repeat(63 - (10 + header.len()) % 64);
+    header.push('\n');
+    let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+    bytes.extend((header.len() as u16).to_le_bytes());
+    bytes.extend(header.into_bytes());
+    array.iter().for_each(|&element| element.put(&mut bytes));
+    bytes
+}
+
+/// The vertices as an (N, 3) array of their coordinates.
+pub fn verts_array(verts: &Verts) -> Array2<i16> {
+    let flat = verts.iter().flat_map(|&(x, y, z)| [x, y, z]).collect();
+    Array2::from_shape_vec((verts.len(), 3), flat).unwrap()
+}
+
+/// The tour as an (N,) array of its nodes in order.
+pub fn tour_array(tour: &Solution) -> Array1<Node> {
+    Array1::from(tour.clone())
+}
+
+/// The edges as an (E, 2) array, each once with its lesser end first, in order.
+pub fn edges_array(adj: &Adjacency) -> Array2<Node> {
+    let edges = edges(adj, false);
+    let flat = edges
+        .iter()
+        .flat_map(|&(node, next)| [node, next])
+        .collect();
+    Array2::from_shape_vec((edges.len(), 2), flat).unwrap()
+}
+
+/// The arrays named in NumPy's `.npz` format, as `numpy.savez` writes it: a zip archive holding
+/// each as `name.npy`, stored without compression. An archive of 4 GiB or more would need zip64,
+/// which is not written.
+pub fn to_npz(arrays: &[(&str, Vec<u8>)]) -> Result<Vec<u8>, &'static str> {
+    let mut bytes = vec![];
+    let mut arrays = arrays.iter().map(|(name, npy)| (*name, npy.clone()));
+    write_npz(&mut bytes, &mut arrays).or(Err("the .npz archive is too large"))?;
+    Ok(bytes)
+}
+
+/// Writes the arrays as `to_npz` has them, each `.npy` made only when its turn comes and let go once
+/// it is written, so that no more than one is held at a time.
+pub fn write_npz(
+    out: &mut dyn Write,
+    arrays: &mut dyn Iterator<Item = (&str, Vec<u8>)>,
+) -> io::Result<()> {
+    let too_large = || io::Error::new(io::ErrorKind::InvalidData, "the .npz archive is too large");
+    let (mut written, mut count) = (0usize, 0u16);
+    let mut directory = vec![];
+    for (name, npy) in arrays {
+        let name = format!("{name}.npy");
+        let offset = u32::try_from(written).or(Err(too_large()))?;
+        let size = u32::try_from(npy.len()).or(Err(too_large()))?;
+        // version 2.0, no flags, stored, at midnight on the first of January 1980.
+        let fields = [20u16, 0, 0, 0, 0x21]
+            .iter()
+            .flat_map(|field| field.to_le_bytes())
+            .chain(crc32(&npy).to_le_bytes())
+            .chain(size.to_le_bytes())
+            .chain(size.to_le_bytes())
+            .chain((name.len() as u16).to_le_bytes())
+            .chain(0u16.to_le_bytes())
+            .collect::<Vec<u8>>();
+        for part in [
+            &0x04034b50u32.to_le_bytes(),
+            &fields[..],
+            name.as_bytes(),
+            &npy,
+        ] {
+            out.write_all(part)?;
+            written += part.len();
+        }
+        directory.extend(0x02014b50u32.to_le_bytes());
+        directory.extend(20u16.to_le_bytes());
+        directory.extend(&fields);
+        // no comment, on the first disk, no attributes, and where the local header is.
+        directory.extend([0u8; 10]);
+        directory.extend(offset.to_le_bytes());
+        directory.extend(name.as_bytes());
+        count += 1;
+    }
+    let offset = u32::try_from(written).or(Err(too_large()))?;
+    let size = directory.len() as u32;
+    directory.extend(0x06054b50u32.to_le_bytes());
+    directory.extend([0u8; 4]);
+    directory.extend(count.to_le_bytes());
+    directory.extend(count.to_le_bytes());
+    directory.extend(size.to_le_bytes());
+    directory.extend(offset.to_le_bytes());
+    directory.extend(0u16.to_le_bytes());
+    out.write_all(&directory)
+}
+
+/// The CRC-32 zip checks every file by, bit by bit.
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| match crc & 1 {
+            1 => (crc >> 1) ^ 0xedb88320,
+            _ => crc >> 1,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{crc32, edges_array, to_npy, to_npz, tour_array, verts_array};
+    use crate::graph::{utils::make::make_graph, weave::weave};
+
+    fn u16_at(bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap())
+    }
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    /// The header of a `.npy` file and the elements after it.
+    fn split_npy(npy: &[u8]) -> (&str, &[u8]) {
+        assert_eq!(&npy[..8], b"\x93NUMPY\x01\x00");
+        let end = 10 + u16_at(npy, 8) as usize;
+        (std::str::from_utf8(&npy[10..end]).unwrap(), &npy[end..])
+    }
+
+    #[test]
+    fn pads_the_header_so_the_elements_start_on_64_bytes() {
+        let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(3);
+        let tour = weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz);
+        for (npy, size) in [
+            (to_npy(&verts_array(&verts)), verts.len() * 3 * 2),
+            (to_npy(&tour_array(&tour)), tour.len() * 4),
+            (
+                to_npy(&edges_array(&adj)),
+                adj.values().map(|next| next.len()).sum::<usize>() * 4,
+            ),
+        ] {
+            let (header, elements) = split_npy(&npy);
+            assert_eq!((10 + header.len()) % 64, 0);
+            assert!(header.ends_with(" \n"));
+            assert_eq!(elements.len(), size);
+        }
+    }
+
+    #[test]
+    fn describes_the_type_and_shape_of_each_array() {
+        let (_, order, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(2);
+        let tour = weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz);
+        let edges = adj.values().map(|next| next.len()).sum::<usize>() / 2;
+        for (npy, dict) in [
+            (
+                to_npy(&verts_array(&verts)),
+                format!("{{'descr': '<i2', 'fortran_order': False, 'shape': ({order}, 3), }}"),
+            ),
+            (
+                to_npy(&tour_array(&tour)),
+                format!("{{'descr': '<u4', 'fortran_order': False, 'shape': ({order},), }}"),
+            ),
+            (
+                to_npy(&edges_array(&adj)),
+                format!("{{'descr': '<u4', 'fortran_order': False, 'shape': ({edges}, 2), }}"),
+            ),
+        ] {
+            assert_eq!(split_npy(&npy).0.trim_end(), dict);
+        }
+        let npy = to_npy(&verts_array(&verts));
+        let (_, elements) = split_npy(&npy);
+        let (x, y, z) = verts[0];
+        let first = [x, y, z].map(i16::to_le_bytes).concat();
+        assert_eq!(&elements[..6], &first[..]);
+    }
+
+    #[test]
+    fn checks_the_standard_string_as_zip_does() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn lays_out_the_local_headers_and_the_end_of_the_directory() {
+        let arrays = [("a", vec![1u8, 2, 3]), ("bc", vec![4u8; 5])];
+        let npz = to_npz(&arrays).unwrap();
+        let mut at = 0;
+        let mut offsets = vec![];
+        for (name, npy) in &arrays {
+            let name = format!("{name}.npy");
+            offsets.push(at as u32);
+            assert_eq!(u32_at(&npz, at), 0x04034b50);
+            // version, flags, method, time and date.
+            let fields = [20, 0, 0, 0, 0x21]
+                .map(|field: u16| field.to_le_bytes())
+                .concat();
+            assert_eq!(&npz[at + 4..at + 14], &fields[..]);
+            assert_eq!(u32_at(&npz, at + 14), crc32(npy));
+            assert_eq!(u32_at(&npz, at + 18), npy.len() as u32);
+            assert_eq!(u32_at(&npz, at + 22), npy.len() as u32);
+            assert_eq!(u16_at(&npz, at + 26), name.len() as u16);
+            assert_eq!(u16_at(&npz, at + 28), 0);
+            assert_eq!(&npz[at + 30..at + 30 + name.len()], name.as_bytes());
+            at += 30 + name.len();
+            assert_eq!(&npz[at..at + npy.len()], &npy[..]);
+            at += npy.len();
+        }
+        let directory = at;
+        for (name, offset) in ["a.npy", "bc.npy"].iter().zip(offsets) {
+            assert_eq!(u32_at(&npz, at), 0x02014b50);
+            assert_eq!(u16_at(&npz, at + 4), 20);
+            assert_eq!(u16_at(&npz, at + 6), 20);
+            assert_eq!(u32_at(&npz, at + 42), offset);
+            assert_eq!(&npz[at + 46..at + 46 + name.len()], name.as_bytes());
+            at += 46 + name.len();
+        }
+        let end = &npz[at..];
+        assert_eq!(end.len(), 22);
+        assert_eq!(u32_at(end, 0), 0x06054b50);
+        assert_eq!((u16_at(end, 4), u16_at(end, 6)), (0, 0));
+        assert_eq!((u16_at(end, 8), u16_at(end, 10)), (2, 2));
+        assert_eq!(u32_at(end, 12), (at - directory) as u32);
+        assert_eq!(u32_at(end, 16), directory as u32);
+        assert_eq!(u16_at(end, 20), 0);
+    }
+}
@@ -0,0 +1,176 @@
+use std::{fmt, str::FromStr};
+
+use super::{
+    darn::{Darner, Objective},
+    defs::{Adjacency, Solution, VIMap, Verts},
+    utils::{
+        certify::{id_seq, SequenceID},
+        info::{min_axis_spread, run_lengths},
+    },
+};
+
+const SEED: u64 = 0;
+const ROUND: usize = 100_000;
+const ROUNDS: usize = 50;
+
+/// What to polish a finished cycle for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Finish {
+    /// Fewer nodes where the cycle goes straight on.
+    Turns,
+    /// Fewer straight runs of two or more edges.
+    Runs,
+    /// Edge counts along x, y and z as even as the order allows.
+    Balance,
+    /// The longest straight run as short as possible.
+    MaxRun,
+}
+
+impl FromStr for Finish {
+    type Err = &'static str;
+
+    fn from_str(finish: &str) -> Result<Self, Self::Err> {
+        match finish {
+            "turns" => Ok(Finish::Turns),
+            "runs" => Ok(Finish::Runs),
+            "balance" => Ok(Finish::Balance),
+            "max-run" => Ok(Finish::MaxRun),
+            _ => Err("unknown polish, expected one of: turns, runs, balance, max-run"),
+        }
+    }
+}
+
+impl fmt::Display for Finish {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finish::Turns => write!(f, "turns"),
+            Finish::Runs => write!(f, "runs"),
+            Finish::Balance => write!(f, "balance"),
+            Finish::MaxRun => write!(f, "max-run"),
+        }
+    }
+}
+
+/// Polishes a hamiltonian cycle with moves that keep it one: flips of unit squares whose opposite edges
+/// the cycle uses, which are the only 2-edge swaps a grid graph allows, and a flip that splits the cycle
+/// followed by one that joins it back. The darner works in rounds and the cycle is certified after
+/// every round, the best certified cycle is the one returned.
+pub fn polish(
+    solution: Solution,
+    adj: &Adjacency,
+    vi_map: &VIMap,
+    verts: &Verts,
+    finish: Finish,
+) -> Result<Solution, &'static str> {
+    match finish {
+        Finish::Turns => darn_rounds(solution, adj, vi_map, verts, Objective::Turns),
+        Finish::Runs => darn_rounds(solution, adj, vi_map, verts, Objective::Runs),
+        Finish::Balance => {
+            let tolerance = min_axis_spread(solution.len() as u32);
+            darn_rounds(solution, adj, vi_map, verts, Objective::Balance(tolerance))
+        }
+        Finish::MaxRun => {
+            // tighten the limit one edge at a time for as long as the darner can keep up.
+            let mut best = solution;
+            let mut limit = run_lengths(&best, verts).into_iter().max().unwrap_or(1);
+            while limit > 1 {
+                let objective = Objective::MaxRun(limit - 1);
+                let polished = darn_rounds(best.clone(), adj, vi_map, verts, objective)?;
+                if Darner::new(polished.clone(), verts, vi_map, objective, SEED).cost() > 0 {
+                    break;
+                }
+                (best, limit) = (polished, limit - 1);
+            }
+            Ok(best)
+        }
+    }
+}
+
+fn darn_rounds(
+    solution: Solution,
+    adj: &Adjacency,
+    vi_map: &VIMap,
+    verts: &Verts,
+    objective: Objective,
+) -> Result<Solution, &'static str> {
+    let mut darner = Darner::new(solution.clone(), verts, vi_map, objective, SEED);
+    let (mut best, mut best_cost) = (solution, darner.cost());
+    for _ in 0..ROUNDS {
+        if best_cost == 0 {
+            break;
+        }
+        let cost = darner.darn(0, ROUND);
+        let tour = darner.tour();
+        if id_seq(&tour, adj) != SequenceID::HamCycle {
+            return Err("polishing broke the hamiltonian cycle");
+        }
+        if cost < best_cost {
+            (best, best_cost) = (tour, cost);
+        }
+    }
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{polish, Finish};
+    use crate::graph::{
+        darn::{Darner, Objective},
+        defs::{TourSlice, Verts},
+        utils::{
+            certify::{id_seq, SequenceID},
+            info::{axis_counts, axis_spread, run_lengths},
+            make::make_graph,
+        },
+        weave::weave,
+    };
+
+    /// Straight nodes, straight runs, longest run and axis spread, the measures the finishes polish.
+    fn measures(tour: TourSlice, verts: &Verts) -> [usize; 4] {
+        let runs = run_lengths(tour, verts);
+        [
+            runs.iter().map(|len| len - 1).sum(),
+            runs.iter().filter(|&&len| len > 1).count(),
+            runs.iter().copied().max().unwrap_or(0),
+            axis_spread(&axis_counts(tour, verts)),
+        ]
+    }
+
+    #[test]
+    fn polishes_each_measure_without_breaking_the_cycle() {
+        let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(6);
+        let woven = weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz);
+        let before = measures(&woven, &verts);
+        for (idx, finish) in [Finish::Turns, Finish::Runs, Finish::MaxRun, Finish::Balance]
+            .into_iter()
+            .enumerate()
+        {
+            let polished = polish(woven.clone(), &adj, &vi_map, &verts, finish).unwrap();
+            assert_eq!(id_seq(&polished, &adj), SequenceID::HamCycle);
+            assert!(measures(&polished, &verts)[idx] <= before[idx]);
+        }
+    }
+
+    #[test]
+    fn costs_the_runs_and_their_overshoot() {
+        let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(6);
+        let woven = weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz);
+        let runs = run_lengths(&woven, &verts);
+        let darner = Darner::new(woven.clone(), &verts, &vi_map, Objective::Runs, 0);
+        assert_eq!(
+            darner.cost(),
+            runs.iter().filter(|&&len| len > 1).count() as i64
+        );
+        let darner = Darner::new(woven, &verts, &vi_map, Objective::MaxRun(2), 0);
+        let overshoot: usize = runs.iter().map(|len| len.saturating_sub(2)).sum();
+        assert_eq!(darner.cost(), overshoot as i64);
+    }
+
+    #[test]
+    fn reads_back_the_finishes_it_writes() {
+        for finish in [Finish::Turns, Finish::Runs, Finish::Balance, Finish::MaxRun] {
+            assert_eq!(finish.to_string().parse::<Finish>(), Ok(finish));
+        }
+        assert!("smooth".parse::<Finish>().is_err());
+    }
+}
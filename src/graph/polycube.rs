@@ -0,0 +1,257 @@
+use std::{fs, path::Path};
+
+use itertools::Itertools;
+
+use super::{
+    cuboid::{is_black, Obstruction},
+    defs::{Adjacency, Node, Point, Solution, VIMap, Verts},
+    factor::{cut_vertex, merge, reach, two_factor, UNSET},
+};
+
+/// A unit cube at integer coordinates, as read, before `make::make_polycube` puts it on odd ones.
+pub type Voxel = [Point; 3];
+
+/// Reads the voxels of a polycube from a MagicaVoxel `.vox` file, or from a text file holding either
+/// one `x y z` or `x,y,z` per line or a mask per layer, as in `from_text`.
+pub fn read(path: &str) -> Result<Vec<Voxel>, &'static str> {
+    let voxels = match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        Some("vox") => from_vox(&fs::read(path).or(Err("could not read the polycube file"))?)?,
+        _ => from_text(&fs::read_to_string(path).or(Err("could not read the polycube file"))?)?,
+    };
+    if voxels.is_empty() {
+        return Err("a polycube needs at least one voxel");
+    }
+    if voxels
+        .iter()
+        .flatten()
+        .any(|coord| !(-16_000..16_000).contains(coord))
+    {
+        return Err("voxel coordinates must lie between -16000 and 16000");
+    }
+    Ok(voxels)
+}
+
+/// Reads voxels from text: three integers per line, split by commas or spaces, or else masks with
+/// `#` for a voxel and `.` or a space for a gap, a line per row going up y and a mask per layer going
+/// up z, the layers split by blank lines.
+pub fn from_text(text: &str) -> Result<Vec<Voxel>, &'static str> {
+    let coords: Option<Vec<Voxel>> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let coords: Vec<Point> = line
+                .split(|char: char| char == ',' || char.is_whitespace())
+                .filter(|coord| !coord.is_empty())
+                .map(|coord| coord.parse().ok())
+                .collect::<Option<_>>()?;
+            <Voxel>::try_from(coords).ok()
+        })
+        .collect();
+    if let Some(voxels) = coords {
+        return Ok(voxels);
+    }
+    let mut voxels: Vec<Voxel> = Vec::new();
+    let (mut z, mut y) = (0, 0);
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            if y > 0 {
+                (z, y) = (z + 1, 0);
+            }
+            continue;
+        }
+        for (x, char) in line.chars().enumerate() {
+            match char {
+                '#' => voxels.push([x as Point, y, z]),
+                '.' | ' ' => {}
+                _ => {
+                    return Err("a polycube is given as lines of x y z, or as masks of '#' and '.'")
+                }
+            }
+        }
+        y += 1;
+    }
+    Ok(voxels)
+}
+
+/// Reads the voxels of the first model in a MagicaVoxel `.vox` file: the `XYZI` chunk, a count and
+/// then x, y, z and a colour index of a byte each per voxel.
+pub fn from_vox(bytes: &[u8]) -> Result<Vec<Voxel>, &'static str> {
+    let word = |at: usize| -> Option<usize> {
+        let word: [u8; 4] = bytes.get(at..at + 4)?.try_into().ok()?;
+        Some(u32::from_le_bytes(word) as usize)
+    };
+    if bytes.get(..4) != Some(b"VOX ") || bytes.get(8..12) != Some(b"MAIN") {
+        return Err("not a MagicaVoxel file");
+    }
+    let mut at = 20 + word(12).ok_or("the vox file is cut short")?;
+    while at + 12 <= bytes.len() {
+        let content = word(at + 4).ok_or("the vox file is cut short")?;
+        if &bytes[at..at + 4] == b"XYZI" {
+            let count = word(at + 12).ok_or("the vox file is cut short")?;
+            let voxels = bytes
+                .get(at + 16..at + 16 + 4 * count)
+                .ok_or("the vox file is cut short")?;
+            return Ok(voxels
+                .chunks_exact(4)
+                .map(|voxel| [0, 1, 2].map(|axis| voxel[axis] as Point))
+                .collect());
+        }
+        at += 12 + content;
+    }
+    Err("the vox file holds no voxels")
+}
+
+/// Solves the grid graph of a polycube made by `make::make_polycube`, or says why it has no
+/// hamiltonian cycle. The cheap reasons are tried first, as in `rule_out`. Then a 2-factor is found
+/// with a flow, or else the black vertices short of edges are the reason, and its cycles are merged by
+/// flipping unit squares in all three planes. Should merging get stuck, the polycube is left
+/// undecided.
+pub fn polycube(adj: &Adjacency, verts: &Verts, vi_map: &VIMap) -> Result<Solution, Obstruction> {
+    let neighbors = neighbors(adj);
+    rule_out(&neighbors, verts)?;
+    let black: Vec<bool> = verts.iter().map(|&vert| is_black(vert)).collect();
+    let mut links = two_factor(&neighbors, &black).map_err(|reached| deficient(reached, verts))?;
+    merge(&mut links, &squares(verts, vi_map))
+        .map(|cycle| cycle.into_iter().map(|node| node as Node).collect())
+        .map_err(|cycles| Obstruction::Stuck { cycles })
+}
+
+/// The neighbours of every vertex, in order of the vertices.
+pub fn neighbors(adj: &Adjacency) -> Vec<Vec<usize>> {
+    (0..adj.len() as Node)
+        .map(|node| {
+            adj[&node]
+                .iter()
+                .map(|&other| other as usize)
+                .sorted()
+                .collect()
+        })
+        .collect()
+}
+
+/// The reasons for a grid graph to have no hamiltonian cycle that are cheap to find: a leaf, unequal
+/// colour classes, a vertex out of reach and a cut vertex.
+pub fn rule_out(neighbors: &[Vec<usize>], verts: &Verts) -> Result<(), Obstruction> {
+    let order = verts.len();
+    if let Some(node) = (0..order).find(|&node| neighbors[node].len() < 2) {
+        return Err(Obstruction::Leaf {
+            node: node as Node,
+            degree: neighbors[node].len(),
+        });
+    }
+    let blacks = verts.iter().filter(|&&vert| is_black(vert)).count();
+    if blacks * 2 != order {
+        return Err(Obstruction::Unbalanced {
+            black: blacks,
+            white: order - blacks,
+        });
+    }
+    if let Some(node) = reach(neighbors, 0, UNSET).iter().position(|&seen| !seen) {
+        return Err(Obstruction::Disconnected { node: node as Node });
+    }
+    if let Some(node) = cut_vertex(neighbors) {
+        return Err(Obstruction::CutVertex { node: node as Node });
+    }
+    Ok(())
+}
+
+/// The vertices a flow for a 2-factor reached, as the proof that there is none.
+pub fn deficient(reached: Vec<usize>, verts: &Verts) -> Obstruction {
+    let (black, white) = reached
+        .into_iter()
+        .map(|node| node as Node)
+        .partition(|&node| is_black(verts[node as usize]));
+    Obstruction::Deficient { black, white }
+}
+
+/// The unit squares of a grid graph in all three planes, by their corners from the lowest one along
+/// the first axis of the plane, along the second, and along both.
+pub fn squares(verts: &Verts, vi_map: &VIMap) -> Vec<[usize; 4]> {
+    verts
+        .iter()
+        .enumerate()
+        .flat_map(|(a, &(x, y, z))| {
+            [[0, 1], [0, 2], [1, 2]]
+                .into_iter()
+                .filter_map(move |[i, j]| {
+                    let corner = |di: Point, dj: Point| {
+                        let mut at = [x, y, z];
+                        (at[i], at[j]) = (at[i] + di, at[j] + dj);
+                        vi_map
+                            .get(&(at[0], at[1], at[2]))
+                            .map(|&node| node as usize)
+                    };
+                    Some([a, corner(2, 0)?, corner(0, 2)?, corner(2, 2)?])
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_text, from_vox, polycube, Voxel};
+    use crate::graph::{
+        cuboid::Obstruction,
+        utils::{
+            certify::{id_seq, SequenceID},
+            make::make_polycube,
+        },
+    };
+
+    /// A MagicaVoxel file holding one model of the voxels given.
+    fn vox(voxels: &[Voxel]) -> Vec<u8> {
+        let word = |value: usize| (value as u32).to_le_bytes();
+        let mut xyzi: Vec<u8> = word(voxels.len()).to_vec();
+        voxels
+            .iter()
+            .for_each(|voxel| xyzi.extend(voxel.map(|coord| coord as u8).into_iter().chain([1])));
+        let mut bytes: Vec<u8> = [b"VOX ".as_slice(), &word(150), b"MAIN", &word(0)].concat();
+        bytes.extend(word(12 + xyzi.len()));
+        bytes.extend([b"XYZI".as_slice(), &word(xyzi.len()), &word(0), &xyzi].concat());
+        bytes
+    }
+
+    #[test]
+    fn reads_coordinates_and_layer_masks_alike() {
+        let listed = from_text("0 0 0\n1,0,0\n0 1 0\n 1 1 0\n").unwrap();
+        let masked = from_text("##\n##\n").unwrap();
+        assert_eq!(listed, masked);
+        let layers = from_text("#.\n\n.#\n").unwrap();
+        assert_eq!(layers, vec![[0, 0, 0], [1, 0, 1]]);
+        assert!(from_text("1 2\n").is_err());
+        assert!(from_text("#?#\n").is_err());
+    }
+
+    #[test]
+    fn reads_the_voxels_of_a_vox_file() {
+        let voxels = vec![[0, 0, 0], [3, 1, 2], [255, 0, 7]];
+        assert_eq!(from_vox(&vox(&voxels)), Ok(voxels.clone()));
+        assert!(from_vox(b"not a vox file at all").is_err());
+        let cut = vox(&voxels);
+        assert!(from_vox(&cut[..cut.len() - 3]).is_err());
+    }
+
+    #[test]
+    fn solves_a_box_and_proves_a_cross_has_no_cycle() {
+        let block: Vec<Voxel> = (0..24).map(|i| [i % 2, i / 2 % 3, i / 6]).collect();
+        let (_, verts, vi_map, adj) = make_polycube(&block);
+        let tour = polycube(&adj, &verts, &vi_map).unwrap();
+        assert_eq!(id_seq(&tour, &adj), SequenceID::HamCycle);
+
+        let cross = [[1, 1, 0], [0, 1, 0], [2, 1, 0], [1, 0, 0], [1, 2, 0]];
+        let (_, verts, vi_map, adj) = make_polycube(&cross);
+        let obstruction = polycube(&adj, &verts, &vi_map).unwrap_err();
+        assert!(matches!(obstruction, Obstruction::Leaf { .. }));
+        assert!(obstruction.is_proof() && obstruction.certify(&adj, &verts));
+    }
+
+    #[test]
+    fn proves_two_boxes_joined_by_one_voxel_have_no_cycle() {
+        let mut voxels: Vec<Voxel> = (0..8).map(|i| [i % 2, i / 2 % 2, i / 4]).collect();
+        voxels.extend((0..8).map(|i| [i % 2 + 3, i / 2 % 2, i / 4]));
+        voxels.push([2, 0, 0]);
+        let (_, verts, vi_map, adj) = make_polycube(&voxels);
+        let obstruction = polycube(&adj, &verts, &vi_map).unwrap_err();
+        assert!(obstruction.is_proof() && obstruction.certify(&adj, &verts));
+    }
+}
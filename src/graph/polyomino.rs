@@ -0,0 +1,556 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+};
+
+use super::{
+    defs::Point,
+    factor::{cut_vertex, merge, reach, two_factor, UNSET},
+};
+
+pub type Cell = [Point; 2];
+
+const STEPS: [Cell; 4] = [[1, 0], [-1, 0], [0, 1], [0, -1]];
+/// Widest polyomino, counted across its narrower side, searched exhaustively once merging gets stuck.
+const MAX_WIDTH: usize = 12;
+const DOWN: u8 = 1;
+const RIGHT: u8 = 2;
+
+/// Why a polyomino has no hamiltonian cycle, each reason checkable against the polyomino on its own.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /// A cell with fewer than two neighbours.
+    Leaf { cell: Cell, degree: usize },
+    /// More cells of one chessboard colour than the other, while a cycle alternates between them.
+    Unbalanced { black: usize, white: usize },
+    /// A cell whose removal cuts the polyomino in two, a cycle would have to pass it twice.
+    CutCell { cell: Cell },
+    /// Black cells that cannot all have two edges to white cells: they need 2·|black| edges, but have
+    /// fewer to the white cells outside `white` than 2·|black| - 2·|white|, and each white cell takes two.
+    Deficient { black: Vec<Cell>, white: Vec<Cell> },
+    /// Every way of laying the cycle out was tried, row by row, and none closes over all the cells.
+    Exhausted,
+    /// Not a proof: merging got stuck and the polyomino is too wide to search through.
+    TooWide { width: usize },
+}
+
+impl Reason {
+    /// Whether the reason proves there is no cycle, which every reason but `TooWide` does.
+    pub fn is_proof(&self) -> bool {
+        !matches!(self, Reason::TooWide { .. })
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::Leaf { cell, degree } => {
+                write!(
+                    f,
+                    "cell {cell:?} has {degree} neighbours, a cycle needs two"
+                )
+            }
+            Reason::Unbalanced { black, white } => {
+                write!(
+                    f,
+                    "{black} black and {white} white cells, a cycle alternates"
+                )
+            }
+            Reason::CutCell { cell } => write!(f, "cell {cell:?} cuts the polyomino in two"),
+            Reason::Deficient { black, white } => write!(
+                f,
+                "{} black cells need more edges than {} white cells and the rest can take",
+                black.len(),
+                white.len()
+            ),
+            Reason::Exhausted => write!(f, "an exhaustive search found no cycle"),
+            Reason::TooWide { width } => write!(
+                f,
+                "merging got stuck and {width} cells across is too wide to search"
+            ),
+        }
+    }
+}
+
+/// A hole-free polyomino: edge-connected cells whose gaps all reach the outside, corner to corner,
+/// which makes its grid graph a solid grid graph.
+#[derive(Clone, Debug)]
+pub struct Polyomino {
+    cells: Vec<Cell>,
+    index: HashMap<Cell, usize>,
+    adj: Vec<Vec<usize>>,
+}
+
+impl Polyomino {
+    pub fn from_cells(cells: &[Cell]) -> Result<Polyomino, &'static str> {
+        let mut cells: Vec<Cell> = cells
+            .iter()
+            .copied()
+            .collect::<HashSet<Cell>>()
+            .into_iter()
+            .collect();
+        cells.sort_unstable_by_key(|&[x, y]| (y, x));
+        if cells.is_empty() {
+            return Err("a polyomino needs at least one cell");
+        }
+        let index: HashMap<Cell, usize> = cells.iter().enumerate().map(|(i, &c)| (c, i)).collect();
+        let adj: Vec<Vec<usize>> = cells
+            .iter()
+            .map(|&[x, y]| {
+                STEPS
+                    .iter()
+                    .filter_map(|[dx, dy]| index.get(&[x + dx, y + dy]).copied())
+                    .collect()
+            })
+            .collect();
+        let polyomino = Polyomino { cells, index, adj };
+        if !reach(&polyomino.adj, 0, UNSET).into_iter().all(|seen| seen) {
+            return Err("the cells of a polyomino must be connected edge to edge");
+        }
+        if polyomino.has_hole() {
+            return Err("the polyomino has a hole");
+        }
+        Ok(polyomino)
+    }
+
+    /// Reads a mask with `#` for every cell and `.` or a space for every gap, one row per line.
+    pub fn from_mask(mask: &str) -> Result<Polyomino, &'static str> {
+        let mut cells: Vec<Cell> = Vec::new();
+        for (y, line) in mask.lines().enumerate() {
+            for (x, char) in line.chars().enumerate() {
+                match char {
+                    '#' => cells.push([x as Point, y as Point]),
+                    '.' | ' ' => {}
+                    _ => return Err("a mask holds only '#' for cells and '.' or ' ' for gaps"),
+                }
+            }
+        }
+        Polyomino::from_cells(&cells)
+    }
+
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Whether the cells are a hamiltonian cycle of the polyomino, in order.
+    pub fn is_cycle(&self, cycle: &[Cell]) -> bool {
+        cycle.len() == self.len()
+            && cycle.iter().collect::<HashSet<_>>().len() == self.len()
+            && (0..cycle.len()).all(|i| {
+                let ([x, y], [a, b]) = (cycle[i], cycle[(i + 1) % cycle.len()]);
+                self.index.contains_key(&[x, y]) && (x - a).abs() + (y - b).abs() == 1
+            })
+    }
+
+    /// Finds a hamiltonian cycle, in the manner of Umans and Lenhart: a 2-factor, a set of cycles
+    /// covering every cell, is found with a flow and its cycles are merged by flipping unit squares
+    /// that hold an edge of two different cycles, shaking the 2-factor when that gets stuck. When it
+    /// stays stuck, polyominoes up to MAX_WIDTH cells across are searched row by row, which either
+    /// finds a cycle or proves there is none. The cheap reasons for there being none are checked first.
+    pub fn solve(&self) -> Result<Vec<Cell>, Reason> {
+        if let Some(i) = (0..self.len()).find(|&i| self.adj[i].len() < 2) {
+            return Err(Reason::Leaf {
+                cell: self.cells[i],
+                degree: self.adj[i].len(),
+            });
+        }
+        let black = self.cells.iter().filter(|&&cell| is_black(cell)).count();
+        if black * 2 != self.len() {
+            return Err(Reason::Unbalanced {
+                black,
+                white: self.len() - black,
+            });
+        }
+        if let Some(i) = cut_vertex(&self.adj) {
+            return Err(Reason::CutCell {
+                cell: self.cells[i],
+            });
+        }
+        let black: Vec<bool> = self.cells.iter().map(|&cell| is_black(cell)).collect();
+        let mut links = two_factor(&self.adj, &black).map_err(|reached| {
+            let (black, white) = reached
+                .into_iter()
+                .map(|i| self.cells[i])
+                .partition(|&cell| is_black(cell));
+            Reason::Deficient { black, white }
+        })?;
+        if let Ok(cycle) = merge(&mut links, &self.squares()) {
+            return Ok(cycle.into_iter().map(|i| self.cells[i]).collect());
+        }
+        match self.width() {
+            width if width > MAX_WIDTH => Err(Reason::TooWide { width }),
+            _ => self.search().ok_or(Reason::Exhausted),
+        }
+    }
+
+    /// Checks a reason against the polyomino, searching it again for `Exhausted`.
+    pub fn verify(&self, reason: &Reason) -> bool {
+        match reason {
+            Reason::Leaf { cell, degree } => {
+                *degree < 2 && self.index.get(cell).map(|&i| self.adj[i].len()) == Some(*degree)
+            }
+            Reason::Unbalanced { black, white } => {
+                let blacks = self.cells.iter().filter(|&&cell| is_black(cell)).count();
+                black != white && (blacks, self.len() - blacks) == (*black, *white)
+            }
+            Reason::CutCell { cell } => match self.index.get(cell) {
+                Some(&i) => {
+                    let seen = reach(&self.adj, (i + 1) % self.len(), i);
+                    self.len() > 2 && seen.into_iter().filter(|&seen| seen).count() < self.len() - 1
+                }
+                None => false,
+            },
+            Reason::Deficient { black, white } => {
+                let is_member = |cells: &Vec<Cell>, colour: bool| {
+                    cells
+                        .iter()
+                        .all(|cell| self.index.contains_key(cell) && is_black(*cell) == colour)
+                        && cells.iter().collect::<HashSet<_>>().len() == cells.len()
+                };
+                let white_set: HashSet<&Cell> = white.iter().collect();
+                let outside: usize = black
+                    .iter()
+                    .flat_map(|cell| self.adj[self.index[cell]].iter())
+                    .filter(|&&j| !white_set.contains(&self.cells[j]))
+                    .count();
+                is_member(black, true)
+                    && is_member(white, false)
+                    && outside + 2 * white.len() < 2 * black.len()
+            }
+            Reason::Exhausted => self.width() <= MAX_WIDTH && self.search().is_none(),
+            Reason::TooWide { .. } => false,
+        }
+    }
+
+    /// Whether a gap inside the bounding box cannot reach its border, stepping edge or corner wise.
+    fn has_hole(&self) -> bool {
+        let ([min_x, min_y], [max_x, max_y]) = self.bounds();
+        let (min_x, min_y, max_x, max_y) = (min_x - 1, min_y - 1, max_x + 1, max_y + 1);
+        let gaps = (max_x - min_x + 1) as usize * (max_y - min_y + 1) as usize - self.len();
+        let mut seen: HashSet<Cell> = HashSet::from([[min_x, min_y]]);
+        let mut queue = VecDeque::from([[min_x, min_y]]);
+        while let Some([x, y]) = queue.pop_front() {
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    let next = [x + dx, y + dy];
+                    if (min_x..=max_x).contains(&next[0])
+                        && (min_y..=max_y).contains(&next[1])
+                        && !self.index.contains_key(&next)
+                        && seen.insert(next)
+                    {
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        seen.len() != gaps
+    }
+
+    fn bounds(&self) -> (Cell, Cell) {
+        let xs = self.cells.iter().map(|&[x, _]| x);
+        let ys = self.cells.iter().map(|&[_, y]| y);
+        (
+            [xs.clone().min().unwrap(), ys.clone().min().unwrap()],
+            [xs.max().unwrap(), ys.max().unwrap()],
+        )
+    }
+
+    fn width(&self) -> usize {
+        let ([min_x, min_y], [max_x, max_y]) = self.bounds();
+        (max_x - min_x).min(max_y - min_y) as usize + 1
+    }
+
+    /// The unit squares of the polyomino, by their corners from the lower left across, up, and across.
+    fn squares(&self) -> Vec<[usize; 4]> {
+        (0..self.len())
+            .filter_map(|a| {
+                let [x, y] = self.cells[a];
+                let corners = [[x + 1, y], [x, y + 1], [x + 1, y + 1]].map(|c| self.index.get(&c));
+                let [Some(&b), Some(&c), Some(&d)] = corners else {
+                    return None;
+                };
+                Some([a, b, c, d])
+            })
+            .collect()
+    }
+
+    /// Searches row by row for a hamiltonian cycle, keeping for every cell the ways the cycle can cross
+    /// the line between the cells done and those to come: which columns it crosses and how the crossings
+    /// pair up, written as brackets. Only the last cell may close the cycle.
+    fn search(&self) -> Option<Vec<Cell>> {
+        let ([min_x, min_y], [max_x, max_y]) = self.bounds();
+        let across = max_x - min_x <= max_y - min_y;
+        let to_grid = |[x, y]: Cell| match across {
+            true => ((y - min_y) as usize, (x - min_x) as usize),
+            false => ((x - min_x) as usize, (y - min_y) as usize),
+        };
+        let (rows, width) = match across {
+            true => ((max_y - min_y) as usize + 1, (max_x - min_x) as usize + 1),
+            false => ((max_x - min_x) as usize + 1, (max_y - min_y) as usize + 1),
+        };
+        let mut grid = vec![vec![UNSET; width]; rows];
+        self.cells.iter().enumerate().for_each(|(i, &cell)| {
+            let (row, col) = to_grid(cell);
+            grid[row][col] = i;
+        });
+        let is_open = |row: usize, col: usize| row < rows && col < width && grid[row][col] != UNSET;
+        let last = (0..rows * width)
+            .filter(|&k| is_open(k / width, k % width))
+            .max()?;
+        let mut states: Vec<u64> = vec![0];
+        let mut history: Vec<Vec<(usize, u8)>> = Vec::with_capacity(rows * width);
+        for k in 0..rows * width {
+            let (row, col) = (k / width, k % width);
+            let mut next_states: Vec<u64> = Vec::new();
+            let mut steps: Vec<(usize, u8)> = Vec::new();
+            let mut seen: HashMap<u64, usize> = HashMap::new();
+            let mut push = |state: u64, parent: usize, bits: u8| {
+                if let std::collections::hash_map::Entry::Vacant(entry) = seen.entry(state) {
+                    entry.insert(next_states.len());
+                    next_states.push(state);
+                    steps.push((parent, bits));
+                }
+            };
+            let (down, right) = (is_open(row + 1, col), is_open(row, col + 1));
+            for (parent, &state) in states.iter().enumerate() {
+                let (left, up) = (plug(state, col), plug(state, col + 1));
+                let cleared = set(set(state, col, 0), col + 1, 0);
+                if !is_open(row, col) {
+                    if left == 0 && up == 0 {
+                        push(state, parent, 0);
+                    }
+                    continue;
+                }
+                match (left, up) {
+                    (0, 0) if down && right => {
+                        push(set(set(state, col, 1), col + 1, 2), parent, DOWN | RIGHT)
+                    }
+                    (0, 0) => {}
+                    (end, 0) | (0, end) => {
+                        if down {
+                            push(set(cleared, col, end), parent, DOWN);
+                        }
+                        if right {
+                            push(set(cleared, col + 1, end), parent, RIGHT);
+                        }
+                    }
+                    (1, 1) => push(set(cleared, partner(state, col + 1), 1), parent, 0),
+                    (2, 2) => push(set(cleared, partner(state, col), 2), parent, 0),
+                    (2, 1) => push(cleared, parent, 0),
+                    _ if k == last && cleared == 0 => push(0, parent, 0),
+                    _ => {}
+                }
+            }
+            if col == width - 1 {
+                next_states.iter_mut().for_each(|state| *state <<= 2);
+            }
+            history.push(steps);
+            states = next_states;
+        }
+        let mut at = states.iter().position(|&state| state == 0)?;
+        let mut links: Vec<Vec<usize>> = vec![Vec::new(); self.len()];
+        for k in (0..rows * width).rev() {
+            let (parent, bits) = history[k][at];
+            let (row, col) = (k / width, k % width);
+            let mut link = |other: usize| {
+                let (i, j) = (grid[row][col], other);
+                links[i].push(j);
+                links[j].push(i);
+            };
+            if bits & DOWN != 0 {
+                link(grid[row + 1][col]);
+            }
+            if bits & RIGHT != 0 {
+                link(grid[row][col + 1]);
+            }
+            at = parent;
+        }
+        let mut cycle = vec![0];
+        let (mut prev, mut node) = (0, links[0][0]);
+        while node != 0 {
+            cycle.push(node);
+            let following = match links[node][0] == prev {
+                true => links[node][1],
+                false => links[node][0],
+            };
+            (prev, node) = (node, following);
+        }
+        Some(cycle.into_iter().map(|i| self.cells[i]).collect())
+    }
+}
+
+fn is_black([x, y]: Cell) -> bool {
+    (x as i32 + y as i32).rem_euclid(2) == 0
+}
+
+fn plug(state: u64, at: usize) -> u64 {
+    state >> (2 * at) & 3
+}
+
+fn set(state: u64, at: usize, value: u64) -> u64 {
+    state & !(3 << (2 * at)) | value << (2 * at)
+}
+
+/// The column of the bracket paired with the one at `at`.
+fn partner(state: u64, at: usize) -> usize {
+    let (step, open): (isize, u64) = match plug(state, at) {
+        1 => (1, 1),
+        _ => (-1, 2),
+    };
+    let (mut col, mut depth) = (at as isize, 0);
+    loop {
+        match plug(state, col as usize) {
+            0 => {}
+            value if value == open => depth += 1,
+            _ => depth -= 1,
+        }
+        if depth == 0 {
+            return col as usize;
+        }
+        col += step;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cell, Polyomino, Reason, MAX_WIDTH};
+
+    /// Whether the polyomino has a hamiltonian cycle, by trying every path from its first cell.
+    fn has_cycle(polyomino: &Polyomino) -> bool {
+        fn extend(polyomino: &Polyomino, path: &mut Vec<usize>, seen: &mut [bool]) -> bool {
+            let last = *path.last().unwrap();
+            if path.len() == polyomino.len() {
+                return path.len() > 2 && polyomino.adj[last].contains(&path[0]);
+            }
+            for &next in &polyomino.adj[last] {
+                if !seen[next] {
+                    seen[next] = true;
+                    path.push(next);
+                    if extend(polyomino, path, seen) {
+                        return true;
+                    }
+                    seen[next] = false;
+                    path.pop();
+                }
+            }
+            false
+        }
+        let mut seen = vec![false; polyomino.len()];
+        seen[0] = true;
+        extend(polyomino, &mut vec![0], &mut seen)
+    }
+
+    /// Every polyomino whose cells are the set bits of a mask over a box of the width given.
+    fn from_bits(bits: u32, width: usize, cells: usize) -> Option<Polyomino> {
+        let cells: Vec<Cell> = (0..cells)
+            .filter(|&i| bits >> i & 1 == 1)
+            .map(|i| [(i % width) as i16, (i / width) as i16])
+            .collect();
+        Polyomino::from_cells(&cells).ok()
+    }
+
+    #[test]
+    fn settles_every_polyomino_in_a_three_by_four_box() {
+        for bits in 1..1 << 12 {
+            let Some(polyomino) = from_bits(bits, 3, 12) else {
+                continue;
+            };
+            match polyomino.solve() {
+                Ok(cycle) => assert!(polyomino.is_cycle(&cycle), "{bits:b}"),
+                Err(reason) => {
+                    assert!(!has_cycle(&polyomino), "{bits:b}");
+                    assert!(reason.is_proof() && polyomino.verify(&reason), "{bits:b}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn searches_row_by_row_as_a_brute_force_does() {
+        for bits in 1..1 << 16 {
+            let Some(polyomino) = from_bits(bits, 4, 16) else {
+                continue;
+            };
+            match polyomino.search() {
+                Some(cycle) => assert!(polyomino.is_cycle(&cycle), "{bits:b}"),
+                None => assert!(!has_cycle(&polyomino), "{bits:b}"),
+            }
+        }
+    }
+
+    #[test]
+    fn winds_the_rectangles_that_have_a_cycle() {
+        for width in 1..=7 {
+            for height in 1..=7 {
+                let mask = vec!["#".repeat(width); height].join("\n");
+                let polyomino = Polyomino::from_mask(&mask).unwrap();
+                let closes = width > 1 && height > 1 && (width * height) % 2 == 0;
+                match polyomino.solve() {
+                    Ok(cycle) => assert!(closes && polyomino.is_cycle(&cycle)),
+                    Err(reason) => assert!(!closes && polyomino.verify(&reason)),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_masks_that_are_not_hole_free_polyominoes() {
+        assert!(Polyomino::from_mask("").is_err());
+        assert!(Polyomino::from_mask("#x#").is_err());
+        assert!(Polyomino::from_mask("#.#").is_err());
+        assert!(Polyomino::from_mask("###\n#.#\n###").is_err());
+        assert!(Polyomino::from_mask("##.\n#.#\n.##").is_err());
+        assert!(Polyomino::from_mask("##\n##").is_ok());
+    }
+
+    #[test]
+    fn rejects_false_reasons() {
+        let polyomino = Polyomino::from_mask("####\n####").unwrap();
+        let false_reasons = [
+            Reason::Leaf {
+                cell: [0, 0],
+                degree: 1,
+            },
+            Reason::Unbalanced { black: 3, white: 5 },
+            Reason::CutCell { cell: [1, 0] },
+            Reason::Deficient {
+                black: vec![[0, 0]],
+                white: vec![],
+            },
+            Reason::Exhausted,
+            Reason::TooWide { width: 2 },
+        ];
+        for reason in false_reasons {
+            assert!(!polyomino.verify(&reason), "{reason:?}");
+        }
+        assert!(!Reason::TooWide { width: 20 }.is_proof());
+        assert!(Reason::Exhausted.is_proof());
+    }
+
+    #[test]
+    fn leaves_a_stuck_polyomino_wider_than_the_search_undecided() {
+        let knot = Polyomino::from_mask(".###\n.###\n####\n###.\n###.").unwrap();
+        assert_eq!(knot.solve(), Err(Reason::Exhausted));
+        assert!(knot.verify(&Reason::Exhausted));
+        // a ladder out of the knot's two top right cells and round a corner: a cycle would have to
+        // run out along it and back, leaving a path through the knot between those two cells, which
+        // their edge would close into a cycle of the knot.
+        let mut cells = knot.cells().to_vec();
+        cells.extend((4..16).flat_map(|x| [[x, 0], [x, 1]]));
+        cells.extend((2..14).flat_map(|y| [[14, y], [15, y]]));
+        let wide = Polyomino::from_cells(&cells).unwrap();
+        assert_eq!(wide.width(), MAX_WIDTH + 2);
+        let reason = wide.solve().unwrap_err();
+        assert_eq!(reason, Reason::TooWide { width: 14 });
+        assert!(!reason.is_proof() && !wide.verify(&reason));
+        assert!(!wide.verify(&Reason::Exhausted));
+    }
+}
@@ -0,0 +1,100 @@
+use numpy::{IntoPyArray, PyArray1, PyArray2};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use super::{
+    defs::{Node, Solution, VecVert},
+    npy::{edges_array, tour_array, verts_array},
+    utils::{
+        certify::{id_seq, SequenceID},
+        info::{get_n_from_order, get_order_from_n},
+        make,
+    },
+    weave as weaving,
+};
+
+/// The coordinates and edges of a graph as NumPy holds them.
+type Arrays<'py> = (Bound<'py, PyArray2<i16>>, Bound<'py, PyArray2<Node>>);
+
+/// The vertices of the discocube of the level given and the cycle woven on it, or drawn from the
+/// seed.
+fn woven(level: u32, seed: Option<u64>) -> Result<(VecVert, Solution), &'static str> {
+    if level == 0 {
+        return Err("the discocubes start at level 1");
+    }
+    let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make::make_graph(level);
+    let tour = match seed {
+        Some(seed) => weaving::weave_seeded(&adj, &vi_map, &verts, z_adj, z_order, min_xyz, seed)?,
+        None => weaving::weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz),
+    };
+    Ok((verts, tour))
+}
+
+/// The discocube of the level given as NumPy arrays: its coordinates, (N, 3) int16, and its edges,
+/// (E, 2) uint32, each once with its lesser end first.
+#[pyfunction]
+fn make_graph(py: Python<'_>, level: u32) -> PyResult<Arrays<'_>> {
+    if level == 0 {
+        return Err(PyValueError::new_err("the discocubes start at level 1"));
+    }
+    let (verts, edges) = py.detach(|| {
+        let (_, _, verts, _, adj, _, _, _) = make::make_graph(level);
+        (verts_array(&verts), edges_array(&adj))
+    });
+    Ok((verts.into_pyarray(py), edges.into_pyarray(py)))
+}
+
+/// A hamiltonian cycle on the discocube of the level given, its nodes in order as an (N,) uint32
+/// array, woven or drawn from the seed with the GIL released.
+#[pyfunction]
+#[pyo3(signature = (level, seed=None))]
+fn weave(py: Python<'_>, level: u32, seed: Option<u64>) -> PyResult<Bound<'_, PyArray1<Node>>> {
+    let (_, tour) = py
+        .detach(|| woven(level, seed))
+        .map_err(PyValueError::new_err)?;
+    Ok(tour_array(&tour).into_pyarray(py))
+}
+
+/// Certifies a tour of the discocube whose order it has, or of the level given: HamCycle, HamChain
+/// or Broken.
+#[pyfunction]
+#[pyo3(signature = (tour, level=None))]
+fn certify(py: Python<'_>, tour: Vec<Node>, level: Option<u32>) -> PyResult<String> {
+    let level = level.unwrap_or_else(|| get_n_from_order(tour.len() as u32));
+    if level == 0 || get_order_from_n(level) != tour.len() as u32 {
+        return Err(PyValueError::new_err(
+            "no discocube has as many vertices as the tour",
+        ));
+    }
+    let seq_id = py.detach(|| {
+        let (.., adj, _, _, _) = make::make_graph(level);
+        match tour.iter().all(|node| adj.contains_key(node)) {
+            true => id_seq(&tour, &adj),
+            false => SequenceID::Broken,
+        }
+    });
+    Ok(seq_id.to_string())
+}
+
+/// The coordinates of the discocube of the level given in the order its woven cycle visits them,
+/// as an (N, 3) int16 array.
+#[pyfunction]
+fn tour_coordinates(py: Python<'_>, level: u32) -> PyResult<Bound<'_, PyArray2<i16>>> {
+    let toured = py
+        .detach(|| -> Result<_, &'static str> {
+            let (verts, tour) = woven(level, None)?;
+            let toured: VecVert = tour.iter().map(|&node| verts[node as usize]).collect();
+            Ok(verts_array(&toured))
+        })
+        .map_err(PyValueError::new_err)?;
+    Ok(toured.into_pyarray(py))
+}
+
+/// The module Python imports as `hamcycle`.
+#[pymodule]
+fn hamcycle(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(make_graph, module)?)?;
+    module.add_function(wrap_pyfunction!(weave, module)?)?;
+    module.add_function(wrap_pyfunction!(certify, module)?)?;
+    module.add_function(wrap_pyfunction!(tour_coordinates, module)?)?;
+    Ok(())
+}
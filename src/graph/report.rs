@@ -0,0 +1,120 @@
+use super::{
+    defs::{GridGraph, Hank, Point, Tour, Weaver},
+    utils::certify::{id_seq, SequenceID},
+};
+
+/// How long each step of solving a level took, in seconds, certifying only when asked to.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timings {
+    pub make: f32,
+    pub solve: f32,
+    pub certify: Option<f32>,
+}
+
+/// All that solving a level comes to: the graph, the weaver of the cycle found on it, what the
+/// certifier made of the cycle and how long it all took.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Report {
+    pub level: u32,
+    pub graph: GridGraph,
+    pub weaver: Hank,
+    pub certified: SequenceID,
+    pub timings: Timings,
+}
+
+impl Report {
+    /// The report of the cycle woven on the discocube of the level given, certified as it is made.
+    pub fn new(
+        level: u32,
+        graph: GridGraph,
+        tour: Tour,
+        min_xyz: Point,
+        timings: Timings,
+    ) -> Report {
+        let certified = id_seq(&tour, &graph.adj);
+        let weaver = Hank::from(&Weaver::new(tour.into(), &graph.verts, true, min_xyz));
+        Report {
+            level,
+            graph,
+            weaver,
+            certified,
+            timings,
+        }
+    }
+
+    /// Certifies the cycle against the graph again, with the weaver wound back onto the vertices. A
+    /// weaver that does not fit them is broken.
+    pub fn recertify(&self) -> SequenceID {
+        if self.graph.verts.len() != self.graph.adj.len() {
+            return SequenceID::Broken;
+        }
+        match self.weaver.clone().rethread(&self.graph.verts) {
+            Ok(weaver) => id_seq(&weaver.get_nodes(), &self.graph.adj),
+            Err(_) => SequenceID::Broken,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Report, Timings};
+    use crate::graph::{
+        defs::GridGraph,
+        utils::{certify::SequenceID, make::make_graph},
+        weave::weave,
+    };
+
+    fn woven(level: u32) -> Report {
+        let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(level);
+        let tour = weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz);
+        Report::new(
+            level,
+            GridGraph { verts, adj },
+            tour,
+            min_xyz,
+            Timings::default(),
+        )
+    }
+
+    #[test]
+    fn certifies_the_woven_cycle_again() {
+        let report = woven(4);
+        assert_eq!(report.certified, SequenceID::HamCycle);
+        assert_eq!(report.recertify(), SequenceID::HamCycle);
+    }
+
+    #[test]
+    fn breaks_a_weaver_that_does_not_fit_the_graph() {
+        let mut report = woven(3);
+        report.graph = GridGraph::discocube(2);
+        assert_eq!(report.recertify(), SequenceID::Broken);
+        let mut report = woven(3);
+        report.weaver.data[0] = 1 << 20;
+        assert_eq!(report.recertify(), SequenceID::Broken);
+        let mut report = woven(3);
+        report.weaver.data.swap(0, 2);
+        assert_ne!(report.recertify(), SequenceID::HamCycle);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn reads_back_the_report_it_writes() {
+        let report = woven(3);
+        let text = serde_json::to_string(&report).unwrap();
+        let read: Report = serde_json::from_str(&text).unwrap();
+        assert_eq!(read, report);
+        assert_eq!(read.recertify(), SequenceID::HamCycle);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn writes_a_weaver_as_it_reads_a_hank() {
+        let report = woven(2);
+        let weaver = report.weaver.clone().rethread(&report.graph.verts).unwrap();
+        let text = serde_json::to_string(&weaver).unwrap();
+        assert_eq!(text, serde_json::to_string(&report.weaver).unwrap());
+        assert!(serde_json::from_str::<crate::graph::defs::Hank>("{\"data\":[0]}").is_err());
+    }
+}
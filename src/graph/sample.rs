@@ -0,0 +1,148 @@
+use std::{collections::HashSet, ops::Range};
+
+use super::{
+    darn::{Darner, Objective},
+    defs::{Adjacency, Point, Solution, VIMap, Verts, ZAdjacency, ZOrder},
+    utils::{
+        certify::{id_seq, SequenceID},
+        info::cycle_hash,
+    },
+    weave::weave_seeded,
+};
+
+/// Samples a hamiltonian cycle for every seed, woven from it and then wandered for as many random darns
+/// as the graph has nodes. Every cycle is certified and those already drawn, wherever they start and
+/// whichever way they run, are dropped by their hash: small graphs only have a handful to go around.
+pub fn sample(
+    adj: &Adjacency,
+    vi_map: &VIMap,
+    verts: &Verts,
+    z_adj: ZAdjacency,
+    z_order: ZOrder,
+    min_xyz: Point,
+    seeds: Range<u64>,
+) -> Result<Vec<Solution>, &'static str> {
+    let mut seen: HashSet<u64> = HashSet::new();
+    let mut samples: Vec<Solution> = Vec::new();
+    for draw in seeds {
+        let woven = weave_seeded(
+            adj,
+            vi_map,
+            verts,
+            z_adj.clone(),
+            z_order.clone(),
+            min_xyz,
+            draw,
+        )?;
+        let mut darner = Darner::new(woven, verts, vi_map, Objective::Wander, draw);
+        darner.wander(verts.len());
+        let tour = darner.tour();
+        if id_seq(&tour, adj) != SequenceID::HamCycle {
+            return Err("a sampled tour is not a hamiltonian cycle");
+        }
+        if seen.insert(cycle_hash(&tour)) {
+            samples.push(tour);
+        }
+    }
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sample;
+    use crate::graph::{
+        darn::{Darner, Objective},
+        utils::{
+            certify::{id_seq, SequenceID},
+            info::cycle_hash,
+            make::make_graph,
+        },
+        weave::weave_seeded,
+    };
+
+    #[test]
+    fn finds_the_six_cycles_of_the_cube() {
+        let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(1);
+        let cycles = sample(&adj, &vi_map, &verts, z_adj, z_order, min_xyz, 0..200).unwrap();
+        assert_eq!(cycles.len(), 6);
+    }
+
+    #[test]
+    fn draws_distinct_certified_cycles() {
+        let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(6);
+        let cycles = sample(&adj, &vi_map, &verts, z_adj, z_order, min_xyz, 0..10).unwrap();
+        assert_eq!(cycles.len(), 10);
+        assert!(cycles
+            .iter()
+            .all(|cycle| id_seq(cycle, &adj) == SequenceID::HamCycle));
+        let mut hashes: Vec<u64> = cycles.iter().map(|cycle| cycle_hash(cycle)).collect();
+        hashes.sort();
+        hashes.dedup();
+        assert_eq!(hashes.len(), 10);
+    }
+
+    #[test]
+    fn draws_the_same_cycles_from_the_same_seeds() {
+        let draw = || {
+            let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(5);
+            sample(&adj, &vi_map, &verts, z_adj, z_order, min_xyz, 3..6).unwrap()
+        };
+        assert_eq!(draw(), draw());
+    }
+
+    #[test]
+    fn weaves_every_seed_into_a_cycle_of_its_own() {
+        for level in 1..=8 {
+            let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(level);
+            for seed in 0..50 {
+                let woven = weave_seeded(
+                    &adj,
+                    &vi_map,
+                    &verts,
+                    z_adj.clone(),
+                    z_order.clone(),
+                    min_xyz,
+                    seed,
+                );
+                let woven = woven.expect("a seed the sampler would fail on");
+                assert_eq!(id_seq(&woven, &adj), SequenceID::HamCycle);
+            }
+        }
+    }
+
+    #[test]
+    fn samples_each_seed_from_its_own_weave() {
+        let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(5);
+        for seed in 0..10 {
+            let woven = weave_seeded(
+                &adj,
+                &vi_map,
+                &verts,
+                z_adj.clone(),
+                z_order.clone(),
+                min_xyz,
+                seed,
+            )
+            .unwrap();
+            let mut darner = Darner::new(woven, &verts, &vi_map, Objective::Wander, seed);
+            darner.wander(verts.len());
+            let drawn = sample(
+                &adj,
+                &vi_map,
+                &verts,
+                z_adj.clone(),
+                z_order.clone(),
+                min_xyz,
+                seed..seed + 1,
+            );
+            assert_eq!(drawn, Ok(vec![darner.tour()]));
+        }
+    }
+
+    #[test]
+    fn errs_when_a_seed_cannot_be_woven() {
+        let (_, _, verts, vi_map, adj, _, z_order, min_xyz) = make_graph(1);
+        let z_adj = [([1, 1], vec![]), ([9, 9], vec![])].into_iter().collect();
+        assert!(sample(&adj, &vi_map, &verts, z_adj, z_order, min_xyz, 0..1).is_err());
+    }
+}
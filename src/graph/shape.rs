@@ -0,0 +1,222 @@
+use std::{fmt, str::FromStr};
+
+use super::defs::{Point, Vert};
+
+/// Polycubes centred on the origin, each grown a layer of cubes at a time by its level n. Their unit
+/// cubes sit on odd coordinates as the discocube's do, but for the centered octahedron, which has a
+/// cube at the origin and so sits on even ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shape {
+    /// A cube 2n cubes across.
+    Cube,
+    /// A box of 2n by 2n + 2 by 2n + 4 cubes.
+    Cuboid,
+    /// The discocube: |x| + |y| + |z| <= 2n + 1.
+    Octahedron,
+    /// An octahedron with its six tips cut square, inside a cube 4n across.
+    TruncatedOctahedron,
+    /// A cube 2n across with its eight corners cut off down to the middles of its edges.
+    Cuboctahedron,
+    /// x² + y² + z² <= (2n + 1)², the cubes within the reach of the octahedron's tips.
+    Ball,
+    /// |x| + |y|, |y| + |z| and |z| + |x| all at most 2n.
+    RhombicDodecahedron,
+    /// |x| + |y| + |z| <= 2n on even coordinates: the cubes within n steps of a central one.
+    CenteredOctahedron,
+}
+
+impl Shape {
+    /// Whether the shape of level n holds the cube at the odd coordinates given.
+    pub fn contains(&self, n: u32, (x, y, z): Vert) -> bool {
+        let n = n as i32;
+        let [a, b, c] = [x, y, z].map(|coord| (coord as i32).abs());
+        let (widest, sum) = (a.max(b).max(c), a + b + c);
+        match self {
+            Shape::Cube => widest < 2 * n,
+            Shape::Cuboid => a < 2 * n && b < 2 * n + 2 && c < 2 * n + 4,
+            Shape::Octahedron => sum <= 2 * n + 1,
+            Shape::TruncatedOctahedron => widest < 4 * n && sum < 6 * n,
+            Shape::Cuboctahedron => widest < 2 * n && sum < 4 * n,
+            Shape::Ball => a * a + b * b + c * c <= (2 * n + 1).pow(2),
+            Shape::RhombicDodecahedron => a + b <= 2 * n && b + c <= 2 * n && c + a <= 2 * n,
+            Shape::CenteredOctahedron => sum <= 2 * n,
+        }
+    }
+
+    /// The largest coordinate a cube of the shape of level n has.
+    pub fn reach(&self, n: u32) -> Point {
+        let n = n as Point;
+        match self {
+            Shape::Cuboid => 2 * n + 3,
+            Shape::TruncatedOctahedron => 4 * n - 1,
+            Shape::CenteredOctahedron => 2 * n,
+            _ => 2 * n - 1,
+        }
+    }
+
+    /// The number of cubes in the shape of level n. The shapes on odd coordinates are symmetric under
+    /// every sign change, so each counts eight times the cubes (u, v, w) of one octant, u, v, w >= 1
+    /// standing for the coordinates 2u - 1, 2v - 1 and 2w - 1.
+    pub fn order(&self, n: u32) -> u64 {
+        let n = n as u64;
+        match self {
+            Shape::Cube => 8 * n.pow(3),
+            Shape::Cuboid => 8 * n * (n + 1) * (n + 2),
+            // u + v + w <= n + 2.
+            Shape::Octahedron => 4 * n * (n + 1) * (n + 2) / 3,
+            // u, v, w <= 2n and u + v + w <= 3n + 1: the octant of the octahedron, less the three
+            // tips past 2n, leaves 4n³.
+            Shape::TruncatedOctahedron => 32 * n.pow(3),
+            // u, v, w <= n and u + v + w <= 2n + 1: the cube, less the corner past it.
+            Shape::Cuboctahedron => 8 * n.pow(3) - 4 * (n - 1) * n * (n + 1) / 3,
+            // For every (u, v), the w with (2w - 1)² <= (2n + 1)² - (2u - 1)² - (2v - 1)².
+            Shape::Ball => {
+                let radius = (2 * n + 1).pow(2);
+                let odd = |u: u64| (2 * u - 1).pow(2);
+                8 * (1..=n)
+                    .flat_map(|u| (1..=n).map(move |v| (u, v)))
+                    .filter(|&(u, v)| odd(u) + odd(v) < radius)
+                    .map(|(u, v)| (radius - odd(u) - odd(v)).isqrt().div_ceil(2))
+                    .sum::<u64>()
+            }
+            // u + v, v + w and w + u <= n + 1, a cubic whose constant term follows the parity of n.
+            Shape::RhombicDodecahedron => 2 * n.pow(3) + 3 * n.pow(2) + 2 * n + n % 2,
+            // The centre and a shell of 4s² + 2 cubes at each distance s from 1 to n.
+            Shape::CenteredOctahedron => (2 * n + 1) * (2 * n.pow(2) + 2 * n + 3) / 3,
+        }
+    }
+
+    /// The black cubes less the white ones of the chessboard colouring, `cuboid::is_black`. A shape on
+    /// odd coordinates is its own mirror image through the origin, which swaps the colours of every
+    /// cube, so it has as many of each. The centered octahedron does not: its shell at distance s is
+    /// all of the colour of s, black for the centre and even s, and the surplus comes to
+    /// (-1)ⁿ(2n² + 2n + 1).
+    pub fn surplus(&self, n: u32) -> i64 {
+        match self {
+            Shape::CenteredOctahedron => {
+                let n = n as i64;
+                (1 - 2 * (n % 2)) * (2 * n * n + 2 * n + 1)
+            }
+            _ => 0,
+        }
+    }
+
+    /// What the colouring leaves possible on level n: a cycle alternates colours, so it needs a
+    /// surplus of 0, and a path through every cube needs a surplus of at most one either way.
+    pub fn parity(&self, n: u32) -> Parity {
+        match self.surplus(n).abs() {
+            0 => Parity::Cycle,
+            1 => Parity::Path,
+            _ => Parity::Neither,
+        }
+    }
+}
+
+/// Whether the colouring leaves room for a hamiltonian cycle, only for a hamiltonian path, or for
+/// neither.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parity {
+    Cycle,
+    Path,
+    Neither,
+}
+
+impl fmt::Display for Parity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Parity::Cycle => write!(f, "cycle allowed"),
+            Parity::Path => write!(f, "path only"),
+            Parity::Neither => write!(f, "neither cycle nor path"),
+        }
+    }
+}
+
+impl FromStr for Shape {
+    type Err = &'static str;
+
+    fn from_str(shape: &str) -> Result<Self, Self::Err> {
+        match shape {
+            "cube" => Ok(Shape::Cube),
+            "cuboid" => Ok(Shape::Cuboid),
+            "octahedron" => Ok(Shape::Octahedron),
+            "truncated-octahedron" => Ok(Shape::TruncatedOctahedron),
+            "cuboctahedron" => Ok(Shape::Cuboctahedron),
+            "ball" => Ok(Shape::Ball),
+            "rhombic-dodecahedron" => Ok(Shape::RhombicDodecahedron),
+            "centered-octahedron" => Ok(Shape::CenteredOctahedron),
+            _ => Err("unknown shape, expected one of: cube, cuboid, octahedron, truncated-octahedron, cuboctahedron, ball, rhombic-dodecahedron, centered-octahedron"),
+        }
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Shape::Cube => write!(f, "cube"),
+            Shape::Cuboid => write!(f, "cuboid"),
+            Shape::Octahedron => write!(f, "octahedron"),
+            Shape::TruncatedOctahedron => write!(f, "truncated-octahedron"),
+            Shape::Cuboctahedron => write!(f, "cuboctahedron"),
+            Shape::Ball => write!(f, "ball"),
+            Shape::RhombicDodecahedron => write!(f, "rhombic-dodecahedron"),
+            Shape::CenteredOctahedron => write!(f, "centered-octahedron"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Parity, Shape};
+    use crate::graph::utils::{info::get_order_from_n, make::shape_vertices};
+
+    const SHAPES: [Shape; 8] = [
+        Shape::Cube,
+        Shape::Cuboid,
+        Shape::Octahedron,
+        Shape::TruncatedOctahedron,
+        Shape::Cuboctahedron,
+        Shape::Ball,
+        Shape::RhombicDodecahedron,
+        Shape::CenteredOctahedron,
+    ];
+
+    #[test]
+    fn counts_the_cubes_each_shape_holds() {
+        for shape in SHAPES {
+            for level in 1..=12 {
+                let verts = shape_vertices(shape, level);
+                assert_eq!(shape.order(level), verts.len() as u64, "{shape} {level}");
+                assert!(verts.iter().all(|&vert| shape.contains(level, vert)));
+            }
+        }
+    }
+
+    #[test]
+    fn grows_the_octahedron_into_the_discocube() {
+        for level in 1..=30 {
+            assert_eq!(
+                Shape::Octahedron.order(level),
+                get_order_from_n(level) as u64
+            );
+        }
+    }
+
+    #[test]
+    fn leaves_the_centered_octahedron_without_cycle_or_path() {
+        for level in 1..=10 {
+            let n = level as i64;
+            let surplus = Shape::CenteredOctahedron.surplus(level);
+            assert_eq!(surplus.abs(), 2 * n * n + 2 * n + 1);
+            assert_eq!(Shape::CenteredOctahedron.parity(level), Parity::Neither);
+        }
+        assert_eq!(Shape::Cube.parity(4), Parity::Cycle);
+    }
+
+    #[test]
+    fn reads_back_the_names_it_writes() {
+        for shape in SHAPES {
+            assert_eq!(shape.to_string().parse::<Shape>(), Ok(shape));
+        }
+        assert!("dodecahedron".parse::<Shape>().is_err());
+    }
+}
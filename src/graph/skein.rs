@@ -0,0 +1,367 @@
+use std::cmp::Ordering;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::defs::{Node, Tour, TourSlice};
+
+const NIL: u32 = u32::MAX;
+
+/// A tour wound as an implicit treap keyed by the nodes themselves: the position of a node, reversing a
+/// stretch of the tour and cutting it into pieces all take logarithmic time, which is what darning a
+/// tour of millions of nodes one flip at a time needs.
+#[derive(Clone, Debug)]
+pub struct Skein {
+    root: u32,
+    left: Vec<u32>,
+    right: Vec<u32>,
+    parent: Vec<u32>,
+    size: Vec<u32>,
+    flipped: Vec<bool>,
+    weight: Vec<u32>,
+    path: Vec<u32>,
+}
+
+impl Skein {
+    pub fn new(tour: TourSlice, seed: u64) -> Skein {
+        let order = tour.len();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut skein = Skein {
+            root: NIL,
+            left: vec![NIL; order],
+            right: vec![NIL; order],
+            parent: vec![NIL; order],
+            size: vec![1; order],
+            flipped: vec![false; order],
+            weight: (0..order).map(|_| rng.gen()).collect(),
+            path: Vec::new(),
+        };
+        let mut spine: Vec<u32> = Vec::with_capacity(order);
+        for &node in tour {
+            let mut last = NIL;
+            while let Some(&top) = spine.last() {
+                if skein.weight[top as usize] >= skein.weight[node as usize] {
+                    break;
+                }
+                last = spine.pop().unwrap();
+            }
+            skein.left[node as usize] = last;
+            if let Some(&top) = spine.last() {
+                skein.right[top as usize] = node;
+            }
+            spine.push(node);
+        }
+        skein.root = spine.first().copied().unwrap_or(NIL);
+        skein.resize(skein.root);
+        skein
+    }
+
+    pub fn len(&self) -> usize {
+        self.size_of(self.root) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root == NIL
+    }
+
+    pub fn position(&mut self, node: Node) -> usize {
+        self.position_in(node).0
+    }
+
+    /// The node at position `pos` of the tour.
+    pub fn node_at(&mut self, mut pos: usize) -> Node {
+        let mut node = self.root;
+        loop {
+            self.push(node);
+            let left = self.size_of(self.left[node as usize]) as usize;
+            match pos.cmp(&left) {
+                Ordering::Less => node = self.left[node as usize],
+                Ordering::Equal => return node,
+                Ordering::Greater => {
+                    pos -= left + 1;
+                    node = self.right[node as usize];
+                }
+            }
+        }
+    }
+
+    pub fn is_forward(&mut self, a: Node, b: Node) -> bool {
+        let order = self.len();
+        (self.position(a) + 1) % order == self.position(b)
+    }
+
+    /// Reverses the tour from position `start` to position `end`, both inclusive.
+    pub fn reverse(&mut self, start: usize, end: usize) {
+        let (head, rest) = self.split(self.root, start);
+        let (mid, tail) = self.split(rest, end + 1 - start);
+        self.toggle(mid);
+        let head = self.merge(head, mid);
+        self.root = self.merge(head, tail);
+    }
+
+    /// Rewinds the tour after flipping the square (a, b, c, d) on a tour running a -> b .. c -> d.
+    pub fn flip(&mut self, (a, b, c, d): (Node, Node, Node, Node)) {
+        let (from, to) = (self.position(b), self.position(c));
+        debug_assert!(self.is_forward(a, b) && self.is_forward(c, d));
+        match from <= to {
+            true => self.reverse(from, to),
+            false => {
+                let (from, to) = (self.position(d), self.position(a));
+                self.reverse(from, to)
+            }
+        }
+    }
+
+    /// Rewinds the tour after flipping the square (a, b, c, d) on a tour running a -> b .. d -> c, which
+    /// cuts the run b..=d loose, then flipping (m, n, o, p) which ties the two loops back together.
+    pub fn splice(
+        &mut self,
+        (a, b, _, d): (Node, Node, Node, Node),
+        (m, n, o, p): (Node, Node, Node, Node),
+    ) {
+        let order = self.len();
+        let start = self.position(b);
+        let span = (self.position(d) + order - start) % order + 1;
+        let (head, tail) = self.split(self.root, start);
+        let rotated = self.merge(tail, head);
+        let (inner, outer) = self.split(rotated, span);
+        debug_assert_eq!(self.position_in(a).0, order - span - 1);
+        let ((m, n), (o, p)) = match self.position_in(m).1 == inner {
+            true => ((m, n), (o, p)),
+            false => ((o, p), (m, n)),
+        };
+        let inner = self.unwind(inner, m, n);
+        let outer = self.unwind(outer, p, o);
+        self.root = self.merge(inner, outer);
+    }
+
+    pub fn to_vec(&mut self) -> Tour {
+        let mut tour = Vec::with_capacity(self.len());
+        let mut stack = Vec::new();
+        let mut node = self.root;
+        while node != NIL || !stack.is_empty() {
+            while node != NIL {
+                self.push(node);
+                stack.push(node);
+                node = self.left[node as usize];
+            }
+            node = stack.pop().unwrap();
+            tour.push(node);
+            node = self.right[node as usize];
+        }
+        tour
+    }
+
+    /// Turns the loop held by `tree` so that it starts with `first` and ends with `last`, which must be
+    /// neighbours on the loop.
+    fn unwind(&mut self, tree: u32, last: Node, first: Node) -> u32 {
+        let len = self.size_of(tree) as usize;
+        if (self.position_in(last).0 + 1) % len != self.position_in(first).0 {
+            self.toggle(tree);
+        }
+        let start = self.position_in(first).0;
+        let (head, tail) = self.split(tree, start);
+        self.merge(tail, head)
+    }
+
+    fn position_in(&mut self, node: Node) -> (usize, u32) {
+        let mut path = std::mem::take(&mut self.path);
+        path.clear();
+        path.push(node);
+        while let Some(&up) = path.last() {
+            match self.parent[up as usize] {
+                NIL => break,
+                up => path.push(up),
+            }
+        }
+        let root = *path.last().unwrap();
+        path.iter().rev().for_each(|&up| self.push(up));
+        let mut pos = self.size_of(self.left[node as usize]);
+        for pair in path.windows(2) {
+            let (child, up) = (pair[0], pair[1]);
+            if self.right[up as usize] == child {
+                pos += self.size_of(self.left[up as usize]) + 1;
+            }
+        }
+        self.path = path;
+        (pos as usize, root)
+    }
+
+    fn split(&mut self, tree: u32, count: usize) -> (u32, u32) {
+        if tree == NIL {
+            return (NIL, NIL);
+        }
+        self.push(tree);
+        let left = self.left[tree as usize];
+        match self.size_of(left) as usize >= count {
+            true => {
+                let (head, tail) = self.split(left, count);
+                self.left[tree as usize] = tail;
+                self.update(tree);
+                self.detach(head);
+                (head, tree)
+            }
+            false => {
+                let count = count - self.size_of(left) as usize - 1;
+                let (head, tail) = self.split(self.right[tree as usize], count);
+                self.right[tree as usize] = head;
+                self.update(tree);
+                self.detach(tail);
+                (tree, tail)
+            }
+        }
+    }
+
+    fn merge(&mut self, head: u32, tail: u32) -> u32 {
+        match (head, tail) {
+            (NIL, tree) | (tree, NIL) => {
+                self.detach(tree);
+                tree
+            }
+            _ if self.weight[head as usize] > self.weight[tail as usize] => {
+                self.push(head);
+                let right = self.merge(self.right[head as usize], tail);
+                self.right[head as usize] = right;
+                self.update(head);
+                self.detach(head);
+                head
+            }
+            _ => {
+                self.push(tail);
+                let left = self.merge(head, self.left[tail as usize]);
+                self.left[tail as usize] = left;
+                self.update(tail);
+                self.detach(tail);
+                tail
+            }
+        }
+    }
+
+    fn push(&mut self, node: u32) {
+        let idx = node as usize;
+        if self.flipped[idx] {
+            self.flipped[idx] = false;
+            (self.left[idx], self.right[idx]) = (self.right[idx], self.left[idx]);
+            self.toggle(self.left[idx]);
+            self.toggle(self.right[idx]);
+        }
+    }
+
+    fn toggle(&mut self, node: u32) {
+        if node != NIL {
+            self.flipped[node as usize] ^= true;
+        }
+    }
+
+    fn update(&mut self, node: u32) {
+        let idx = node as usize;
+        let (left, right) = (self.left[idx], self.right[idx]);
+        self.size[idx] = self.size_of(left) + self.size_of(right) + 1;
+        [left, right]
+            .into_iter()
+            .filter(|&child| child != NIL)
+            .for_each(|child| {
+                self.parent[child as usize] = node;
+            });
+    }
+
+    fn detach(&mut self, node: u32) {
+        if node != NIL {
+            self.parent[node as usize] = NIL;
+        }
+    }
+
+    fn resize(&mut self, node: u32) {
+        let mut order = Vec::new();
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            if node != NIL {
+                order.push(node);
+                stack.push(self.left[node as usize]);
+                stack.push(self.right[node as usize]);
+            }
+        }
+        order.iter().rev().for_each(|&node| self.update(node));
+    }
+
+    fn size_of(&self, node: u32) -> u32 {
+        match node {
+            NIL => 0,
+            node => self.size[node as usize],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+    use super::Skein;
+    use crate::graph::defs::{Node, Tour};
+
+    fn shuffled(order: usize, rng: &mut StdRng) -> Tour {
+        let mut tour: Tour = (0..order as Node).collect();
+        tour.shuffle(rng);
+        tour
+    }
+
+    /// The edges of a cycle, each with its lesser end first, so that two windings of one cycle match.
+    fn links(tour: &[Node]) -> Vec<(Node, Node)> {
+        let mut links: Vec<(Node, Node)> = (0..tour.len())
+            .map(|idx| (tour[idx], tour[(idx + 1) % tour.len()]))
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .collect();
+        links.sort();
+        links
+    }
+
+    #[test]
+    fn winds_the_tour_it_is_given() {
+        let rng = &mut StdRng::seed_from_u64(0);
+        for order in [1, 2, 3, 10, 257] {
+            let tour = shuffled(order, rng);
+            let mut skein = Skein::new(&tour, order as u64);
+            assert_eq!(skein.len(), order);
+            assert_eq!(skein.to_vec(), tour);
+            for (pos, &node) in tour.iter().enumerate() {
+                assert_eq!(skein.position(node), pos);
+                assert_eq!(skein.node_at(pos), node);
+            }
+        }
+    }
+
+    #[test]
+    fn reverses_as_a_vec_does() {
+        let rng = &mut StdRng::seed_from_u64(1);
+        let mut tour = shuffled(300, rng);
+        let mut skein = Skein::new(&tour, 1);
+        for _ in 0..500 {
+            let start = rng.gen_range(0..tour.len());
+            let end = rng.gen_range(start..tour.len());
+            tour[start..=end].reverse();
+            skein.reverse(start, end);
+            let node = tour[rng.gen_range(0..tour.len())];
+            assert_eq!(
+                skein.position(node),
+                tour.iter().position(|&n| n == node).unwrap()
+            );
+            let pos = rng.gen_range(0..tour.len());
+            assert_eq!(skein.node_at(pos), tour[pos]);
+        }
+        assert_eq!(skein.to_vec(), tour);
+    }
+
+    #[test]
+    fn flips_a_square_either_way_round() {
+        let tour: Tour = (0..12).collect();
+        for (a, b, c, d) in [(2, 3, 7, 8), (11, 0, 4, 5), (8, 9, 1, 2)] {
+            let mut skein = Skein::new(&tour, 2);
+            assert!(skein.is_forward(a, b) && skein.is_forward(c, d));
+            skein.flip((a, b, c, d));
+            let mut expected = links(&tour);
+            expected.retain(|&link| link != (a.min(b), a.max(b)) && link != (c.min(d), c.max(d)));
+            expected.extend([(a.min(c), a.max(c)), (b.min(d), b.max(d))]);
+            expected.sort();
+            assert_eq!(links(&skein.to_vec()), expected);
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+use super::{
+    defs::{Adjacency, Node, Solution},
+    formats::{edges, written},
+};
+
+/// The graph in TSPLIB's HCP format, as the FHCP challenge set has it: a header and an edge list,
+/// every edge once with its lesser end first, the vertices numbered from 1.
+pub fn to_hcp(name: &str, adj: &Adjacency) -> String {
+    written(|out| write_hcp(out, name, adj))
+}
+
+/// Writes the graph as `to_hcp` has it, a line at a time.
+pub fn write_hcp(out: &mut dyn Write, name: &str, adj: &Adjacency) -> io::Result<()> {
+    write!(
+        out,
+        "NAME : {name}\nCOMMENT : hamiltonian cycle problem\nTYPE : HCP\nDIMENSION : {}\n\
+         EDGE_DATA_FORMAT : EDGE_LIST\nEDGE_DATA_SECTION\n",
+        adj.len()
+    )?;
+    for (node, next) in edges(adj, false) {
+        writeln!(out, "{} {}", node + 1, next + 1)?;
+    }
+    write!(out, "-1\nEOF\n")
+}
+
+/// The cycle in TSPLIB's TOUR format, the vertices numbered from 1.
+pub fn to_tour(name: &str, tour: &Solution) -> String {
+    written(|out| write_tour(out, name, tour))
+}
+
+/// Writes the cycle as `to_tour` has it, a line at a time.
+pub fn write_tour(out: &mut dyn Write, name: &str, tour: &Solution) -> io::Result<()> {
+    write!(
+        out,
+        "NAME : {name}\nTYPE : TOUR\nDIMENSION : {}\nTOUR_SECTION\n",
+        tour.len()
+    )?;
+    for node in tour {
+        writeln!(out, "{}", node + 1)?;
+    }
+    write!(out, "-1\nEOF\n")
+}
+
+/// Reads a graph from TSPLIB's HCP format, its edges given as an `EDGE_LIST` or an `ADJ_LIST`.
+pub fn from_hcp(text: &str) -> Result<Adjacency, &'static str> {
+    let (spec, data) = sections(text, "EDGE_DATA_SECTION")?;
+    if spec.get("TYPE").map(String::as_str) != Some("HCP") {
+        return Err("an HCP file has TYPE : HCP");
+    }
+    let order = dimension(&spec)?;
+    let mut adj: Adjacency = (0..order).map(|node| (node, Default::default())).collect();
+    let mut link = |a: i64, b: i64| -> Result<(), &'static str> {
+        let [a, b] = [a, b].map(|node| node - 1);
+        if !(0..order as i64).contains(&a) || !(0..order as i64).contains(&b) || a == b {
+            return Err("an edge of the HCP file is out of the graph");
+        }
+        adj.get_mut(&(a as Node)).unwrap().insert(b as Node);
+        adj.get_mut(&(b as Node)).unwrap().insert(a as Node);
+        Ok(())
+    };
+    match spec.get("EDGE_DATA_FORMAT").map(String::as_str) {
+        None | Some("EDGE_LIST") => {
+            let ends: Vec<i64> = data.into_iter().take_while(|&node| node != -1).collect();
+            if ends.len() % 2 == 1 {
+                return Err("an edge of the HCP file lacks its second end");
+            }
+            for pair in ends.chunks(2) {
+                link(pair[0], pair[1])?;
+            }
+        }
+        Some("ADJ_LIST") => {
+            // a vertex and its neighbours up to -1, and the list of lists up to another -1.
+            let mut data = data.into_iter();
+            while let Some(node) = data.next().filter(|&node| node != -1) {
+                for next in data.by_ref().take_while(|&next| next != -1) {
+                    link(node, next)?;
+                }
+            }
+        }
+        Some(_) => return Err("the HCP file's EDGE_DATA_FORMAT is neither EDGE_LIST nor ADJ_LIST"),
+    }
+    Ok(adj)
+}
+
+/// Reads the first tour of a file in TSPLIB's TOUR format, the vertices renumbered from 0.
+pub fn from_tour(text: &str) -> Result<Solution, &'static str> {
+    let (spec, data) = sections(text, "TOUR_SECTION")?;
+    let order = dimension(&spec)?;
+    let tour: Vec<i64> = data.into_iter().take_while(|&node| node != -1).collect();
+    if tour.len() != order as usize {
+        return Err("the tour does not hold as many vertices as its DIMENSION");
+    }
+    tour.into_iter()
+        .map(|node| match (1..=order as i64).contains(&node) {
+            true => Ok(node as Node - 1),
+            false => Err("a vertex of the tour is out of the graph"),
+        })
+        .collect()
+}
+
+/// The keywords of the specification part of a TSPLIB file, each `KEY : VALUE` or `KEY: VALUE`, and
+/// the integers of the data section named, up to `EOF`.
+fn sections(
+    text: &str,
+    section: &str,
+) -> Result<(HashMap<String, String>, Vec<i64>), &'static str> {
+    let mut spec = HashMap::new();
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    for line in lines.by_ref() {
+        if line.starts_with(section) {
+            let data = lines
+                .flat_map(str::split_whitespace)
+                .take_while(|&token| token != "EOF")
+                .map(|token| {
+                    token
+                        .parse()
+                        .or(Err("the data section holds more than integers"))
+                })
+                .collect::<Result<_, _>>()?;
+            return Ok((spec, data));
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            spec.insert(key.trim().to_uppercase(), value.trim().to_string());
+        }
+    }
+    Err("the TSPLIB file has no data section")
+}
+
+/// The number of vertices, which a graph or tour to solve or certify cannot be without.
+fn dimension(spec: &HashMap<String, String>) -> Result<Node, &'static str> {
+    match spec
+        .get("DIMENSION")
+        .and_then(|dimension| dimension.parse().ok())
+    {
+        None => Err("the TSPLIB file has no DIMENSION"),
+        Some(0) => Err("the TSPLIB file has a DIMENSION of 0"),
+        Some(order) => Ok(order),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_hcp, from_tour, to_hcp, to_tour};
+    use crate::graph::{
+        defs::Adjacency,
+        utils::{
+            certify::{id_seq, SequenceID},
+            make::make_graph,
+        },
+    };
+
+    #[test]
+    fn reads_back_the_graph_and_tour_it_writes() {
+        let (.., adj, _, _, _) = make_graph(3);
+        assert_eq!(from_hcp(&to_hcp("discocube_3", &adj)), Ok(adj.clone()));
+        let tour = (0..adj.len() as u32).rev().collect();
+        assert_eq!(from_tour(&to_tour("discocube_3", &tour)), Ok(tour));
+    }
+
+    #[test]
+    fn reads_an_adjacency_list() {
+        let text = "NAME: square\nTYPE: HCP\nDIMENSION: 4\nEDGE_DATA_FORMAT: ADJ_LIST\n\
+                    EDGE_DATA_SECTION\n1 2 4 -1\n3 2 4 -1\n-1\nEOF\n";
+        let adj = from_hcp(text).unwrap();
+        assert!((0..4).all(|node| adj[&node].len() == 2));
+        assert_eq!(id_seq(&vec![0, 1, 2, 3], &adj), SequenceID::HamCycle);
+    }
+
+    #[test]
+    fn rejects_an_empty_graph_or_tour() {
+        let hcp = "TYPE : HCP\nDIMENSION : 0\nEDGE_DATA_SECTION\n-1\nEOF\n";
+        assert!(from_hcp(hcp).is_err());
+        let tour = "TYPE : TOUR\nDIMENSION : 0\nTOUR_SECTION\n-1\nEOF\n";
+        assert!(from_tour(tour).is_err());
+        assert_eq!(id_seq(&vec![], &Adjacency::default()), SequenceID::Broken);
+    }
+
+    #[test]
+    fn rejects_what_is_out_of_the_graph() {
+        let hcp =
+            |edges: &str| format!("TYPE : HCP\nDIMENSION : 3\nEDGE_DATA_SECTION\n{edges}\n-1\n");
+        assert!(from_hcp(&hcp("1 2 2 3 3 1")).is_ok());
+        assert!(from_hcp(&hcp("1 2 2 4")).is_err());
+        assert!(from_hcp(&hcp("1 1")).is_err());
+        assert!(from_hcp(&hcp("1 2 3")).is_err());
+        assert!(from_hcp(&hcp("1 two")).is_err());
+        assert!(from_hcp("TYPE : TSP\nDIMENSION : 3\nEDGE_DATA_SECTION\n-1\n").is_err());
+        let tour = |nodes: &str| format!("DIMENSION : 3\nTOUR_SECTION\n{nodes}\n-1\nEOF\n");
+        assert_eq!(from_tour(&tour("3 1 2")), Ok(vec![2, 0, 1]));
+        assert!(from_tour(&tour("1 2")).is_err());
+        assert!(from_tour(&tour("1 2 4")).is_err());
+        assert!(from_tour("DIMENSION : 3\n1 2 3\n").is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use super::{
+    darn::{Darner, Objective},
+    defs::{Adjacency, Point, Solution, VIMap, Verts, ZAdjacency, ZOrder},
+    weave::weave,
+};
+
+const SEED: u64 = 0;
+const MAX_ITERS: usize = 1_000_000;
+
+/// The woven cycle already turns at every node except along one straight run of three edges near the
+/// top of the loom. The darner flips squares around that run until no two consecutive edges share an
+/// axis.
+pub fn turn(
+    adj: &Adjacency,
+    vi_map: &VIMap,
+    verts: &Verts,
+    z_adj: ZAdjacency,
+    z_order: ZOrder,
+    min_xyz: Point,
+) -> Result<Solution, &'static str> {
+    let solution = weave(adj, vi_map, verts, z_adj, z_order, min_xyz);
+    let mut darner = Darner::new(solution, verts, vi_map, Objective::Turns, SEED);
+    match darner.darn(0, MAX_ITERS) {
+        0 => Ok(darner.tour()),
+        _ => Err("could not darn every straight step out of the woven cycle"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::turn;
+    use crate::graph::utils::{
+        certify::{id_seq, is_turning, SequenceID},
+        make::make_graph,
+    };
+
+    #[test]
+    fn turns_at_every_node_of_small_levels() {
+        for level in 1..=10 {
+            let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(level);
+            let tour = turn(&adj, &vi_map, &verts, z_adj, z_order, min_xyz).unwrap();
+            assert_eq!(id_seq(&tour, &adj), SequenceID::HamCycle);
+            assert!(is_turning(&tour, &adj, &verts));
+        }
+    }
+
+    #[test]
+    fn does_not_call_a_broken_tour_turning() {
+        let (_, _, verts, _, adj, ..) = make_graph(3);
+        let tour: Vec<u32> = (0..verts.len() as u32).collect();
+        assert_ne!(id_seq(&tour, &adj), SequenceID::HamCycle);
+        assert!(!is_turning(&tour, &adj, &verts));
+    }
+}
@@ -1,25 +1,65 @@
 use itertools;
 use itertools::Itertools;
 use ndarray::{arr2, Array2};
-use rayon;
 use std::fmt;
 
 use super::defs::{
-    Adjacency, Edge, Edges, Neighbors, Node, Nodes, Point, Points, SignedIdx, Solution, VIMap,
-    VecVert, Vert, Verts, ZAdjacency, ZOrder, ZlevelNodesMap,
+    Adjacency, Edge, Edges, GridGraph, Neighbors, Node, Nodes, Point, Points, SignedIdx, Solution,
+    TourSlice, VIMap, VecVert, Vert, Verts, ZAdjacency, ZOrder, ZlevelNodesMap,
 };
+use super::{par, shape::Shape};
 
 pub mod make {
     use super::{
         arr2,
-        info::{absumv, get_max_xyz, get_order_from_n},
+        info::{absumv, get_hexprism_order_from_n, get_max_xyz, get_order_from_n, hex_radius},
         itertools::{iproduct, Itertools},
         modify::shift_xyz,
-        rayon::prelude::*,
+        par::*,
         shrink::shrink_adjacency,
-        Adjacency, Neighbors, Node, Point, VIMap, VecVert, Verts, ZAdjacency, ZOrder,
+        Adjacency, GridGraph, Neighbors, Node, Point, Shape, VIMap, VecVert, Vert, Verts,
+        ZAdjacency, ZOrder,
     };
 
+    /// Hexagonal prisms stacked into a diamond: the layers at z = ±1, ±3, .. ±(2n - 1) are hexagons of
+    /// cells on axial coordinates (q, r), of radius n at the middle down to 1 at the tips.
+    pub fn make_hexprism(n: u32) -> (u32, u32, VecVert, VIMap, Adjacency) {
+        let max_qr = n as Point;
+        let verts: VecVert = iproduct!(
+            (1..2 * max_qr).step_by(2),
+            -max_qr..=max_qr,
+            -max_qr..=max_qr
+        )
+        .flat_map(|(z, q, r)| [(q, r, -z), (q, r, z)])
+        .filter(|&(q, r, z)| hex_radius(q, r) <= max_qr - (z.abs() - 1) / 2)
+        .sorted_by_key(|&(q, r, z)| (z, q, r))
+        .collect();
+        let vi_map: VIMap = vi_map(&verts);
+        let adj: Adjacency = verts
+            .par_iter()
+            .enumerate()
+            .map(|(idx, &(q, r, z))| {
+                (
+                    idx as Node,
+                    [
+                        (1, 0, 0),
+                        (-1, 0, 0),
+                        (0, 1, 0),
+                        (0, -1, 0),
+                        (1, -1, 0),
+                        (-1, 1, 0),
+                        (0, 0, 2),
+                        (0, 0, -2),
+                    ]
+                    .into_iter()
+                    .filter_map(|(dq, dr, dz)| vi_map.get(&(q + dq, r + dr, z + dz)).copied())
+                    .collect::<Neighbors>(),
+                )
+            })
+            .collect();
+        (n, get_hexprism_order_from_n(n), verts, vi_map, adj)
+    }
+
     pub fn make_graph(n: u32) -> (u32, u32, VecVert, VIMap, Adjacency, ZAdjacency, ZOrder, i16) {
         let order = get_order_from_n(n);
         let max_xyz = get_max_xyz(order) as i16;
@@ -30,14 +70,87 @@ pub mod make {
         (n, order, verts, vi_map, adj, z_adj, z_order, max_xyz - 4)
     }
 
+    impl GridGraph {
+        /// The discocube of the level given.
+        pub fn discocube(level: u32) -> Self {
+            let (_, _, verts, _, adj, _, _, _) = make_graph(level);
+            GridGraph { verts, adj }
+        }
+    }
+
+    /// Grid graph of a × b × c vertices on odd coordinates two apart, as the discocubes are, starting
+    /// at (1, 1, 1).
+    pub fn make_cuboid([a, b, c]: [usize; 3]) -> (u32, VecVert, VIMap, Adjacency) {
+        let voxels: Vec<[Point; 3]> = iproduct!(0..c, 0..b, 0..a)
+            .map(|(z, y, x)| [x as Point, y as Point, z as Point])
+            .collect();
+        make_polycube(&voxels)
+    }
+
+    /// Grid graph of the unit cubes at the given integer coordinates, each cube (i, j, k) a vertex at
+    /// (2i + 1, 2j + 1, 2k + 1), ordered by z, y and x, and joined to the cubes it shares a face with.
+    pub fn make_polycube(voxels: &[[Point; 3]]) -> (u32, VecVert, VIMap, Adjacency) {
+        let verts: VecVert = voxels
+            .iter()
+            .map(|&[x, y, z]| (2 * x + 1, 2 * y + 1, 2 * z + 1))
+            .unique()
+            .sorted_by_key(|&(x, y, z)| (z, y, x))
+            .collect();
+        let vi_map: VIMap = vi_map(&verts);
+        let adj: Adjacency = grid_adjacency(&verts, &vi_map);
+        (verts.len() as u32, verts, vi_map, adj)
+    }
+
+    /// Grid graph of the shape of level n, as the shape orders its cubes by their distance from the
+    /// origin. The octahedron of level n is the discocube of `make_graph`.
+    pub fn make_shape(shape: Shape, n: u32) -> (u32, VecVert, VIMap, Adjacency) {
+        let verts: VecVert = shape_vertices(shape, n);
+        let vi_map: VIMap = vi_map(&verts);
+        let adj: Adjacency = grid_adjacency(&verts, &vi_map);
+        (verts.len() as u32, verts, vi_map, adj)
+    }
+
+    /// Grid graph of the discocube of level n with the given vertices taken out, the rest left in the
+    /// order of `make_graph`.
+    pub fn make_holed(n: u32, holes: &[Vert]) -> (u32, VecVert, VIMap, Adjacency) {
+        let verts: VecVert = shape_vertices(Shape::Octahedron, n)
+            .into_iter()
+            .filter(|vert| !holes.contains(vert))
+            .collect();
+        let vi_map: VIMap = vi_map(&verts);
+        let adj: Adjacency = grid_adjacency(&verts, &vi_map);
+        (verts.len() as u32, verts, vi_map, adj)
+    }
+
+    /// Joins every vertex to those two apart from it on one axis.
+    fn grid_adjacency(verts: &Verts, vi_map: &VIMap) -> Adjacency {
+        verts
+            .par_iter()
+            .enumerate()
+            .map(|(idx, (x, y, z))| {
+                (
+                    idx as Node,
+                    shift_xyz(arr2(&[[*x, *y, *z]]))
+                        .into_iter()
+                        .filter_map(|neighbor| vi_map.get(&neighbor).copied())
+                        .collect::<Neighbors>(),
+                )
+            })
+            .collect()
+    }
+
     pub fn vertices(max_xyz: Point) -> VecVert {
-        let max_xyz_plus_4 = max_xyz + 4;
+        shape_vertices(Shape::Octahedron, (max_xyz as u32).div_ceil(2))
+    }
+
+    pub fn shape_vertices(shape: Shape, n: u32) -> VecVert {
+        let reach = shape.reach(n);
         iproduct!(
-            (-max_xyz..=max_xyz).step_by(2),
-            (-max_xyz..=max_xyz).step_by(2),
-            (-max_xyz..=max_xyz).step_by(2)
+            (-reach..=reach).step_by(2),
+            (-reach..=reach).step_by(2),
+            (-reach..=reach).step_by(2)
         )
-        .filter(|&vert| absumv(vert) < max_xyz_plus_4)
+        .filter(|&vert| shape.contains(n, vert))
         .sorted_by_key(|&vert| (absumv(vert), vert.0, vert.1))
         .collect::<VecVert>()
     }
@@ -169,7 +282,69 @@ pub mod modify {
 }
 
 pub mod info {
-    use super::{Point, SignedIdx, Vert};
+    use super::{Itertools, Point, SignedIdx, TourSlice, Vert, Verts};
+
+    pub fn axis((x, y, z): &Vert, (a, b, c): &Vert) -> usize {
+        (0..3)
+            .find(|&i| [x, y, z][i] != [a, b, c][i])
+            .expect("Something's wrong, the same verts are being compared.")
+    }
+
+    /// Number of edges of the tour, closing edge included, running along the x, y and z axes.
+    pub fn axis_counts(tour: TourSlice, verts: &Verts) -> [usize; 3] {
+        tour.iter()
+            .circular_tuple_windows()
+            .fold([0; 3], |mut counts, (a, b)| {
+                counts[axis(&verts[*a as usize], &verts[*b as usize])] += 1;
+                counts
+            })
+    }
+
+    /// Lengths in edges of the maximal straight runs of the tour, a run being consecutive edges along
+    /// one axis. A length of one is a lone edge between two turns.
+    pub fn run_lengths(tour: TourSlice, verts: &Verts) -> Vec<usize> {
+        let axes: Vec<usize> = tour
+            .iter()
+            .circular_tuple_windows()
+            .map(|(a, b)| axis(&verts[*a as usize], &verts[*b as usize]))
+            .collect();
+        let start = (0..axes.len())
+            .find(|&idx| axes[idx] != axes[(idx + axes.len() - 1) % axes.len()])
+            .unwrap_or(0);
+        axes[start..]
+            .iter()
+            .chain(&axes[..start])
+            .group_by(|&&axis| axis)
+            .into_iter()
+            .map(|(_, run)| run.count())
+            .collect()
+    }
+
+    /// FNV-1a hash of the edge set of the tour, the same wherever the tour starts and whichever way it
+    /// runs, so two tours hash alike exactly when they are the same cycle, barring collisions.
+    pub fn cycle_hash(tour: TourSlice) -> u64 {
+        tour.iter()
+            .circular_tuple_windows()
+            .map(|(&a, &b)| (a.min(b), a.max(b)))
+            .sorted_unstable()
+            .flat_map(|(a, b)| a.to_le_bytes().into_iter().chain(b.to_le_bytes()))
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+
+    pub fn axis_spread(counts: &[usize; 3]) -> usize {
+        counts.iter().max().unwrap() - counts.iter().min().unwrap()
+    }
+
+    /// Smallest spread any hamiltonian cycle on `order` nodes can reach: a closed walk crosses every axis
+    /// an even number of times, so the counts are even and split order / 2 pairs three ways.
+    pub fn min_axis_spread(order: u32) -> usize {
+        match (order / 2) % 3 {
+            0 => 0,
+            _ => 2,
+        }
+    }
 
     pub fn axis2d((x, y, _): &Vert, (a, b, _): &Vert) -> usize {
         (0..2)
@@ -212,6 +387,16 @@ pub mod info {
         ((4.0 / 3.0) * ((n + 2) * (n + 1) * n) as f64).round() as u32
     }
 
+    /// Two stacks of hexagons of radius n down to 1, a hexagon of radius r holding 3r(r + 1) + 1 cells.
+    pub fn get_hexprism_order_from_n(n: u32) -> u32 {
+        2 * ((n + 1).pow(3) - 1)
+    }
+
+    /// Rings of hexagons out from the cell (0, 0) on axial coordinates.
+    pub fn hex_radius(q: Point, r: Point) -> Point {
+        q.abs().max(r.abs()).max((q + r).abs())
+    }
+
     pub fn get_n_from_order(order: u32) -> u32 {
         (((3.0 / 4.0) * order as f64).powf(1.0 / 3.0) - 2.0 / 3.0).round() as u32
     }
@@ -269,8 +454,7 @@ pub mod check_edge {
 }
 
 pub mod make_edges_eadjs {
-    use super::{Edge, Edges, VIMap, Vert};
-    use rayon::prelude::*;
+    use super::{par::*, Edge, Edges, VIMap, Vert};
 
     pub fn make_eadjs((a, b, c): Vert, (x, y, z): Vert, min_xyz: i16, vi_map: &VIMap) -> Edges {
         match (a != x, b != y, c != z) {
@@ -350,9 +534,14 @@ pub mod make_edges_eadjs {
 }
 
 pub mod certify {
-    use super::{fmt, Adjacency, Itertools, Solution};
+    use super::{
+        fmt,
+        info::{axis, axis_counts, axis_spread},
+        Adjacency, Itertools, Solution, Verts,
+    };
 
-    #[derive(Debug, PartialEq)]
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub enum SequenceID {
         Broken,
         HamChain,
@@ -370,7 +559,11 @@ pub mod certify {
     }
 
     pub fn id_seq(seq: &Solution, adj: &Adjacency) -> SequenceID {
-        if seq.iter().duplicates().count() > 0 || seq.len() != adj.len() {
+        if seq.is_empty()
+            || seq.len() != adj.len()
+            || seq.iter().duplicates().count() > 0
+            || seq.iter().any(|node| !adj.contains_key(node))
+        {
             return SequenceID::Broken;
         }
         match seq
@@ -382,6 +575,19 @@ pub mod certify {
             false => SequenceID::Broken,
         }
     }
+
+    pub fn is_turning(seq: &Solution, adj: &Adjacency, verts: &Verts) -> bool {
+        id_seq(seq, adj) == SequenceID::HamCycle
+            && seq.iter().circular_tuple_windows().all(|(a, b, c)| {
+                axis(&verts[*a as usize], &verts[*b as usize])
+                    != axis(&verts[*b as usize], &verts[*c as usize])
+            })
+    }
+
+    pub fn is_balanced(seq: &Solution, adj: &Adjacency, verts: &Verts, tolerance: usize) -> bool {
+        id_seq(seq, adj) == SequenceID::HamCycle
+            && axis_spread(&axis_counts(seq, verts)) <= tolerance
+    }
 }
 
 pub mod translate {
@@ -1,14 +1,15 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use itertools::Itertools;
 use ndarray::{arr2, Array2};
-use rayon::prelude::*;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 
 use super::{
     defs::{
-        Adjacency, Bobbins, Count, Loom, Point, Solution, Spool, Subtours, Tour, TourSliceThick,
-        VIMap, Vert, Verts, Warps, Weaver, Yarn, ZAdjacency, ZOrder,
+        Adjacency, Bobbins, Count, Edge, Edges, Loom, Point, Solution, Spool, Subtours, Tour,
+        TourSliceThick, VIMap, Vert, Verts, Warps, Weaver, Yarn, ZAdjacency, ZOrder,
     },
+    par::*,
     utils::{
         info::absumv2dc,
         make_edges_eadjs::{make_eadjs, make_edges},
@@ -17,13 +18,13 @@ use super::{
 
 pub fn weave(
     adj: &Adjacency,
-    vi_map: VIMap,
+    vi_map: &VIMap,
     verts: &Verts,
     z_adj: ZAdjacency,
     z_order: ZOrder,
     min_xyz: Point,
 ) -> Solution {
-    let mut loom = wrap_and_reflect_loom(&vi_map, verts, z_adj, z_order);
+    let mut loom = wrap_and_reflect_loom(vi_map, verts, z_adj, z_order, None).unwrap();
     let mut weaver: Weaver = Weaver::new(loom[0].split_off(0), verts, true, min_xyz);
     let mut loom = loom
         .split_off(1)
@@ -31,31 +32,96 @@ pub fn weave(
         .map(|mut data| data.drain(..).collect())
         .collect::<Vec<Vec<_>>>();
     loom.iter_mut().for_each(|warp| {
-        let warp_edges = weaver.make_edges_for(warp);
-        if let Some((m, n)) = (&weaver.edges()
-            & &warp_edges
-                .iter()
-                .flat_map(|(m, n)| {
-                    make_edges(verts[*m as usize], verts[*n as usize], min_xyz, &vi_map)
-                })
-                .collect())
-            .into_iter()
-            .next()
+        if let Some((edge, wedge)) = find_join(&mut weaver, warp, adj, vi_map, verts, min_xyz, None)
         {
-            if let Some((o, p)) =
-                (&make_eadjs(verts[m as usize], verts[n as usize], min_xyz, &vi_map) & &warp_edges)
-                    .into_iter()
-                    .next()
-            {
-                weaver.join((m, n), if adj[&n].contains(&o){(o, p)} else {(p, o)}, warp);
-            }
+            weaver.join(edge, wedge, warp);
         }
     });
     weaver.get_nodes()
 }
 
-fn wrap_and_reflect_loom(vi_map: &VIMap, verts: &Verts, z_adj: ZAdjacency, z_order: ZOrder) -> Loom {
-    let spool: Spool = spin_and_color_yarn(z_adj);
+/// Weaves with the free choices left to chance: ties between equally far fibers while spinning the
+/// yarn, the order in which warps are taken up and the edges along which each one is joined. Not every
+/// draw weaves through, a warp may find no edge left to join along.
+pub fn weave_seeded(
+    adj: &Adjacency,
+    vi_map: &VIMap,
+    verts: &Verts,
+    z_adj: ZAdjacency,
+    z_order: ZOrder,
+    min_xyz: Point,
+    seed: u64,
+) -> Result<Solution, &'static str> {
+    let rng = &mut StdRng::seed_from_u64(seed);
+    let mut loom = wrap_and_reflect_loom(vi_map, verts, z_adj, z_order, Some(rng))?;
+    let mut weaver: Weaver = Weaver::new(loom[0].split_off(0), verts, true, min_xyz);
+    let mut loom = loom
+        .split_off(1)
+        .into_iter()
+        .map(|mut data| data.drain(..).collect())
+        .collect::<Vec<Vec<_>>>();
+    loom.shuffle(rng);
+    let mut loom: VecDeque<Tour> = loom.into();
+    let mut idle = 0;
+    while let Some(mut warp) = loom.pop_front() {
+        match find_join(&mut weaver, &warp, adj, vi_map, verts, min_xyz, Some(rng)) {
+            Some((edge, wedge)) => {
+                weaver.join(edge, wedge, &mut warp);
+                idle = 0;
+            }
+            None if idle < loom.len() => {
+                loom.push_back(warp);
+                idle += 1;
+            }
+            None => return Err("a warp found no edge left to join the weaver along"),
+        }
+    }
+    Ok(weaver.get_nodes())
+}
+
+fn find_join(
+    weaver: &mut Weaver,
+    warp: &Tour,
+    adj: &Adjacency,
+    vi_map: &VIMap,
+    verts: &Verts,
+    min_xyz: Point,
+    mut rng: Option<&mut StdRng>,
+) -> Option<(Edge, Edge)> {
+    let warp_edges = weaver.make_edges_for(warp);
+    let edges = &weaver.edges()
+        & &warp_edges
+            .iter()
+            .flat_map(|(m, n)| make_edges(verts[*m as usize], verts[*n as usize], min_xyz, vi_map))
+            .collect();
+    let (m, n) = pick(edges, rng.as_deref_mut())?;
+    let wedges = &make_eadjs(verts[m as usize], verts[n as usize], min_xyz, vi_map) & &warp_edges;
+    let (o, p) = pick(wedges, rng)?;
+    Some(((m, n), if adj[&n].contains(&o) { (o, p) } else { (p, o) }))
+}
+
+/// The least edge when weaving plainly, a random one, drawn in a fixed order, when weaving from a seed.
+/// Either way the choice does not hang on the order the set happens to iterate in.
+fn pick(edges: Edges, rng: Option<&mut StdRng>) -> Option<Edge> {
+    match rng {
+        None => edges.into_iter().min(),
+        Some(rng) => edges
+            .into_iter()
+            .sorted()
+            .collect::<Vec<Edge>>()
+            .choose(rng)
+            .copied(),
+    }
+}
+
+fn wrap_and_reflect_loom(
+    vi_map: &VIMap,
+    verts: &Verts,
+    z_adj: ZAdjacency,
+    z_order: ZOrder,
+    rng: Option<&mut StdRng>,
+) -> Result<Loom, &'static str> {
+    let spool: Spool = spin_and_color_yarn(z_adj, rng)?;
     let mut bobbins: Bobbins = Vec::new();
     let mut loom: Loom = Loom::new();
     for (z, length) in z_order {
@@ -76,25 +142,56 @@ fn wrap_and_reflect_loom(vi_map: &VIMap, verts: &Verts, z_adj: ZAdjacency, z_ord
                 .collect::<Tour>(),
         )
     });
-    loom
+    Ok(loom)
+}
+
+fn spin_and_color_yarn(z_adj: ZAdjacency, rng: Option<&mut StdRng>) -> Result<Spool, &'static str> {
+    let natur: Yarn = Array2::from(spin(&z_adj, rng)?);
+    let color: Yarn = natur.dot(&arr2(&[[-1, 0], [0, -1]])) + arr2(&[[0, 2]]);
+    Ok(Spool::from([(3, natur), (1, color)]))
+}
+
+/// Spins the yarn through a layer of cells, each a neighbour of the cells two away from it on x or on
+/// y, such as the z = -1 layer of the discocube of level n: the diamond of odd cells with
+/// |x| + |y| <= 2n, 2n(n + 1) of them. The path starts at the greatest cell and always steps to the
+/// unspun neighbour furthest from the origin, turning at every step of the last five. When it comes
+/// through, which it does for every diamond, it passes through every cell exactly once, stepping to
+/// a neighbour each time, and on a diamond it spirals in to end on one of the four centre cells.
+/// Repeated cells count once. Should the rule lead into a dead end on some other layer, that is an
+/// error.
+pub fn spin_path(layer_cells: TourSliceThick) -> Result<Vec<[i16; 2]>, &'static str> {
+    let cells: HashSet<[i16; 2]> = layer_cells.iter().copied().collect();
+    let z_adj: ZAdjacency = cells
+        .iter()
+        .map(|&[x, y]| {
+            let neighbors = [[x + 2, y], [x - 2, y], [x, y + 2], [x, y - 2]];
+            (
+                [x, y],
+                neighbors
+                    .into_iter()
+                    .filter(|cell| cells.contains(cell))
+                    .collect(),
+            )
+        })
+        .collect();
+    spin(&z_adj, None)
 }
 
-fn spin_and_color_yarn(z_adj: ZAdjacency) -> Spool {
+fn spin(z_adj: &ZAdjacency, mut rng: Option<&mut StdRng>) -> Result<Vec<[i16; 2]>, &'static str> {
     let order_z = z_adj.len();
-    let spindle: &mut Vec<[i16; 2]> = &mut Vec::with_capacity(order_z);
-    let start: [i16; 2] = *z_adj.keys().max().unwrap();
+    let mut spindle: Vec<[i16; 2]> = Vec::with_capacity(order_z);
+    let start: [i16; 2] = *z_adj.keys().max().ok_or("the layer has no cells to spin")?;
     let mut visited: HashMap<[i16; 2], bool> = HashMap::with_capacity(order_z);
     visited.insert(start, true);
     spindle.push(start);
-    let tail = order_z - 5;
-    (1..order_z).for_each(|idx| {
-        let next_fiber = get_unspun(spindle, &z_adj, idx, tail, &mut visited);
+    let tail = order_z.saturating_sub(5).max(2);
+    for idx in 1..order_z {
+        let next_fiber = get_unspun(&spindle, z_adj, idx, tail, &mut visited, rng.as_deref_mut())
+            .ok_or("the yarn ran into a dead end while spinning")?;
         spindle.push(next_fiber);
         visited.insert(next_fiber, true);
-    });
-    let natur: Yarn = Array2::from(spindle.drain(..).collect::<Vec<_>>());
-    let color: Yarn = natur.dot(&arr2(&[[-1, 0], [0, -1]])) + arr2(&[[0, 2]]);
-    Spool::from([(3, natur), (1, color)])
+    }
+    Ok(spindle)
 }
 
 fn get_unspun(
@@ -103,20 +200,30 @@ fn get_unspun(
     idx: usize,
     tail: usize,
     visited: &mut HashMap<[i16; 2], bool>,
-) -> [i16; 2] {
+    rng: Option<&mut StdRng>,
+) -> Option<[i16; 2]> {
     let [x, y] = *spindle.last().unwrap();
-    *z_adj[&[x, y]]
+    let fibers: Vec<([i16; 2], i16)> = z_adj[&[x, y]]
         .iter()
         .filter_map(|node| match (visited.get(node), *node) {
             (Some(true), _) => None,
             (None, [m, n]) if idx < tail || (spindle[spindle.len() - 2][0] == x) != (x == m) => {
-                Some((node, absumv2dc([m, n])))
+                Some((*node, absumv2dc([m, n])))
             }
             _ => None,
         })
-        .max_by_key(|&(_, absumv)| absumv)
-        .unwrap()
-        .0
+        .collect();
+    let furthest = fibers.iter().map(|&(_, absumv)| absumv).max()?;
+    let ties: Vec<[i16; 2]> = fibers
+        .into_iter()
+        .filter(|&(_, absumv)| absumv == furthest)
+        .map(|(node, _)| node)
+        .sorted()
+        .collect();
+    match rng {
+        Some(rng) => Some(ties[rng.gen_range(0..ties.len())]),
+        None => ties.last().copied(),
+    }
 }
 
 fn get_warps(
@@ -157,7 +264,7 @@ fn cut_yarn(yarn: Tour, cuts: &Bobbins) -> Subtours {
         .enumerate()
     {
         if e == last_idx && idx != last_ix {
-            if let Some(first_slice) = yarn.get(prev as usize + 1..idx) {
+            if let Some(first_slice) = yarn.get((prev + 1) as usize..idx) {
                 if !first_slice.is_empty() {
                     subtours.push(if cuts.contains(&first_slice[0]) {
                         first_slice.to_vec()
@@ -176,7 +283,7 @@ fn cut_yarn(yarn: Tour, cuts: &Bobbins) -> Subtours {
                 }
             }
         } else {
-            if let Some(first_slice) = yarn.get(prev as usize + 1..=idx) {
+            if let Some(first_slice) = yarn.get((prev + 1) as usize..=idx) {
                 if !first_slice.is_empty() {
                     subtours.push(if cuts.contains(&first_slice[0]) {
                         first_slice.to_vec()
@@ -232,3 +339,109 @@ fn wrap_warps_onto_loom(mut warps: Warps, loom: &mut Loom) {
         loom.append(&mut vec![seq.drain(..).collect::<VecDeque<_>>()]);
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{spin_path, weave, weave_seeded};
+    use crate::graph::utils::{
+        certify::{id_seq, SequenceID},
+        make::make_graph,
+    };
+
+    /// The z = -1 layer of the discocube of level n.
+    fn diamond(n: i16) -> Vec<[i16; 2]> {
+        let reach = 2 * n - 1;
+        (-reach..=reach)
+            .step_by(2)
+            .flat_map(|x| (-reach..=reach).step_by(2).map(move |y| [x, y]))
+            .filter(|&[x, y]| x.abs() + y.abs() <= 2 * n)
+            .collect()
+    }
+
+    #[test]
+    fn spins_every_diamond_through() {
+        for n in 1..=100 {
+            let mut cells = diamond(n);
+            let path = spin_path(&cells).unwrap();
+            assert_eq!(path.len(), 2 * n as usize * (n as usize + 1));
+            assert_eq!(path[0], *cells.iter().max().unwrap());
+            assert!(path.windows(2).all(|pair| (pair[0][0] - pair[1][0]).abs()
+                + (pair[0][1] - pair[1][1]).abs()
+                == 2));
+            let [x, y] = path[path.len() - 1];
+            assert_eq!((x.abs(), y.abs()), (1, 1));
+            let mut spun = path.clone();
+            spun.sort();
+            cells.sort();
+            assert_eq!(spun, cells);
+        }
+    }
+
+    #[test]
+    fn turns_at_every_step_of_the_tail() {
+        for n in 2..=40 {
+            let path = spin_path(&diamond(n)).unwrap();
+            assert!(path[path.len() - 6..]
+                .windows(3)
+                .all(|step| (step[0][0] == step[1][0]) != (step[1][0] == step[2][0])));
+        }
+    }
+
+    #[test]
+    fn ignores_repeated_cells() {
+        let mut cells = diamond(5);
+        cells.extend(diamond(3));
+        assert_eq!(spin_path(&cells), spin_path(&diamond(5)));
+    }
+
+    #[test]
+    fn spins_a_single_cell() {
+        assert_eq!(spin_path(&[[1, 1]]), Ok(vec![[1, 1]]));
+    }
+
+    #[test]
+    fn errs_on_an_empty_layer() {
+        assert!(spin_path(&[]).is_err());
+    }
+
+    #[test]
+    fn weaves_every_small_level() {
+        for level in 1..=12 {
+            let (_, order, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(level);
+            let tour = weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz);
+            assert_eq!(tour.len(), order as usize);
+            assert_eq!(id_seq(&tour, &adj), SequenceID::HamCycle);
+        }
+    }
+
+    #[test]
+    fn weaves_the_same_cycle_every_time() {
+        for level in [2, 7, 15] {
+            let tours: Vec<_> = (0..4)
+                .map(|_| {
+                    let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(level);
+                    weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz)
+                })
+                .collect();
+            assert!(tours.windows(2).all(|pair| pair[0] == pair[1]));
+        }
+    }
+
+    #[test]
+    fn weaves_the_same_cycle_from_the_same_seed() {
+        let weave_from = |seed| {
+            let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(9);
+            weave_seeded(&adj, &vi_map, &verts, z_adj, z_order, min_xyz, seed)
+        };
+        for seed in 0..4 {
+            assert_eq!(weave_from(seed), weave_from(seed));
+        }
+    }
+
+    #[test]
+    fn errs_on_a_dead_end() {
+        assert!(spin_path(&[[1, 1], [9, 9]]).is_err());
+        let plus = [[1, 1], [3, 1], [-1, 1], [1, 3], [1, -1]];
+        assert!(spin_path(&plus).is_err());
+    }
+}
@@ -0,0 +1,3 @@
+//! Weaves, certifies and exports hamiltonian cycles on the discocubes and the other grid graphs, for
+//! other crates to take up; the binary is a command line over the same graph module.
+pub mod graph;
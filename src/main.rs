@@ -11,55 +11,702 @@
 /// continues to the next orders up to the 100th which is an order with 1,373,600 vertices,
 /// makes graph, solves it
 /// 1 (start with order 8 end at order 1,373,600) 100
+///
+/// ```
+/// cargo run --release [N] [N_UPPER_INCLUSIVE] --mode always-turning --certify
+/// ```
+/// --mode weave (default) | always-turning | balanced
+/// --tolerance largest spread allowed between the x, y and z edge counts in balanced mode, defaults
+///   to the smallest spread possible for the order (0 or 2)
+/// --darns K square flips balanced mode may try before it gives up, defaults to 200 per vertex and
+///   ten million at most
+/// --polish turns | runs | balance | max-run polishes the solution of any mode with square flips,
+///   certifying the cycle as it goes, and prints the straight nodes, runs, longest run and axis
+///   spread before and after.
+/// --seed S weaves from seed S, leaving the ties while spinning the yarn, the order the warps are
+///   taken up in and the edges they are joined along to chance, then wanders the cycle with random
+///   square flips.
+/// --samples K draws K cycles from seeds S, S + 1, .. (S defaults to 0) and reports how many of
+///   them are distinct, telling cycles apart by a hash of their edges, and how many are distinct up
+///   to symmetry.
+/// --fingerprint prints the 256-bit fingerprint of the canonical form of the solution, shared by
+///   every cycle that is the same up to the 48 symmetries of the octahedron, starting node and
+///   direction.
+/// --graph discocube (default) | hexprism, the hexprism honeycomb diamond of the same level, which
+///   is woven and certified but takes no other options.
+/// --graph cube | cuboid | octahedron | truncated-octahedron | cuboctahedron | ball |
+///   rhombic-dodecahedron | centered-octahedron solves the polycube of that shape at each level,
+///   octahedron being the discocube, and combines as --cuboid does.
+/// --info prints the order of the graph at each level, from its formula, without making or solving
+///   it, and for the polycubes the black cubes less the white ones and whether that leaves room for
+///   a hamiltonian cycle or path.
+/// --cuboid AxBxC solves the A by B by C grid graph instead of the discocubes, or when it has no
+///   hamiltonian cycle prints a proof of that, which --certify checks against the graph. The sides
+///   are at most 16383 and hold 2^30 vertices at most.
+/// --polycube FILE solves the polycube in FILE, a MagicaVoxel .vox file or text holding a line of
+///   x y z per voxel or a '#' mask per layer, or prints why it has no hamiltonian cycle: a leaf,
+///   unequal colour classes, a voxel out of reach or a cut voxel. When merging gets stuck it prints
+///   UNDECIDED and fails. Combines as --cuboid does.
+/// --holes "X,Y,Z X,Y,Z .." solves the discocubes with the vertices given taken out, odd
+///   coordinates split by spaces or semicolons, mending the woven cycle around the holes, or prints
+///   why none is left. Combines as --cuboid does.
+/// --grow grows the discocube a shell at a time from level 1 up to N_UPPER, the cycle of each level
+///   from the one two below, and prints from level N on how many edges every cycle keeps from that
+///   one and shares with the one just below. Only combines with --certify.
+/// --dimension D plaits hamiltonian cycles on the balls of D dimensions, 2 to 5, of levels N to
+///   N_UPPER: the odd points whose absolute coordinates sum to 2N + D - 2 at most, the discocube
+///   being the ball of three. Only combines with --certify.
+/// --polyomino FILE solves the hole-free polyomino drawn in FILE, '#' for a cell and '.' or ' ' for
+///   a gap, or prints why it has no hamiltonian cycle, which --certify checks. When it can neither
+///   find a cycle nor prove there is none it prints UNDECIDED and fails.
+/// --hcp DIR writes every discocube solved to DIR in TSPLIB's formats, its graph as discocube_N.hcp
+///   and its cycle as discocube_N.tour, for Concorde and LKH and the FHCP tools to take up.
+/// --read-hcp FILE reads a graph from the TSPLIB .hcp file FILE and a tour of it from the .tour
+///   file beside it, and certifies the tour.
+/// --export DIR writes the graph of every discocube solved to DIR as discocube_N.col in the DIMACS
+///   format, discocube_N.edges as a plain edge list and discocube_N.graphml in GraphML with the
+///   coordinates.
+/// --read-graph FILE reads a graph from FILE, a DIMACS .col file, a .graphml file or else an edge
+///   list, and a tour of it from the TSPLIB .tour file beside it, and certifies the tour.
+/// --dot DIR writes every discocube solved, up to level 6, to DIR as discocube_N.dot for Graphviz,
+///   its vertices pinned where they lie and its cycle bold and coloured along its length. Draw it
+///   with neato -n.
+/// --projection isometric (default) | layers lays the vertices of --dot out in the isometric view
+///   or as their z-layers side by side.
+/// --npy DIR writes every discocube solved to DIR for NumPy, as discocube_N_verts.npy, an (N, 3)
+///   int16 array of the coordinates, discocube_N_tour.npy, an (N,) uint32 array of the cycle, and
+///   discocube_N_edges.npy, an (E, 2) uint32 array of the edges.
+/// --npz DIR writes the same three arrays to DIR as discocube_N.npz, named verts, tour and edges.
+/// --json DIR writes a report of every discocube solved to DIR as discocube_N.json, with its graph,
+///   the weaver of its cycle, how the cycle certifies and how long each step took. Needs the serde
+///   feature.
+/// --read-json FILE reads a report written by --json back, rethreads its weaver onto its graph and
+///   certifies the cycle again, failing if it does not. Needs the serde feature.
+/// --certify checks every solution, for always-turning that no two consecutive edges share an axis,
+///   and for balanced that the axis counts are within the tolerance.
+///
+/// ```
+/// cargo run --release -- serve [--port P] [--cache DIR] [--workers K] [--memory MB]
+/// ```
+/// serve runs a local HTTP server on 127.0.0.1:P (7474) answering GET /solve?level=N with the cycle
+///   as JSON, POST /certify?level=N with the verdict on the tour in the body, and GET
+///   /export?level=N&format=F with the graph or cycle as hcp, tour, col, edges, graphml, dot or
+///   npz. Solved cycles are cached in DIR (cache) and certified again when read, K workers (the
+///   cores) answer at once and the levels being worked on and the request bodies share MB megabytes
+///   (2048). A client stalling for 30 seconds is cut off.
 /////////////////////////////////////////////////////////////////////////////
-extern crate rayon;
+use std::{env, fmt, fs, str::FromStr, time::Instant};
 
-use std::{env, time::Instant};
+use itertools::Itertools;
 
-pub mod graph;
+mod serve;
 
-use graph::{
+use hamcycle::graph::{
+    balance,
+    canonical::{self, Fingerprint},
+    cuboid::{self, Obstruction},
     defs::*,
+    formats::{self, Projection},
+    grow::Growth,
+    hexprism, lattice, mend, npy,
+    polish::{self, Finish},
+    polycube,
+    polyomino::Polyomino,
+    report::Timings,
+    sample,
+    shape::Shape,
+    tsplib, turn,
     utils::certify::{self, SequenceID},
-    utils::make::make_graph,
+    utils::info::{
+        axis_counts, axis_spread, cycle_hash, get_hexprism_order_from_n, min_axis_spread,
+        run_lengths,
+    },
+    utils::make::{make_cuboid, make_graph, make_hexprism, make_holed, make_polycube, make_shape},
     weave,
 };
 
-pub fn main() -> Result<(), &'static str> {
-    let args: Vec<String> = env::args().collect();
-    let n_start: u32 = match args.get(1) {
-        Some(arg) => match arg.parse::<u32>() {
-            Ok(parsed) => {
-                if parsed == 0 {
-                    1
-                } else {
-                    parsed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    Weave,
+    AlwaysTurning,
+    /// Largest spread allowed between the axis counts, the smallest possible one when None, and the
+    /// darns allowed to reach it, `balance::darns` of the order when None.
+    Balanced {
+        tolerance: Option<usize>,
+        darns: Option<usize>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Settings {
+    pub mode: Mode,
+    pub polish: Option<Finish>,
+    /// Seed of the first sampled cycle and how many to sample, weaving plainly when None.
+    pub sample: Option<(u64, u64)>,
+    pub fingerprint: bool,
+    pub certify: bool,
+}
+
+impl Settings {
+    /// Takes the flags that choose how each cycle is found and checked out of the arguments.
+    pub fn parse(args: &mut Vec<String>) -> Result<Self, &'static str> {
+        let mode: Mode = match take_flag(args, "--mode")? {
+            Some(mode) => mode.parse()?,
+            None => Mode::Weave,
+        };
+        let mode = match (mode, take_flag(args, "--tolerance")?) {
+            (Mode::Balanced { darns, .. }, Some(tolerance)) => match tolerance.parse::<usize>() {
+                Ok(tolerance) => Mode::Balanced {
+                    tolerance: Some(tolerance),
+                    darns,
+                },
+                Err(_) => return Err("--tolerance expects a non-negative integer"),
+            },
+            (_, Some(_)) => return Err("--tolerance only applies to --mode balanced"),
+            (mode, None) => mode,
+        };
+        let mode = match (mode, take_flag(args, "--darns")?) {
+            (Mode::Balanced { tolerance, .. }, Some(darns)) => match darns.parse::<usize>() {
+                Ok(darns) if darns > 0 => Mode::Balanced {
+                    tolerance,
+                    darns: Some(darns),
+                },
+                _ => return Err("--darns expects a positive integer"),
+            },
+            (_, Some(_)) => return Err("--darns only applies to --mode balanced"),
+            (mode, None) => mode,
+        };
+        let polish = match take_flag(args, "--polish")? {
+            Some(finish) => Some(finish.parse::<Finish>()?),
+            None => None,
+        };
+        let sample = match (take_flag(args, "--seed")?, take_flag(args, "--samples")?) {
+            (None, None) => None,
+            (seed, samples) => Some((
+                match seed {
+                    Some(seed) => seed
+                        .parse()
+                        .or(Err("--seed expects a non-negative integer"))?,
+                    None => 0,
+                },
+                match samples {
+                    Some(samples) => match samples.parse() {
+                        Ok(samples) if samples > 0 => samples,
+                        _ => return Err("--samples expects a positive integer"),
+                    },
+                    None => 1,
+                },
+            )),
+        };
+        if sample.is_some() && mode != Mode::Weave {
+            return Err("--seed and --samples only apply to --mode weave");
+        }
+        Ok(Settings {
+            mode,
+            polish,
+            sample,
+            fingerprint: take_switch(args, "--fingerprint"),
+            certify: take_switch(args, "--certify"),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Graph {
+    Discocube,
+    Hexprism,
+    /// Any other shape of the catalogue, solved as a polycube.
+    Shape(Shape),
+}
+
+/// Directories every discocube solved is written to, in each of the formats asked for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Writes {
+    pub hcp: Option<String>,
+    pub export: Option<String>,
+    pub dot: Option<String>,
+    pub projection: Projection,
+    pub npy: Option<String>,
+    pub npz: Option<String>,
+    pub json: Option<String>,
+}
+
+impl Writes {
+    /// Takes the flags that choose where and how the discocubes are written out of the arguments.
+    pub fn parse(args: &mut Vec<String>) -> Result<Self, &'static str> {
+        let dot = take_flag(args, "--dot")?;
+        let projection: Projection = match take_flag(args, "--projection")? {
+            Some(projection) if dot.is_some() => projection.parse()?,
+            Some(_) => return Err("--projection only lays out --dot"),
+            None => Projection::Isometric,
+        };
+        let json = take_flag(args, "--json")?;
+        if json.is_some() && !cfg!(feature = "serde") {
+            return Err(NO_SERDE);
+        }
+        Ok(Writes {
+            hcp: take_flag(args, "--hcp")?,
+            export: take_flag(args, "--export")?,
+            dot,
+            projection,
+            npy: take_flag(args, "--npy")?,
+            npz: take_flag(args, "--npz")?,
+            json,
+        })
+    }
+    /// Whether any of the formats is written at all.
+    pub fn any(&self) -> bool {
+        [
+            &self.hcp,
+            &self.export,
+            &self.dot,
+            &self.npy,
+            &self.npz,
+            &self.json,
+        ]
+        .iter()
+        .any(|dir| dir.is_some())
+    }
+}
+
+impl FromStr for Graph {
+    type Err = &'static str;
+
+    fn from_str(graph: &str) -> Result<Self, Self::Err> {
+        match graph {
+            "discocube" | "octahedron" => Ok(Graph::Discocube),
+            "hexprism" => Ok(Graph::Hexprism),
+            _ => match graph.parse() {
+                Ok(shape) => Ok(Graph::Shape(shape)),
+                Err(_) => Err("unknown graph, expected one of: discocube, hexprism, cube, cuboid, octahedron, truncated-octahedron, cuboctahedron, ball, rhombic-dodecahedron, centered-octahedron"),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Graph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Graph::Discocube => write!(f, "discocube"),
+            Graph::Hexprism => write!(f, "hexprism"),
+            Graph::Shape(shape) => write!(f, "{shape}"),
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = &'static str;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "weave" => Ok(Mode::Weave),
+            "always-turning" => Ok(Mode::AlwaysTurning),
+            "balanced" => Ok(Mode::Balanced {
+                tolerance: None,
+                darns: None,
+            }),
+            _ => Err("unknown mode, expected one of: weave, always-turning, balanced"),
+        }
+    }
+}
+
+/// What one run of the binary does, parsed from its arguments before any of it is done.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Serve(serve::Options),
+    ReadHcp(String),
+    ReadGraph(String),
+    ReadJson(String),
+    Polyomino {
+        path: String,
+        certify: bool,
+    },
+    Cuboid {
+        dims: [usize; 3],
+        settings: Settings,
+    },
+    Polycube {
+        path: String,
+        settings: Settings,
+    },
+    Grow {
+        levels: (u32, u32),
+        certify: bool,
+    },
+    Plait {
+        dimension: usize,
+        levels: (u32, u32),
+        certify: bool,
+    },
+    Solve(Run),
+}
+
+/// The graphs of the levels from the first to the last, inclusive, and what is done with each.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Run {
+    pub graph: Graph,
+    pub levels: (u32, u32),
+    pub settings: Settings,
+    /// Prints the order of each level instead of solving it.
+    pub info: bool,
+    pub holes: Option<Vec<Vert>>,
+    pub writes: Writes,
+}
+
+impl Command {
+    /// Takes the command out of the arguments, failing when the flags given do not combine or when
+    /// anything is left that no flag takes.
+    pub fn parse(mut args: Vec<String>) -> Result<Self, &'static str> {
+        if args.get(1).map(String::as_str) == Some("serve") {
+            args.remove(1);
+            return Ok(Command::Serve(serve::Options::parse(args)?));
+        }
+        let settings = Settings::parse(&mut args)?;
+        let Settings {
+            mode,
+            polish,
+            sample,
+            fingerprint,
+            certify,
+        } = settings;
+        let info = take_switch(&mut args, "--info");
+        let cuboid = match take_flag(&mut args, "--cuboid")? {
+            Some(dims) => Some(parse_dims(&dims)?),
+            None => None,
+        };
+        let polycube = take_flag(&mut args, "--polycube")?;
+        if cuboid.is_some() && polycube.is_some() {
+            return Err("--cuboid and --polycube do not combine");
+        }
+        if (cuboid.is_some() || polycube.is_some()) && (mode != Mode::Weave || sample.is_some()) {
+            return Err(
+                "--cuboid and --polycube only combine with --polish, --fingerprint and --certify",
+            );
+        }
+        let plain = mode == Mode::Weave && polish.is_none() && sample.is_none() && !fingerprint;
+        if let Some(path) = take_flag(&mut args, "--read-hcp")? {
+            if !plain || info || cuboid.is_some() || polycube.is_some() || args.len() > 1 {
+                return Err("--read-hcp takes no other options");
+            }
+            return Ok(Command::ReadHcp(path));
+        }
+        if let Some(path) = take_flag(&mut args, "--read-graph")? {
+            if !plain || info || cuboid.is_some() || polycube.is_some() || args.len() > 1 {
+                return Err("--read-graph takes no other options");
+            }
+            return Ok(Command::ReadGraph(path));
+        }
+        if let Some(path) = take_flag(&mut args, "--read-json")? {
+            if !plain || info || cuboid.is_some() || polycube.is_some() || args.len() > 1 {
+                return Err("--read-json takes no other options");
+            }
+            return Ok(Command::ReadJson(path));
+        }
+        if let Some(path) = take_flag(&mut args, "--polyomino")? {
+            if !plain || info || cuboid.is_some() || polycube.is_some() || args.len() > 1 {
+                return Err("--polyomino only combines with --certify");
+            }
+            return Ok(Command::Polyomino { path, certify });
+        }
+        let graph: Graph = match take_flag(&mut args, "--graph")? {
+            Some(graph) => graph.parse()?,
+            None => Graph::Discocube,
+        };
+        let holes = match take_flag(&mut args, "--holes")? {
+            Some(holes) => Some(parse_holes(&holes)?),
+            None => None,
+        };
+        if holes.is_some()
+            && (graph != Graph::Discocube
+                || mode != Mode::Weave
+                || sample.is_some()
+                || cuboid.is_some()
+                || polycube.is_some())
+        {
+            return Err("--holes only combines with --polish, --fingerprint and --certify");
+        }
+        if graph == Graph::Hexprism && (!plain || cuboid.is_some() || polycube.is_some()) {
+            return Err("--graph hexprism only combines with --certify");
+        }
+        if matches!(graph, Graph::Shape(_))
+            && (mode != Mode::Weave || sample.is_some() || cuboid.is_some() || polycube.is_some())
+        {
+            return Err(
+                "--graph of a polycube shape only combines with --polish, --fingerprint and --certify",
+            );
+        }
+        let writes = Writes::parse(&mut args)?;
+        if writes.any() && (graph != Graph::Discocube || holes.is_some() || info) {
+            return Err(
+                "--hcp, --export, --dot, --npy, --npz and --json only write the discocubes",
+            );
+        }
+        let grow = take_switch(&mut args, "--grow");
+        if grow && (!plain || graph != Graph::Discocube || holes.is_some() || info || writes.any())
+        {
+            return Err("--grow only combines with --certify");
+        }
+        let dimension = match take_flag(&mut args, "--dimension")? {
+            Some(dimension) => match dimension.parse::<usize>() {
+                Ok(dimension) if (2..=5).contains(&dimension) => Some(dimension),
+                _ => return Err("--dimension takes 2, 3, 4 or 5"),
+            },
+            None => None,
+        };
+        if dimension.is_some()
+            && (!plain
+                || graph != Graph::Discocube
+                || holes.is_some()
+                || info
+                || grow
+                || writes.any())
+        {
+            return Err("--dimension only combines with --certify");
+        }
+        if let Some(dims) = cuboid {
+            if info || writes.any() || grow || dimension.is_some() || args.len() > 1 {
+                return Err(
+                    "--cuboid and --polycube only combine with --polish, --fingerprint and --certify",
+                );
+            }
+            return Ok(Command::Cuboid { dims, settings });
+        }
+        if let Some(path) = polycube {
+            if info || writes.any() || grow || dimension.is_some() || args.len() > 1 {
+                return Err(
+                    "--cuboid and --polycube only combine with --polish, --fingerprint and --certify",
+                );
+            }
+            return Ok(Command::Polycube { path, settings });
+        }
+        let (n_start, n_end) = take_levels(&mut args)?;
+        if writes.dot.is_some() && n_end > 6 {
+            return Err("--dot only draws the discocubes up to level 6");
+        }
+        if grow {
+            return Ok(Command::Grow {
+                levels: (n_start, n_end),
+                certify,
+            });
+        }
+        if let Some(dimension) = dimension {
+            return Ok(Command::Plait {
+                dimension,
+                levels: (n_start, n_end),
+                certify,
+            });
+        }
+        Ok(Command::Solve(Run {
+            graph,
+            levels: (n_start, n_end),
+            settings,
+            info,
+            holes,
+            writes,
+        }))
+    }
+
+    /// Runs the command, printing what it finds.
+    pub fn run(self) -> Result<(), &'static str> {
+        match self {
+            Command::Serve(options) => serve::serve(options),
+            Command::ReadHcp(path) => find_hcp_tour(&path),
+            Command::ReadGraph(path) => find_graph_tour(&path),
+            Command::ReadJson(path) => find_json_tour(&path),
+            Command::Polyomino { path, certify } => find_polyomino(&path, certify),
+            Command::Cuboid { dims, settings } => find_cuboid(dims, settings).map(|_| ()),
+            Command::Polycube { path, settings } => find_polycube(&path, settings).map(|_| ()),
+            Command::Grow {
+                levels: (n_start, n_end),
+                certify,
+            } => find_grown(n_start, n_end, certify),
+            Command::Plait {
+                dimension,
+                levels: (n_start, n_end),
+                certify,
+            } => {
+                for level in n_start..=n_end {
+                    match dimension {
+                        2 => find_plaited::<2>(level, certify),
+                        3 => find_plaited::<3>(level, certify),
+                        4 => find_plaited::<4>(level, certify),
+                        _ => find_plaited::<5>(level, certify),
+                    }
                 }
+                Ok(())
             }
-            Err(_) => 100,
-        },
-        None => 100,
-    };
-    let n_end: u32 = match args.get(2) {
-        Some(arg) => match arg.parse::<u32>() {
-            Ok(parsed) => {
-                if parsed < n_start {
-                    n_start
-                } else {
-                    parsed
+            Command::Solve(run) => run.run(),
+        }
+    }
+}
+
+impl Run {
+    /// Solves, writes out or counts the graph of every level in turn.
+    pub fn run(self) -> Result<(), &'static str> {
+        let Run {
+            graph,
+            levels: (n_start, n_end),
+            settings,
+            info,
+            holes,
+            writes,
+        } = self;
+        let certify = settings.certify;
+        for level in n_start..=n_end {
+            if info {
+                let shape = match graph {
+                    Graph::Hexprism => {
+                        let order = get_hexprism_order_from_n(level);
+                        println!("| {graph} | 🇳 {level:>4} | ⭕️ {order:>14} |");
+                        continue;
+                    }
+                    Graph::Discocube => Shape::Octahedron,
+                    Graph::Shape(shape) => shape,
+                };
+                println!(
+                    "| {graph} | 🇳 {level:>4} | ⭕️ {:>14} | ⚖️ {:>+10} | {} |",
+                    shape.order(level),
+                    shape.surplus(level),
+                    shape.parity(level)
+                );
+                continue;
+            }
+            if let Some(holes) = &holes {
+                find_holed(level, holes, settings)?;
+                continue;
+            }
+            match graph {
+                Graph::Discocube => {
+                    let (solution, timings) = find_solution(level, settings)?;
+                    if let Some(dir) = &writes.hcp {
+                        write_hcp(dir, level, &solution)?;
+                    }
+                    if let Some(dir) = &writes.export {
+                        write_graph(dir, level)?;
+                    }
+                    if let Some(dir) = &writes.dot {
+                        write_dot(dir, level, &solution, writes.projection)?;
+                    }
+                    if writes.npy.is_some() || writes.npz.is_some() {
+                        write_numpy(
+                            writes.npy.as_deref(),
+                            writes.npz.as_deref(),
+                            level,
+                            &solution,
+                        )?;
+                    }
+                    if let Some(dir) = &writes.json {
+                        write_json(dir, level, solution, timings)?;
+                    }
+                }
+                Graph::Hexprism => {
+                    find_hexprism(level, certify)?;
                 }
+                Graph::Shape(shape) => {
+                    find_shape(shape, level, settings)?;
+                }
+            };
+        }
+        Ok(())
+    }
+}
+
+pub fn main() -> Result<(), &'static str> {
+    Command::parse(env::args().collect())?.run()
+}
+
+/// The value given after `flag`, taking both out of the arguments. A flag given last or followed by
+/// another flag has no value, which is an error rather than a flag left out.
+fn take_flag(args: &mut Vec<String>, flag: &str) -> Result<Option<String>, &'static str> {
+    let Some(idx) = args.iter().position(|arg| arg == flag) else {
+        return Ok(None);
+    };
+    args.remove(idx);
+    match args.get(idx) {
+        Some(value) if !value.starts_with("--") => Ok(Some(args.remove(idx))),
+        _ => Err("a flag is given without its value"),
+    }
+}
+
+/// The levels N and N_UPPER left once every flag is taken, 100 alone when neither is given. N is
+/// at least 1 and N_UPPER at least N. Anything else left is an error, a mistyped flag most likely.
+fn take_levels(args: &mut Vec<String>) -> Result<(u32, u32), &'static str> {
+    let levels: Vec<u32> = args
+        .drain(1..)
+        .map(|arg| arg.parse())
+        .collect::<Result<_, _>>()
+        .or(Err(
+            "unknown argument, expected a flag of the usage or a level",
+        ))?;
+    match levels[..] {
+        [] => Ok((100, 100)),
+        [n] => Ok((n.max(1), n.max(1))),
+        [n, n_upper] => Ok((n.max(1), n_upper.max(n.max(1)))),
+        _ => Err("expected two levels at most, N and N_UPPER"),
+    }
+}
+
+/// Sides of a cuboid written as AxBxC, small enough for every coordinate to fit a Point and few
+/// enough vertices between them for the solver to hold.
+fn parse_dims(dims: &str) -> Result<[usize; 3], &'static str> {
+    let sides: Vec<usize> = dims
+        .split('x')
+        .map(|side| side.parse::<usize>())
+        .filter_map(Result::ok)
+        .filter(|&side| (1..=Point::MAX as usize / 2).contains(&side))
+        .collect();
+    match sides[..] {
+        [a, b, c] if dims.split('x').count() == 3 => match a as u64 * b as u64 * c as u64 {
+            order if order > MAX_CUBOID_ORDER => {
+                Err("--cuboid takes at most 2^30 vertices, as many as 1024x1024x1024")
             }
-            Err(_) => n_start,
+            _ => Ok([a, b, c]),
         },
-        None => n_start,
-    };
-    for level in n_start..=n_end {
-        find_solution(level, false)?;
+        _ => Err("--cuboid expects three sides between 1 and 16383, as in 4x5x6"),
     }
-    Ok(())
 }
 
-pub fn find_solution(level: u32, certify: bool) -> Result<Solution, &'static str> {
+/// The most vertices a cuboid may have: its nodes are numbered in a u32, and at about 500 bytes a
+/// vertex its graph and cycle already take half a terabyte.
+const MAX_CUBOID_ORDER: u64 = 1 << 30;
+
+/// Vertices written as x,y,z and split by spaces or semicolons, each on odd coordinates.
+fn parse_holes(holes: &str) -> Result<Vec<Vert>, &'static str> {
+    holes
+        .split(|char: char| char == ';' || char.is_whitespace())
+        .filter(|hole| !hole.is_empty())
+        .map(|hole| {
+            let coords: Vec<Point> = hole
+                .split(',')
+                .map(|coord| coord.parse::<Point>().ok().filter(|coord| coord % 2 != 0))
+                .collect::<Option<_>>()
+                .ok_or("--holes expects vertices on odd coordinates, as in 1,1,1;-1,1,3")?;
+            match coords[..] {
+                [x, y, z] => Ok((x, y, z)),
+                _ => Err("--holes expects vertices on odd coordinates, as in 1,1,1;-1,1,3"),
+            }
+        })
+        .collect()
+}
+
+fn take_switch(args: &mut Vec<String>, switch: &str) -> bool {
+    match args.iter().position(|arg| arg == switch) {
+        Some(idx) => {
+            args.remove(idx);
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn find_solution(level: u32, settings: Settings) -> Result<(Solution, Timings), &'static str> {
+    let Settings {
+        mode,
+        polish,
+        sample,
+        certify,
+        ..
+    } = settings;
     println!("👷 MAKE GRAPH ➤ 🔀 SOLVE GRAPH ➤ 🔎 CERTIFY SOLUTION");
 
     println!("🛠️ MAKING GRAPH....");
@@ -68,12 +715,68 @@ pub fn find_solution(level: u32, certify: bool) -> Result<Solution, &'static str
     let dur_make = Instant::now() - start;
     println!("MADE GRAPH: 🕗 {dur_make:?}. 🔀 SOLVING GRAPH ⭕️ {order}");
     start = Instant::now();
-    let solution = weave::weave(&adj, vi_map, &verts, z_adj, z_order, min_xyz);
+    let mut solution = match (mode, sample) {
+        (Mode::Weave, Some((seed, samples))) => {
+            let seeds = seed..seed.saturating_add(samples);
+            let cycles = sample::sample(&adj, &vi_map, &verts, z_adj, z_order, min_xyz, seeds)?;
+            let shapes = cycles
+                .iter()
+                .map(|cycle| canonical::fingerprint(cycle, &verts))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .unique()
+                .count();
+            println!(
+                "| 🇳 {n:>4} | 🎲 SEED: {seed} | SAMPLES: {samples} | DISTINCT: {} | UP TO SYMMETRY: {shapes} |",
+                cycles.len()
+            );
+            cycles
+                .iter()
+                .for_each(|cycle| println!("| 🇳 {n:>4} | 🧬 {:016x} |", cycle_hash(cycle)));
+            cycles.into_iter().next().unwrap()
+        }
+        (Mode::Weave, None) => weave::weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz),
+        (Mode::AlwaysTurning, _) => turn::turn(&adj, &vi_map, &verts, z_adj, z_order, min_xyz)?,
+        (Mode::Balanced { tolerance, darns }, _) => balance::balance(
+            &adj,
+            &vi_map,
+            &verts,
+            z_adj,
+            z_order,
+            min_xyz,
+            tolerance.unwrap_or_else(|| min_axis_spread(order)),
+            darns,
+        )?,
+    };
     let dur_solve = Instant::now() - start;
     println!(
         "| 🇳 {n:>4} | ⭕️ {order:>10} | 🕗 SOLVE: {} |",
         dur_solve.as_secs_f32()
     );
+    let mut timings = Timings {
+        make: dur_make.as_secs_f32(),
+        solve: dur_solve.as_secs_f32(),
+        certify: None,
+    };
+    solution = finish(
+        &format!("🇳 {n:>4}"),
+        solution,
+        &adj,
+        &vi_map,
+        &verts,
+        settings,
+    )?;
+    if let Mode::Balanced { .. } = mode {
+        let counts = axis_counts(&solution, &verts);
+        println!(
+            "| 🇳 {n:>4} | ⭕️ {order:>10} | 📐 X: {} Y: {} Z: {} | SPREAD: {} | BOUND: {} |",
+            counts[0],
+            counts[1],
+            counts[2],
+            axis_spread(&counts),
+            min_axis_spread(order)
+        );
+    }
 
     if certify {
         println!("🇳 {n:>4} FINISHED WEAVING. 🔎 CERTIFYING SOLUTION...");
@@ -86,7 +789,895 @@ pub fn find_solution(level: u32, certify: bool) -> Result<Solution, &'static str
         dur_solve.as_secs_f32(),
         dur_certify.as_secs_f32()
         );
+        timings.certify = Some(dur_certify.as_secs_f32());
+        assert_eq!(seq_id, SequenceID::HamCycle);
+        // a polish for another objective may trade away what the mode guarantees.
+        if mode == Mode::AlwaysTurning && polish.is_none() {
+            assert!(certify::is_turning(&solution, &adj, &verts));
+            println!("🇳 {n:>4} 🔎 ALWAYS TURNING");
+        }
+        if let (Mode::Balanced { tolerance, .. }, None) = (mode, polish) {
+            let tolerance = tolerance.unwrap_or_else(|| min_axis_spread(order));
+            assert!(certify::is_balanced(&solution, &adj, &verts, tolerance));
+            println!("🇳 {n:>4} 🔎 BALANCED WITHIN {tolerance}");
+        }
+    }
+    Ok((solution, timings))
+}
+
+/// Grows the discocube and its cycle a shell at a time from level 1 up to level n_upper, every
+/// level past the second from the cycle of the one two below it, printing the levels from n on.
+/// Fails should a level not grow or its cycle not certify.
+pub fn find_grown(n: u32, n_upper: u32, certify: bool) -> Result<(), &'static str> {
+    let mut growth = Growth::new();
+    let mut start: Instant = Instant::now();
+    let mut kept = None;
+    loop {
+        let level = growth.level();
+        let dur_grow = Instant::now() - start;
+        let order = growth.order();
+        if level >= n {
+            match kept {
+                None => println!(
+                    "| 🌱 {level:>4} | ⭕️ {order:>10} | 🕗 WEAVE: {} |",
+                    dur_grow.as_secs_f32()
+                ),
+                Some(kept) => println!(
+                    "| 🌱 {level:>4} | ⭕️ {order:>10} | 🕗 GROW: {} | 🧵 KEPT: {kept:>10} | 🔗 SHARED: {:>10} |",
+                    dur_grow.as_secs_f32(),
+                    growth.shared(growth.below())
+                ),
+            }
+        }
+        if certify && level >= n {
+            let (.., adj, _, _, _) = make_graph(level);
+            let seq_id = certify::id_seq(&growth.cycle(), &adj);
+            println!("| 🌱 {level:>4} | ⭕️ {order:>10} | 📌 {seq_id:?} |");
+            if seq_id != SequenceID::HamCycle {
+                return Err("the grown cycle is not a hamiltonian cycle");
+            }
+        }
+        if level >= n_upper {
+            return Ok(());
+        }
+        start = Instant::now();
+        kept = growth.grow().inspect_err(|obstruction| {
+            println!("| 🌱 {:>4} | 🚫 COULD NOT GROW: {obstruction} |", level + 1);
+        })?;
+    }
+}
+
+/// Plaits a hamiltonian cycle on the ball of D dimensions of the level given, or prints why there is
+/// none.
+pub fn find_plaited<const D: usize>(level: u32, certify: bool) {
+    let start: Instant = Instant::now();
+    let cycle = match lattice::plait::<D>(level) {
+        Ok(cycle) => cycle,
+        Err(reason) => {
+            println!("| 📐 {D}D | 🇳 {level:>4} | 🚫 NO CYCLE: {reason} |");
+            return;
+        }
+    };
+    let dur_plait = Instant::now() - start;
+    let order = cycle.len();
+    println!(
+        "| 📐 {D}D | 🇳 {level:>4} | ⭕️ {order:>10} | 🕗 PLAIT: {} |",
+        dur_plait.as_secs_f32()
+    );
+    if certify {
+        let (_, vi_map, adj) = lattice::make_ball::<D>(level);
+        let seq: Solution = cycle.iter().map(|point| vi_map[point]).collect();
+        let seq_id = certify::id_seq(&seq, &adj);
+        println!("| 📐 {D}D | 🇳 {level:>4} | ⭕️ {order:>10} | 📌 {seq_id:?} |");
         assert_eq!(seq_id, SequenceID::HamCycle);
     }
+}
+
+/// Writes the discocube of the level given and its cycle to the directory in TSPLIB's formats.
+pub fn write_hcp(dir: &str, level: u32, solution: &Solution) -> Result<(), &'static str> {
+    let (.., adj, _, _, _) = make_graph(level);
+    let name = format!("discocube_{level}");
+    let path = std::path::Path::new(dir).join(&name);
+    fs::write(path.with_extension("hcp"), tsplib::to_hcp(&name, &adj))
+        .or(Err("could not write the .hcp file"))?;
+    fs::write(
+        path.with_extension("tour"),
+        tsplib::to_tour(&format!("{name}.tour"), solution),
+    )
+    .or(Err("could not write the .tour file"))?;
+    println!("🇳 {level:>4} 📄 WROTE {}.hcp AND .tour", path.display());
+    Ok(())
+}
+
+/// Writes the graph of the discocube of the level given to the directory as a DIMACS .col file, an
+/// edge list and GraphML.
+pub fn write_graph(dir: &str, level: u32) -> Result<(), &'static str> {
+    let (_, _, verts, _, adj, _, _, _) = make_graph(level);
+    let name = format!("discocube_{level}");
+    let path = std::path::Path::new(dir).join(&name);
+    for (ext, text) in [
+        ("col", formats::to_col(&name, &adj)),
+        ("edges", formats::to_edges(&adj)),
+        ("graphml", formats::to_graphml(&name, &verts, &adj)),
+    ] {
+        fs::write(path.with_extension(ext), text).or(Err("could not write the graph files"))?;
+    }
+    println!(
+        "🇳 {level:>4} 📄 WROTE {}.col, .edges AND .graphml",
+        path.display()
+    );
+    Ok(())
+}
+
+/// Writes the discocube of the level given and its cycle to the directory in Graphviz's DOT language.
+pub fn write_dot(
+    dir: &str,
+    level: u32,
+    solution: &Solution,
+    projection: Projection,
+) -> Result<(), &'static str> {
+    let (_, _, verts, _, adj, _, _, _) = make_graph(level);
+    let name = format!("discocube_{level}");
+    let path = std::path::Path::new(dir).join(format!("{name}.dot"));
+    fs::write(
+        &path,
+        formats::to_dot(&name, &verts, &adj, solution, projection),
+    )
+    .or(Err("could not write the .dot file"))?;
+    println!("🇳 {level:>4} 📄 WROTE {} ({projection})", path.display());
+    Ok(())
+}
+
+/// Writes the vertices, cycle and edges of the discocube of the level given for NumPy, as three .npy
+/// files to one directory and as a .npz archive of them to the other.
+pub fn write_numpy(
+    npy_dir: Option<&str>,
+    npz_dir: Option<&str>,
+    level: u32,
+    solution: &Solution,
+) -> Result<(), &'static str> {
+    let (_, _, verts, _, adj, _, _, _) = make_graph(level);
+    let name = format!("discocube_{level}");
+    let arrays = [
+        ("verts", npy::to_npy(&npy::verts_array(&verts))),
+        ("tour", npy::to_npy(&npy::tour_array(solution))),
+        ("edges", npy::to_npy(&npy::edges_array(&adj))),
+    ];
+    if let Some(dir) = npy_dir {
+        for (array, bytes) in &arrays {
+            let path = std::path::Path::new(dir).join(format!("{name}_{array}.npy"));
+            fs::write(path, bytes).or(Err("could not write the .npy files"))?;
+        }
+        println!("🇳 {level:>4} 📄 WROTE {dir}/{name}_{{verts,tour,edges}}.npy");
+    }
+    if let Some(dir) = npz_dir {
+        let path = std::path::Path::new(dir).join(format!("{name}.npz"));
+        fs::write(&path, npy::to_npz(&arrays)?).or(Err("could not write the .npz file"))?;
+        println!("🇳 {level:>4} 📄 WROTE {}", path.display());
+    }
+    Ok(())
+}
+
+const NO_SERDE: &str = "--json and --read-json need hamcycle built with --features serde";
+
+/// Writes the report of the discocube of the level given, its cycle and timings to the directory as
+/// JSON.
+#[cfg(feature = "serde")]
+pub fn write_json(
+    dir: &str,
+    level: u32,
+    tour: Solution,
+    timings: Timings,
+) -> Result<(), &'static str> {
+    let (_, _, verts, _, adj, _, _, min_xyz) = make_graph(level);
+    let report = hamcycle::graph::report::Report::new(
+        level,
+        GridGraph { verts, adj },
+        tour,
+        min_xyz,
+        timings,
+    );
+    let path = std::path::Path::new(dir).join(format!("discocube_{level}.json"));
+    let text = serde_json::to_string(&report).or(Err("could not write the report as JSON"))?;
+    fs::write(&path, text).or(Err("could not write the .json file"))?;
+    println!("🇳 {level:>4} 📄 WROTE {}", path.display());
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+pub fn write_json(_: &str, _: u32, _: Solution, _: Timings) -> Result<(), &'static str> {
+    Err(NO_SERDE)
+}
+
+/// Reads a report written by --json and certifies its cycle against its graph again, printing what
+/// the report said alongside, and fails if it does not certify.
+#[cfg(feature = "serde")]
+pub fn find_json_tour(path: &str) -> Result<(), &'static str> {
+    let text = fs::read_to_string(path).or(Err("could not read the .json file"))?;
+    let report: hamcycle::graph::report::Report =
+        serde_json::from_str(&text).or(Err("the .json file holds no report"))?;
+    let seq_id = report.recertify();
+    let Timings {
+        make,
+        solve,
+        certify,
+    } = report.timings;
+    println!(
+        "| 📄 {path} | 🇳 {:>4} | ⭕️ {:>10} | 🕗 MAKE: {make} | 🕗 SOLVE: {solve} | 🕗 CERTIFY: {} | 📌 WAS {:?} | 📌 {seq_id:?} |",
+        report.level,
+        report.graph.adj.len(),
+        certify.map_or(String::from("-"), |certify| certify.to_string()),
+        report.certified,
+    );
+    match seq_id {
+        SequenceID::HamCycle => Ok(()),
+        _ => Err("the report's cycle is not a hamiltonian cycle of its graph"),
+    }
+}
+
+#[cfg(not(feature = "serde"))]
+pub fn find_json_tour(_: &str) -> Result<(), &'static str> {
+    Err(NO_SERDE)
+}
+
+/// Reads a graph from the .hcp file and a tour of it from the .tour file beside it, and certifies the
+/// tour.
+pub fn find_hcp_tour(path: &str) -> Result<(), &'static str> {
+    let adj = tsplib::from_hcp(&fs::read_to_string(path).or(Err("could not read the .hcp file"))?)?;
+    certify_tour(path, &adj)
+}
+
+/// Reads a graph from the .col, .graphml or edge list file and a tour of it from the .tour file beside
+/// it, and certifies the tour.
+pub fn find_graph_tour(path: &str) -> Result<(), &'static str> {
+    let text = fs::read_to_string(path).or(Err("could not read the graph file"))?;
+    let adj = match std::path::Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+    {
+        Some("col") => formats::from_col(&text)?,
+        Some("graphml") => formats::from_graphml(&text)?,
+        _ => formats::from_edges(&text)?,
+    };
+    certify_tour(path, &adj)
+}
+
+/// Certifies the tour in the TSPLIB .tour file beside the graph file against the graph read from it.
+fn certify_tour(path: &str, adj: &Adjacency) -> Result<(), &'static str> {
+    let tour_path = std::path::Path::new(path).with_extension("tour");
+    let tour = tsplib::from_tour(
+        &fs::read_to_string(tour_path).or(Err("could not read the .tour file beside the graph"))?,
+    )?;
+    if tour.len() != adj.len() {
+        return Err("the tour and the graph differ in their order");
+    }
+    if tour.iter().any(|node| !adj.contains_key(node)) {
+        return Err("the tour passes a vertex the graph does not have");
+    }
+    let seq_id = certify::id_seq(&tour, adj);
+    println!("| 📄 {path} | ⭕️ {:>10} | 📌 {seq_id:?} |", adj.len());
+    match seq_id {
+        SequenceID::HamCycle => Ok(()),
+        _ => Err("the tour is not a hamiltonian cycle of the graph"),
+    }
+}
+
+/// Solves the polyomino drawn in the file, or prints why it has no hamiltonian cycle.
+pub fn find_polyomino(path: &str, certify: bool) -> Result<(), &'static str> {
+    let mask = fs::read_to_string(path).or(Err("could not read the polyomino file"))?;
+    let polyomino = Polyomino::from_mask(&mask)?;
+    let order = polyomino.len();
+    let start: Instant = Instant::now();
+    let solved = polyomino.solve();
+    let dur_solve = Instant::now() - start;
+    match solved {
+        Ok(cycle) => {
+            println!(
+                "| ▦ {path} | ⭕️ {order:>10} | 🕗 SOLVE: {} |",
+                dur_solve.as_secs_f32()
+            );
+            if certify {
+                assert!(polyomino.is_cycle(&cycle));
+                println!("| ▦ {path} | ⭕️ {order:>10} | 📌 HamCycle |");
+            }
+        }
+        Err(reason) if !reason.is_proof() => {
+            println!("| ▦ {path} | ⭕️ {order:>10} | ❔ UNDECIDED: {reason} |");
+            return Err("could not decide whether the polyomino has a hamiltonian cycle");
+        }
+        Err(reason) => {
+            println!("| ▦ {path} | ⭕️ {order:>10} | 🚫 NO HAMILTONIAN CYCLE: {reason} |");
+            if certify {
+                match polyomino.verify(&reason) {
+                    true => println!("| ▦ {path} | 📌 PROOF CERTIFIED |"),
+                    false => return Err("the proof that the polyomino has no cycle is wrong"),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn find_hexprism(level: u32, certify: bool) -> Result<Solution, &'static str> {
+    println!("🛠️ MAKING GRAPH....");
+    let mut start: Instant = Instant::now();
+    let (n, order, verts, vi_map, adj) = make_hexprism(level);
+    let dur_make = Instant::now() - start;
+    println!("MADE GRAPH: 🕗 {dur_make:?}. 🔀 SOLVING GRAPH ⭕️ {order}");
+    start = Instant::now();
+    let solution = hexprism::hexprism(n, &adj, &verts, &vi_map)?;
+    let dur_solve = Instant::now() - start;
+    println!(
+        "| ⬡ {n:>4} | ⭕️ {order:>10} | 🕗 SOLVE: {} |",
+        dur_solve.as_secs_f32()
+    );
+    if certify {
+        start = Instant::now();
+        let seq_id = certify::id_seq(&solution, &adj);
+        println!(
+            "| ⬡ {n:>4} | ⭕️ {order:>10} | 📌 {seq_id:?} | 🕗 CERTIFY: {}",
+            (Instant::now() - start).as_secs_f32()
+        );
+        assert_eq!(seq_id, SequenceID::HamCycle);
+    }
+    Ok(solution)
+}
+
+/// Makes the a × b × c grid graph and solves it, or prints why it has no hamiltonian cycle.
+pub fn find_cuboid(dims: [usize; 3], settings: Settings) -> Result<Option<Solution>, &'static str> {
+    let label = format!("🧊 {}x{}x{}", dims[0], dims[1], dims[2]);
+    find_grid(
+        &label,
+        || make_cuboid(dims),
+        |adj, verts, vi_map| cuboid::cuboid(dims, adj, verts, vi_map),
+        settings,
+    )
+}
+
+/// Solves the shape of the catalogue at the level given, as a polycube.
+pub fn find_shape(
+    shape: Shape,
+    level: u32,
+    settings: Settings,
+) -> Result<Option<Solution>, &'static str> {
+    find_grid(
+        &format!("🔷 {shape} {level}"),
+        || make_shape(shape, level),
+        polycube::polycube,
+        settings,
+    )
+}
+
+/// Solves the discocube of the level given with the holes taken out, by mending its woven cycle.
+pub fn find_holed(
+    level: u32,
+    holes: &[Vert],
+    settings: Settings,
+) -> Result<Option<Solution>, &'static str> {
+    if holes
+        .iter()
+        .any(|&hole| !Shape::Octahedron.contains(level, hole))
+    {
+        return Err("every hole must be a vertex of the discocube");
+    }
+    if holes.iter().unique().count() as u64 == Shape::Octahedron.order(level) {
+        return Err("the holes leave nothing of the discocube");
+    }
+    find_grid(
+        &format!("🕳️ discocube {level} less {}", holes.len()),
+        || make_holed(level, holes),
+        |adj, verts, vi_map| mend::mend(level, adj, verts, vi_map),
+        settings,
+    )
+}
+
+/// Solves the polycube read from the file, a `.vox` or a text file of coordinates or layer masks.
+pub fn find_polycube(path: &str, settings: Settings) -> Result<Option<Solution>, &'static str> {
+    let voxels = polycube::read(path)?;
+    find_grid(
+        &format!("🧱 {path}"),
+        || make_polycube(&voxels),
+        polycube::polycube,
+        settings,
+    )
+}
+
+/// Makes a grid graph and solves it, or prints why it has no hamiltonian cycle, which is checked
+/// against the graph when certifying. An obstruction that is no proof is printed as undecided and
+/// fails.
+fn find_grid(
+    label: &str,
+    make: impl FnOnce() -> (u32, VecVert, VIMap, Adjacency),
+    solve: impl FnOnce(&Adjacency, &Verts, &VIMap) -> Result<Solution, Obstruction>,
+    settings: Settings,
+) -> Result<Option<Solution>, &'static str> {
+    println!("🛠️ MAKING GRAPH....");
+    let mut start: Instant = Instant::now();
+    let (order, verts, vi_map, adj) = make();
+    let dur_make = Instant::now() - start;
+    println!("MADE GRAPH: 🕗 {dur_make:?}. 🔀 SOLVING GRAPH ⭕️ {order}");
+    start = Instant::now();
+    let solved = solve(&adj, &verts, &vi_map);
+    let dur_solve = Instant::now() - start;
+    let solution = match solved {
+        Ok(solution) => solution,
+        Err(obstruction) if !obstruction.is_proof() => {
+            println!("| {label} | ⭕️ {order:>10} | ❔ UNDECIDED: {obstruction} |");
+            return Err("could not decide whether the graph has a hamiltonian cycle");
+        }
+        Err(obstruction) => {
+            println!("| {label} | ⭕️ {order:>10} | 🚫 NO HAMILTONIAN CYCLE: {obstruction} |");
+            if settings.certify {
+                match obstruction.certify(&adj, &verts) {
+                    true => println!("| {label} | 📌 PROOF CERTIFIED |"),
+                    false => {
+                        return Err("the proof that the graph has no hamiltonian cycle is wrong")
+                    }
+                }
+            }
+            return Ok(None);
+        }
+    };
+    println!(
+        "| {label} | ⭕️ {order:>10} | 🕗 SOLVE: {} |",
+        dur_solve.as_secs_f32()
+    );
+    let solution = finish(label, solution, &adj, &vi_map, &verts, settings)?;
+    if settings.certify {
+        let seq_id = certify::id_seq(&solution, &adj);
+        println!("| {label} | ⭕️ {order:>10} | 📌 {seq_id:?} |");
+        assert_eq!(seq_id, SequenceID::HamCycle);
+    }
+    Ok(Some(solution))
+}
+
+/// Polishes the solution and prints its fingerprint, as the settings ask.
+fn finish(
+    label: &str,
+    mut solution: Solution,
+    adj: &Adjacency,
+    vi_map: &VIMap,
+    verts: &Verts,
+    settings: Settings,
+) -> Result<Solution, &'static str> {
+    if let Some(finish) = settings.polish {
+        let before = finish_stats(&solution, verts);
+        let start = Instant::now();
+        solution = polish::polish(solution, adj, vi_map, verts, finish)?;
+        let after = finish_stats(&solution, verts);
+        println!(
+            "| {label} | 🧶 POLISH {finish} | STRAIGHT: {} ➤ {} | RUNS: {} ➤ {} | MAX RUN: {} ➤ {} | SPREAD: {} ➤ {} | 🕗 {} |",
+            before[0],
+            after[0],
+            before[1],
+            after[1],
+            before[2],
+            after[2],
+            before[3],
+            after[3],
+            (Instant::now() - start).as_secs_f32()
+        );
+    }
+    if settings.fingerprint {
+        println!(
+            "| {label} | 🫆 {} |",
+            to_hex(&canonical::fingerprint(&solution, verts)?)
+        );
+    }
     Ok(solution)
 }
+
+fn to_hex(fingerprint: &Fingerprint) -> String {
+    fingerprint
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Straight nodes, straight runs of two or more edges, longest run and axis spread of a solution.
+fn finish_stats(solution: &Solution, verts: &Verts) -> [usize; 4] {
+    let runs = run_lengths(solution, verts);
+    [
+        runs.iter().map(|len| len - 1).sum(),
+        runs.iter().filter(|&&len| len > 1).count(),
+        runs.into_iter().max().unwrap_or(0),
+        axis_spread(&axis_counts(solution, verts)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn parse(line: &str) -> Result<Command, &'static str> {
+        Command::parse(args(line))
+    }
+
+    fn levels(line: &str) -> Result<(u32, u32), &'static str> {
+        match parse(line)? {
+            Command::Solve(run) => Ok(run.levels),
+            _ => panic!("expected a run of the discocubes"),
+        }
+    }
+
+    #[test]
+    fn takes_a_flag_and_its_value_wherever_they_stand() {
+        let mut line = args("hamcycle 3 --mode balanced 5");
+        assert_eq!(take_flag(&mut line, "--mode"), Ok(Some("balanced".into())));
+        assert_eq!(line, args("hamcycle 3 5"));
+        assert_eq!(take_flag(&mut line, "--mode"), Ok(None));
+    }
+
+    #[test]
+    fn takes_values_that_start_with_a_minus() {
+        let mut line = args("hamcycle --holes -1,1,3");
+        assert_eq!(take_flag(&mut line, "--holes"), Ok(Some("-1,1,3".into())));
+    }
+
+    #[test]
+    fn errs_on_a_flag_without_its_value() {
+        assert!(take_flag(&mut args("hamcycle 2 --mode"), "--mode").is_err());
+        assert!(take_flag(&mut args("hamcycle --hcp --certify"), "--hcp").is_err());
+    }
+
+    #[test]
+    fn takes_a_switch_once() {
+        let mut line = args("hamcycle --certify 4");
+        assert!(take_switch(&mut line, "--certify"));
+        assert!(!take_switch(&mut line, "--certify"));
+        assert_eq!(line, args("hamcycle 4"));
+    }
+
+    #[test]
+    fn reads_the_mode_and_whether_to_certify() {
+        let mut line = args("hamcycle --mode always-turning 4 --certify");
+        let settings = Settings::parse(&mut line).unwrap();
+        assert_eq!(settings.mode, Mode::AlwaysTurning);
+        assert!(settings.certify);
+        assert_eq!(line, args("hamcycle 4"));
+        assert_eq!(
+            Settings::parse(&mut args("hamcycle")).map(|settings| settings.mode),
+            Ok(Mode::Weave)
+        );
+        assert_eq!(
+            Settings::parse(&mut args("hamcycle --mode turning")),
+            Err("unknown mode, expected one of: weave, always-turning, balanced")
+        );
+    }
+
+    #[test]
+    fn reads_the_tolerance_and_darns_of_the_balanced_mode() {
+        let line = "hamcycle --mode balanced --tolerance 2 --darns 50";
+        assert_eq!(
+            Settings::parse(&mut args(line)).map(|settings| settings.mode),
+            Ok(Mode::Balanced {
+                tolerance: Some(2),
+                darns: Some(50)
+            })
+        );
+        assert_eq!(
+            Settings::parse(&mut args("hamcycle --tolerance 2")),
+            Err("--tolerance only applies to --mode balanced")
+        );
+        assert_eq!(
+            Settings::parse(&mut args("hamcycle --mode balanced --darns 0")),
+            Err("--darns expects a positive integer")
+        );
+    }
+
+    #[test]
+    fn reads_the_polish() {
+        assert_eq!(
+            Settings::parse(&mut args("hamcycle --polish max-run")).map(|s| s.polish),
+            Ok(Some(Finish::MaxRun))
+        );
+        assert!(Settings::parse(&mut args("hamcycle --polish shine")).is_err());
+    }
+
+    #[test]
+    fn reads_the_seed_and_samples_of_the_plain_weave() {
+        let sample = |line| Settings::parse(&mut args(line)).map(|s| s.sample);
+        assert_eq!(sample("hamcycle"), Ok(None));
+        assert_eq!(sample("hamcycle --seed 7"), Ok(Some((7, 1))));
+        assert_eq!(sample("hamcycle --samples 3"), Ok(Some((0, 3))));
+        assert_eq!(
+            sample("hamcycle --samples 0"),
+            Err("--samples expects a positive integer")
+        );
+        assert_eq!(
+            sample("hamcycle --seed 1 --mode always-turning"),
+            Err("--seed and --samples only apply to --mode weave")
+        );
+    }
+
+    #[test]
+    fn reads_whether_to_fingerprint() {
+        let mut line = args("hamcycle --fingerprint 3");
+        assert_eq!(Settings::parse(&mut line).map(|s| s.fingerprint), Ok(true));
+        assert_eq!(line, args("hamcycle 3"));
+    }
+
+    #[test]
+    fn reads_where_to_write_the_discocubes() {
+        let mut line = args("hamcycle 3 --hcp out");
+        let writes = Writes::parse(&mut line).unwrap();
+        assert_eq!(writes.hcp.as_deref(), Some("out"));
+        assert_eq!(writes.export, None);
+        assert_eq!(line, args("hamcycle 3"));
+        let writes = Writes::parse(&mut args("hamcycle --export graphs")).unwrap();
+        assert_eq!(writes.export.as_deref(), Some("graphs"));
+        assert!(Writes::parse(&mut args("hamcycle --hcp --certify")).is_err());
+    }
+
+    #[test]
+    fn reads_the_projection_of_the_drawings() {
+        let writes = Writes::parse(&mut args("hamcycle --dot out --projection layers")).unwrap();
+        assert_eq!(writes.dot.as_deref(), Some("out"));
+        assert_eq!(writes.projection, Projection::Layers);
+        let writes = Writes::parse(&mut args("hamcycle --dot out")).unwrap();
+        assert_eq!(writes.projection, Projection::Isometric);
+        assert_eq!(
+            Writes::parse(&mut args("hamcycle --projection layers")),
+            Err("--projection only lays out --dot")
+        );
+    }
+
+    #[test]
+    fn reads_where_to_write_the_reports_when_built_with_serde() {
+        let writes = Writes::parse(&mut args("hamcycle --json reports"));
+        match cfg!(feature = "serde") {
+            true => assert_eq!(writes.unwrap().json.as_deref(), Some("reports")),
+            false => assert_eq!(writes, Err(NO_SERDE)),
+        }
+    }
+
+    #[test]
+    fn reads_where_to_write_the_arrays() {
+        let writes = Writes::parse(&mut args("hamcycle --npz zipped --npy arrays")).unwrap();
+        assert_eq!(writes.npy.as_deref(), Some("arrays"));
+        assert_eq!(writes.npz.as_deref(), Some("zipped"));
+    }
+
+    #[test]
+    fn solves_the_discocube_of_level_100_when_given_nothing() {
+        let Ok(Command::Solve(run)) = parse("hamcycle") else {
+            panic!("expected a run of the discocubes");
+        };
+        assert_eq!(run.graph, Graph::Discocube);
+        assert_eq!(run.levels, (100, 100));
+        assert_eq!(
+            run.settings,
+            Settings::parse(&mut args("hamcycle")).unwrap()
+        );
+        assert!(!run.info && run.holes.is_none() && !run.writes.any());
+    }
+
+    #[test]
+    fn clamps_the_levels_to_at_least_one_and_at_least_the_first() {
+        assert_eq!(levels("hamcycle 0"), Ok((1, 1)));
+        assert_eq!(levels("hamcycle 7 3"), Ok((7, 7)));
+        assert_eq!(levels("hamcycle 2 --certify 9"), Ok((2, 9)));
+    }
+
+    #[test]
+    fn refuses_arguments_that_no_flag_takes() {
+        assert_eq!(
+            levels("hamcycle --certfy 2"),
+            Err("unknown argument, expected a flag of the usage or a level")
+        );
+        assert_eq!(
+            levels("hamcycle two"),
+            Err("unknown argument, expected a flag of the usage or a level")
+        );
+        assert_eq!(
+            levels("hamcycle 1 2 3"),
+            Err("expected two levels at most, N and N_UPPER")
+        );
+        assert_eq!(
+            levels("hamcycle 2 --mode"),
+            Err("a flag is given without its value")
+        );
+    }
+
+    #[test]
+    fn picks_out_the_commands_that_do_one_thing() {
+        assert_eq!(
+            parse("hamcycle serve --workers 2").map(|command| matches!(command, Command::Serve(_))),
+            Ok(true)
+        );
+        assert_eq!(
+            parse("hamcycle 2 8 --grow"),
+            Ok(Command::Grow {
+                levels: (2, 8),
+                certify: false
+            })
+        );
+        assert_eq!(
+            parse("hamcycle 1 4 --dimension 4 --certify"),
+            Ok(Command::Plait {
+                dimension: 4,
+                levels: (1, 4),
+                certify: true
+            })
+        );
+        assert_eq!(
+            parse("hamcycle 1 7 --dot out"),
+            Err("--dot only draws the discocubes up to level 6")
+        );
+    }
+
+    #[test]
+    fn solves_a_cuboid_alone() {
+        assert_eq!(
+            parse("hamcycle --cuboid 2x3x4 --fingerprint"),
+            Ok(Command::Cuboid {
+                dims: [2, 3, 4],
+                settings: Settings::parse(&mut args("hamcycle --fingerprint")).unwrap()
+            })
+        );
+        for line in [
+            "hamcycle --cuboid 2x3x4 5",
+            "hamcycle --cuboid 2x3x4 --info",
+            "hamcycle --cuboid 2x3x4 --grow",
+            "hamcycle --cuboid 2x3x4 --hcp out",
+        ] {
+            assert_eq!(
+                parse(line),
+                Err("--cuboid and --polycube only combine with --polish, --fingerprint and --certify")
+            );
+        }
+    }
+
+    #[test]
+    fn refuses_a_cuboid_too_large_to_hold() {
+        assert_eq!(parse_dims("1024x1024x1024"), Ok([1024, 1024, 1024]));
+        assert_eq!(parse_dims("16383x2x2"), Ok([16383, 2, 2]));
+        for dims in ["1024x1024x1025", "16383x16383x16383", "16383x16383x5"] {
+            assert_eq!(
+                parse_dims(dims),
+                Err("--cuboid takes at most 2^30 vertices, as many as 1024x1024x1024")
+            );
+        }
+        assert_eq!(
+            parse_dims("16384x2x2"),
+            Err("--cuboid expects three sides between 1 and 16383, as in 4x5x6")
+        );
+    }
+
+    #[test]
+    fn solves_a_polyomino_alone() {
+        assert_eq!(
+            parse("hamcycle --polyomino p.txt --certify"),
+            Ok(Command::Polyomino {
+                path: "p.txt".into(),
+                certify: true
+            })
+        );
+        for line in [
+            "hamcycle --polyomino p.txt 3",
+            "hamcycle --polyomino p.txt --info",
+            "hamcycle --polyomino p.txt --graph cube",
+        ] {
+            assert_eq!(parse(line), Err("--polyomino only combines with --certify"));
+        }
+    }
+
+    #[test]
+    fn solves_a_polycube_alone() {
+        assert_eq!(
+            parse("hamcycle --polycube shape.vox --certify"),
+            Ok(Command::Polycube {
+                path: "shape.vox".into(),
+                settings: Settings::parse(&mut args("hamcycle --certify")).unwrap()
+            })
+        );
+        for line in [
+            "hamcycle --polycube shape.vox 5",
+            "hamcycle --polycube shape.vox --dimension 4",
+            "hamcycle --polycube shape.vox --npy out",
+        ] {
+            assert_eq!(
+                parse(line),
+                Err("--cuboid and --polycube only combine with --polish, --fingerprint and --certify")
+            );
+        }
+    }
+
+    #[test]
+    fn reads_a_hcp_alone() {
+        assert_eq!(
+            parse("hamcycle --read-hcp g.hcp"),
+            Ok(Command::ReadHcp("g.hcp".into()))
+        );
+        for line in [
+            "hamcycle --read-hcp g.hcp 4",
+            "hamcycle --read-hcp g.hcp --info",
+            "hamcycle --read-hcp g.hcp --graph hexprism",
+        ] {
+            assert_eq!(parse(line), Err("--read-hcp takes no other options"));
+        }
+    }
+
+    #[test]
+    fn reads_a_graph_alone() {
+        assert_eq!(
+            parse("hamcycle --read-graph g.col"),
+            Ok(Command::ReadGraph("g.col".into()))
+        );
+        for line in [
+            "hamcycle --read-graph g.col 4",
+            "hamcycle --read-graph g.col --info",
+            "hamcycle --read-graph g.col --graph hexprism",
+        ] {
+            assert_eq!(parse(line), Err("--read-graph takes no other options"));
+        }
+    }
+
+    #[test]
+    fn reads_a_json_alone() {
+        assert_eq!(
+            parse("hamcycle --read-json r.json"),
+            Ok(Command::ReadJson("r.json".into()))
+        );
+        for line in [
+            "hamcycle --read-json r.json 4",
+            "hamcycle --read-json r.json --info",
+            "hamcycle --read-json r.json --graph hexprism",
+        ] {
+            assert_eq!(parse(line), Err("--read-json takes no other options"));
+        }
+    }
+
+    #[test]
+    fn fails_on_a_tour_beside_the_graph_that_is_not_a_cycle() {
+        let dir = env::temp_dir().join(format!("hamcycle-read-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(2);
+        let mut tour = weave::weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz);
+        let hcp = dir.join("discocube_2.hcp");
+        let edges = dir.join("discocube_2.edges");
+        fs::write(&hcp, tsplib::to_hcp("discocube_2", &adj)).unwrap();
+        fs::write(&edges, formats::to_edges(&adj)).unwrap();
+        let write_tour = |tour: &Solution| {
+            fs::write(
+                dir.join("discocube_2.tour"),
+                tsplib::to_tour("discocube_2", tour),
+            )
+            .unwrap()
+        };
+        write_tour(&tour);
+        assert_eq!(find_hcp_tour(hcp.to_str().unwrap()), Ok(()));
+        assert_eq!(find_graph_tour(edges.to_str().unwrap()), Ok(()));
+        tour.swap(0, 2);
+        write_tour(&tour);
+        let broken = Err("the tour is not a hamiltonian cycle of the graph");
+        assert_eq!(find_hcp_tour(hcp.to_str().unwrap()), broken);
+        assert_eq!(find_graph_tour(edges.to_str().unwrap()), broken);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn names_the_centered_octahedron_among_the_graphs_it_takes() {
+        assert_eq!(
+            "centered-octahedron".parse::<Graph>(),
+            Ok(Graph::Shape(Shape::CenteredOctahedron))
+        );
+        let unknown = "tetrahedron".parse::<Graph>().unwrap_err();
+        assert!(unknown.ends_with("rhombic-dodecahedron, centered-octahedron"));
+    }
+
+    #[test]
+    fn rules_out_a_cycle_on_every_centered_octahedron_with_a_certified_proof() {
+        let settings = Settings::parse(&mut args("hamcycle --certify")).unwrap();
+        for level in 1..=4 {
+            assert_eq!(
+                find_shape(Shape::CenteredOctahedron, level, settings),
+                Ok(None)
+            );
+        }
+    }
+}
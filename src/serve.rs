@@ -0,0 +1,844 @@
+//! `hamcycle serve`: solves, certifies and exports the discocubes for other tools over HTTP on the
+//! local machine, so that they need not each link the crate.
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, TrySendError},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use hamcycle::graph::{
+    defs::*,
+    formats::{self, Projection},
+    npy, tsplib,
+    utils::{
+        certify::{self, SequenceID},
+        info::get_n_from_order,
+        make::make_graph,
+    },
+    weave,
+};
+
+use super::take_flag;
+
+/// Bytes a vertex takes while its level is made, woven or certified and its tour held, which the
+/// memory budget counts by.
+const BYTES_PER_VERTEX: u64 = 512;
+/// The longest request body read, enough for a tour of level 150 written out in full.
+const MAX_BODY: usize = 128 << 20;
+/// The longest request line and headers read, together.
+const MAX_HEAD: u64 = 64 << 10;
+/// How long a connection may keep the server waiting on a read or a write.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An HTTP status and why, for a request that cannot be answered as asked.
+type Refusal = (u16, &'static str);
+
+const TOO_LARGE: Refusal = (413, "the request needs more memory than the server allows");
+
+/// How the server runs, taken from the arguments after `serve`: --port P (7474), --cache DIR (cache),
+/// --workers K (the cores) and --memory MB (2048).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub port: u16,
+    pub cache: PathBuf,
+    pub workers: usize,
+    /// Bytes the levels being worked on and the request bodies share.
+    pub memory: u64,
+}
+
+impl Options {
+    pub fn parse(mut args: Vec<String>) -> Result<Self, &'static str> {
+        let port: u16 = match take_flag(&mut args, "--port")? {
+            Some(port) => port.parse().or(Err("--port expects a port number"))?,
+            None => 7474,
+        };
+        let cache =
+            PathBuf::from(take_flag(&mut args, "--cache")?.unwrap_or_else(|| "cache".into()));
+        let workers: usize = match take_flag(&mut args, "--workers")? {
+            Some(workers) => match workers.parse() {
+                Ok(workers) if workers > 0 => workers,
+                _ => return Err("--workers expects a positive integer"),
+            },
+            None => thread::available_parallelism().map_or(4, usize::from),
+        };
+        let memory: u64 = match take_flag(&mut args, "--memory")? {
+            Some(memory) => match memory.parse::<u64>() {
+                Ok(memory) if memory > 0 => memory << 20,
+                _ => return Err("--memory expects a positive number of megabytes"),
+            },
+            None => 2048 << 20,
+        };
+        if args.len() > 1 {
+            return Err("serve only takes --port, --cache, --workers and --memory");
+        }
+        Ok(Options {
+            port,
+            cache,
+            workers,
+            memory,
+        })
+    }
+}
+
+/// Runs the server until it is killed.
+pub fn serve(options: Options) -> Result<(), &'static str> {
+    let Options {
+        port,
+        cache,
+        workers,
+        memory,
+    } = options;
+    fs::create_dir_all(&cache).or(Err("could not make the cache directory"))?;
+    let listener =
+        TcpListener::bind(("127.0.0.1", port)).or(Err("could not listen on the port"))?;
+    println!(
+        "🌐 SERVING ON http://127.0.0.1:{port} | 👷 {workers} WORKERS | 💾 {} MB | 📁 {} |",
+        memory >> 20,
+        cache.display()
+    );
+    listen(
+        listener,
+        Arc::new(Service::new(cache, memory, TIMEOUT)),
+        workers,
+    );
+    Ok(())
+}
+
+/// Hands the connections to the workers, for as long as the listener takes them.
+fn listen(listener: TcpListener, service: Arc<Service>, workers: usize) {
+    // a connection waits in the queue for a worker, and is turned away when the queue is full.
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(workers * 4);
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..workers {
+        let (service, receiver) = (service.clone(), receiver.clone());
+        thread::spawn(move || loop {
+            let stream = match receiver.lock().unwrap().recv() {
+                Ok(stream) => stream,
+                Err(_) => return,
+            };
+            service.answer(stream);
+        });
+    }
+    for stream in listener.incoming().filter_map(Result::ok) {
+        if let Err(TrySendError::Full(mut stream)) = sender.try_send(stream) {
+            let _ = refuse(
+                &mut stream,
+                (503, "every worker is busy, try again shortly"),
+            );
+        }
+    }
+}
+
+struct Service {
+    cache: PathBuf,
+    budget: Budget,
+    timeout: Duration,
+    /// The levels being woven, which a request missing the cache for one of them waits on.
+    weaving: Mutex<HashSet<u32>>,
+    woven: Condvar,
+    /// How many cycles have been woven, the cache missed.
+    weaves: AtomicU64,
+}
+
+/// The memory the levels being worked on may take between them, in bytes.
+struct Budget {
+    limit: u64,
+    used: Mutex<u64>,
+    freed: Condvar,
+}
+
+/// Bytes taken from the budget, given back when dropped.
+struct Lease<'a> {
+    budget: &'a Budget,
+    bytes: u64,
+}
+
+impl Budget {
+    /// Waits until the bytes fit beside those already taken, or refuses them when they never could.
+    fn take(&self, bytes: u64) -> Result<Lease<'_>, Refusal> {
+        if bytes > self.limit {
+            return Err(TOO_LARGE);
+        }
+        let mut used = self.used.lock().unwrap();
+        while *used + bytes > self.limit {
+            used = self.freed.wait(used).unwrap();
+        }
+        *used += bytes;
+        Ok(Lease {
+            budget: self,
+            bytes,
+        })
+    }
+}
+
+impl Drop for Lease<'_> {
+    fn drop(&mut self) {
+        *self.budget.used.lock().unwrap() -= self.bytes;
+        self.budget.freed.notify_all();
+    }
+}
+
+/// A level claimed for weaving, let go when dropped.
+struct Claim<'a> {
+    service: &'a Service,
+    level: u32,
+}
+
+impl Drop for Claim<'_> {
+    fn drop(&mut self) {
+        self.service.weaving.lock().unwrap().remove(&self.level);
+        self.service.woven.notify_all();
+    }
+}
+
+/// A request, its body held on a lease of the memory it takes. A handler drops the request before
+/// it takes a lease of its own, so that it never waits while holding memory another needs.
+struct Request<'a> {
+    method: String,
+    /// The path and query as asked for, for the log.
+    target: String,
+    path: String,
+    query: HashMap<String, String>,
+    body: Vec<u8>,
+    _lease: Lease<'a>,
+}
+
+/// The cycle of a level and the discocube it was certified on, on a lease of their memory.
+struct Woven<'a> {
+    tour: Solution,
+    verts: VecVert,
+    adj: Adjacency,
+    _lease: Lease<'a>,
+}
+
+impl Service {
+    fn new(cache: PathBuf, memory: u64, timeout: Duration) -> Service {
+        Service {
+            cache,
+            budget: Budget {
+                limit: memory,
+                used: Mutex::new(0),
+                freed: Condvar::new(),
+            },
+            timeout,
+            weaving: Mutex::new(HashSet::new()),
+            woven: Condvar::new(),
+            weaves: AtomicU64::new(0),
+        }
+    }
+
+    /// Reads a request off the connection and answers it, a panic while solving answering 500. A
+    /// client that stalls longer than the timeout is answered 408, or dropped while answered.
+    fn answer(&self, mut stream: TcpStream) {
+        let start = Instant::now();
+        let timeout = Some(self.timeout);
+        if stream.set_read_timeout(timeout).is_err() || stream.set_write_timeout(timeout).is_err() {
+            return;
+        }
+        let request = match read_request(&stream, &self.budget) {
+            Ok(request) => request,
+            Err(refusal) => {
+                let _ = refuse(&mut stream, refusal);
+                // what is left unread would reset the connection before the client reads the
+                // refusal, so it is read and thrown away once the client has all of it.
+                if stream.shutdown(Shutdown::Write).is_ok() {
+                    let _ = io::copy(&mut (&stream).take(MAX_HEAD), &mut io::sink());
+                }
+                return;
+            }
+        };
+        let (method, target) = (request.method.clone(), request.target.clone());
+        let status = catch_unwind(AssertUnwindSafe(|| self.route(request, &mut stream)))
+            .unwrap_or(Err((500, "the solver failed")))
+            .or_else(|refusal| refuse(&mut stream, refusal).map(|_| refusal.0))
+            .unwrap_or(0);
+        println!(
+            "| 🌐 {method} {target} | {status} | 🕗 {} |",
+            start.elapsed().as_secs_f32()
+        );
+    }
+
+    fn route(&self, request: Request, stream: &mut TcpStream) -> Result<u16, Refusal> {
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/solve") => self.solve(request, stream),
+            ("POST", "/certify") => self.certify(request, stream),
+            ("GET", "/export") => self.export(request, stream),
+            (_, "/solve" | "/export") => Err((405, "use GET")),
+            (_, "/certify") => Err((405, "use POST with the tour as the body")),
+            _ => Err((404, "the endpoints are /solve, /certify and /export")),
+        }
+    }
+
+    /// The cycle of the level as JSON, streamed as it is written out.
+    fn solve(&self, request: Request, stream: &mut TcpStream) -> Result<u16, Refusal> {
+        let level = level(&request)?;
+        drop(request);
+        let Woven { tour, .. } = self.tour(level)?;
+        respond(stream, 200, "application/json", |out| {
+            write!(
+                out,
+                "{{\"level\":{level},\"order\":{},\"tour\":[",
+                tour.len()
+            )?;
+            for (idx, node) in tour.iter().enumerate() {
+                match idx {
+                    0 => write!(out, "{node}")?,
+                    _ => write!(out, ",{node}")?,
+                }
+            }
+            write!(out, "]}}")
+        })
+    }
+
+    /// Certifies the tour in the body against the discocube of the level given or of the order the
+    /// tour has. The tour's nodes are split by anything that is not a digit, and only those between
+    /// the brackets are read when there are any, so that an answer of /solve can be sent back as it is.
+    fn certify(&self, request: Request, stream: &mut TcpStream) -> Result<u16, Refusal> {
+        let body = String::from_utf8_lossy(&request.body);
+        let nodes = match (body.find('['), body.rfind(']')) {
+            (Some(open), Some(close)) if open < close => &body[open + 1..close],
+            _ => &body[..],
+        };
+        let tour: Solution = nodes
+            .split(|char: char| !char.is_ascii_digit())
+            .filter(|node| !node.is_empty())
+            .map(|node| {
+                node.parse()
+                    .or(Err((400, "a node of the tour is too large")))
+            })
+            .collect::<Result<_, _>>()?;
+        let level = match request.query.contains_key("level") {
+            true => level(&request)?,
+            false => get_n_from_order(tour.len() as u32).max(1),
+        };
+        drop(request);
+        if order(level)? != tour.len() as u64 {
+            return Err((422, "the tour does not have as many nodes as the discocube"));
+        }
+        let _lease = self.budget.take(footprint(level)?)?;
+        let (.., adj, _, _, _) = make_graph(level);
+        let seq_id = certify::id_seq(&tour, &adj);
+        respond(stream, 200, "application/json", |out| {
+            write!(
+                out,
+                "{{\"level\":{level},\"order\":{},\"certified\":\"{seq_id}\"}}",
+                tour.len()
+            )
+        })
+    }
+
+    /// The discocube of the level and its cycle in the format asked for: hcp, tour, col, edges,
+    /// graphml, dot or npz, each written straight out as it is made.
+    fn export(&self, request: Request, stream: &mut TcpStream) -> Result<u16, Refusal> {
+        let level = level(&request)?;
+        let format = request.query.get("format").cloned().unwrap_or_default();
+        drop(request);
+        let content_type = match format.as_str() {
+            "hcp" | "tour" | "col" | "edges" => "text/plain",
+            "graphml" => "application/xml",
+            "dot" if level > 6 => {
+                return Err((422, "dot only draws the discocubes up to level 6"));
+            }
+            "dot" => "text/vnd.graphviz",
+            "npz" => "application/zip",
+            _ => {
+                return Err((
+                    400,
+                    "format is one of: hcp, tour, col, edges, graphml, dot, npz",
+                ))
+            }
+        };
+        let Woven {
+            tour, verts, adj, ..
+        } = &self.tour(level)?;
+        let name = format!("discocube_{level}");
+        respond(stream, 200, content_type, |out| match format.as_str() {
+            "hcp" => tsplib::write_hcp(out, &name, adj),
+            "tour" => tsplib::write_tour(out, &name, tour),
+            "col" => formats::write_col(out, &name, adj),
+            "edges" => formats::write_edges(out, adj),
+            "graphml" => formats::write_graphml(out, &name, verts, adj),
+            "dot" => formats::write_dot(out, &name, verts, adj, tour, Projection::Isometric),
+            _ => {
+                let mut arrays = ["verts", "tour", "edges"].into_iter().map(|array| {
+                    let npy = match array {
+                        "verts" => npy::to_npy(&npy::verts_array(verts)),
+                        "tour" => npy::to_npy(&npy::tour_array(tour)),
+                        _ => npy::to_npy(&npy::edges_array(adj)),
+                    };
+                    (array, npy)
+                });
+                npy::write_npz(out, &mut arrays)
+            }
+        })
+    }
+
+    /// The cycle of the level and the discocube, the cycle read from the cache when it certifies on
+    /// the discocube, or else woven, certified and put in the cache. Of the requests that miss the
+    /// cache for one level at once, one weaves while the others wait for it and read the cache
+    /// after. A request waits on a level only once it holds its lease, and never takes a lease while
+    /// weaving, so that the levels and the budget cannot wait on each other.
+    fn tour(&self, level: u32) -> Result<Woven<'_>, Refusal> {
+        let _lease = self.budget.take(footprint(level)?)?;
+        let (_, _, verts, vi_map, adj, z_adj, z_order, min_xyz) = make_graph(level);
+        if let Some(tour) = self.cached(level, &adj) {
+            return Ok(Woven {
+                tour,
+                verts,
+                adj,
+                _lease,
+            });
+        }
+        let _claim = self.claim(level);
+        if let Some(tour) = self.cached(level, &adj) {
+            return Ok(Woven {
+                tour,
+                verts,
+                adj,
+                _lease,
+            });
+        }
+        let tour = weave::weave(&adj, &vi_map, &verts, z_adj, z_order, min_xyz);
+        self.weaves.fetch_add(1, Ordering::Relaxed);
+        if certify::id_seq(&tour, &adj) != SequenceID::HamCycle {
+            return Err((500, "the woven cycle did not certify"));
+        }
+        // written aside and renamed, so that a reader never finds half a tour.
+        static WRITES: AtomicU64 = AtomicU64::new(0);
+        let path = self.cache.join(format!("discocube_{level}.tour"));
+        let aside = path.with_extension(format!("{}", WRITES.fetch_add(1, Ordering::Relaxed)));
+        let name = format!("discocube_{level}");
+        if fs::write(&aside, tsplib::to_tour(&name, &tour)).is_ok() {
+            let _ = fs::rename(&aside, &path);
+        }
+        Ok(Woven {
+            tour,
+            verts,
+            adj,
+            _lease,
+        })
+    }
+
+    /// The cycle of the level in the cache, if it is there and certifies on the discocube, which a
+    /// file changed or cut short since it was written does not.
+    fn cached(&self, level: u32, adj: &Adjacency) -> Option<Solution> {
+        let path = self.cache.join(format!("discocube_{level}.tour"));
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|text| tsplib::from_tour(&text).ok())
+            .filter(|tour| certify::id_seq(tour, adj) == SequenceID::HamCycle)
+    }
+
+    /// Claims the level for weaving, once no other request has it.
+    fn claim(&self, level: u32) -> Claim<'_> {
+        let mut weaving = self.weaving.lock().unwrap();
+        while !weaving.insert(level) {
+            weaving = self.woven.wait(weaving).unwrap();
+        }
+        Claim {
+            service: self,
+            level,
+        }
+    }
+}
+
+/// The level of the query, from 1.
+fn level(request: &Request) -> Result<u32, Refusal> {
+    match request.query.get("level").map(|level| level.parse::<u32>()) {
+        Some(Ok(level)) if level > 0 => Ok(level),
+        _ => Err((400, "level is a positive integer")),
+    }
+}
+
+/// The order of the discocube of the level, refused when it is too large to count in a u64, as it
+/// is past level 1.6 million or so.
+fn order(level: u32) -> Result<u64, Refusal> {
+    let n = level as u64;
+    (4 * n)
+        .checked_mul(n + 1)
+        .and_then(|count| count.checked_mul(n + 2))
+        .map(|count| count / 3)
+        .ok_or(TOO_LARGE)
+}
+
+/// The bytes of the budget the discocube of the level takes.
+fn footprint(level: u32) -> Result<u64, Refusal> {
+    order(level)?.checked_mul(BYTES_PER_VERTEX).ok_or(TOO_LARGE)
+}
+
+/// Reads the request line and the headers, up to `MAX_HEAD` between them, and a body as long as
+/// Content-Length says, its memory taken from the budget before it is read.
+fn read_request<'a>(stream: &TcpStream, budget: &'a Budget) -> Result<Request<'a>, Refusal> {
+    let mut reader = BufReader::new(stream.take(MAX_HEAD));
+    let mut line = String::new();
+    let mut read_line = |line: &mut String| match reader.read_line(line) {
+        Ok(_) if line.ends_with('\n') => Ok(()),
+        Ok(_) if reader.get_ref().limit() == 0 => Err((431, "the request's headers are too large")),
+        Ok(_) => Err((400, "the request ends before its headers do")),
+        Err(error) => Err(unread(error)),
+    };
+    read_line(&mut line)?;
+    let (method, target) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+        [method, target, _] => (method.to_string(), target.to_string()),
+        _ => return Err((400, "the request line is malformed")),
+    };
+    let mut length = 0;
+    loop {
+        line.clear();
+        read_line(&mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value
+                    .trim()
+                    .parse()
+                    .or(Err((400, "Content-Length is not a number")))?;
+            }
+        }
+    }
+    if length > MAX_BODY {
+        return Err((413, "the body is too large"));
+    }
+    let lease = budget.take(length as u64)?;
+    reader.get_mut().set_limit(length as u64);
+    let mut body = vec![0; length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|error| match error.kind() {
+            io::ErrorKind::UnexpectedEof => (400, "the body is shorter than its Content-Length"),
+            _ => unread(error),
+        })?;
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+    let query = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    Ok(Request {
+        method,
+        path: path.to_string(),
+        target: target.clone(),
+        query,
+        body,
+        _lease: lease,
+    })
+}
+
+/// Why a request could not be read: the client was too slow, or the connection failed.
+fn unread(error: io::Error) -> Refusal {
+    match error.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => (408, "the request took too long"),
+        _ => (400, "the request could not be read"),
+    }
+}
+
+/// Answers with the status and the body written by `write`, sent in chunks as it is written so that
+/// a large tour is never held twice.
+fn respond(
+    stream: &mut TcpStream,
+    status: u16,
+    content_type: &str,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> Result<u16, Refusal> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Content Too Large",
+        422 => "Unprocessable Content",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    let sent = (|| {
+        write!(
+            stream,
+            "HTTP/1.1 {status} {reason}\r\nContent-Type: {content_type}\r\n\
+             Transfer-Encoding: chunked\r\nConnection: close\r\n\r\n"
+        )?;
+        let mut out = BufWriter::with_capacity(1 << 16, Chunked(&mut *stream));
+        write(&mut out)?;
+        out.flush()?;
+        drop(out);
+        stream.write_all(b"0\r\n\r\n")
+    })();
+    // a client that hangs up early has had all it wanted.
+    let _ = sent;
+    Ok(status)
+}
+
+fn refuse(stream: &mut TcpStream, (status, reason): Refusal) -> Result<u16, Refusal> {
+    respond(stream, status, "application/json", |out| {
+        write!(out, "{{\"error\":\"{reason}\"}}")
+    })
+}
+
+/// Writes everything it is given as one chunk of HTTP's chunked transfer coding.
+struct Chunked<W: Write>(W);
+
+impl<W: Write> Write for Chunked<W> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        if !bytes.is_empty() {
+            write!(self.0, "{:x}\r\n", bytes.len())?;
+            self.0.write_all(bytes)?;
+            self.0.write_all(b"\r\n")?;
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{Read, Write},
+        net::{SocketAddr, TcpListener, TcpStream},
+        path::PathBuf,
+        sync::{atomic::Ordering, Arc},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use hamcycle::graph::{
+        defs::Solution,
+        formats::{self, Projection},
+        npy, tsplib,
+        utils::{
+            certify::{id_seq, SequenceID},
+            make::make_graph,
+        },
+    };
+
+    use super::{listen, Options, Service};
+
+    /// A server on a port of its own with an empty cache, named for the test, of the memory and
+    /// timeout given.
+    fn start(name: &str, memory: u64, timeout: Duration) -> (SocketAddr, Arc<Service>) {
+        let cache: PathBuf =
+            std::env::temp_dir().join(format!("hamcycle-serve-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&cache);
+        fs::create_dir_all(&cache).unwrap();
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let service = Arc::new(Service::new(cache, memory, timeout));
+        let serving = service.clone();
+        thread::spawn(move || listen(listener, serving, 4));
+        (addr, service)
+    }
+
+    /// Sends the request as it is and returns the status and the body, its chunks joined.
+    fn ask(addr: SocketAddr, request: &[u8]) -> (u16, Vec<u8>) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(60)))
+            .unwrap();
+        stream.write_all(request).unwrap();
+        let mut answer = vec![];
+        stream.read_to_end(&mut answer).unwrap();
+        let head = answer
+            .windows(4)
+            .position(|end| end == b"\r\n\r\n")
+            .unwrap();
+        let status = String::from_utf8_lossy(&answer[9..12]).parse().unwrap();
+        let (mut rest, mut body) = (&answer[head + 4..], vec![]);
+        loop {
+            let line = rest.windows(2).position(|end| end == b"\r\n").unwrap();
+            let size = usize::from_str_radix(std::str::from_utf8(&rest[..line]).unwrap(), 16);
+            match size.unwrap() {
+                0 => return (status, body),
+                size => {
+                    body.extend(&rest[line + 2..line + 2 + size]);
+                    rest = &rest[line + 4 + size..];
+                }
+            }
+        }
+    }
+
+    fn get(addr: SocketAddr, target: &str) -> (u16, Vec<u8>) {
+        ask(addr, format!("GET {target} HTTP/1.1\r\n\r\n").as_bytes())
+    }
+
+    fn post(addr: SocketAddr, target: &str, body: &[u8]) -> (u16, Vec<u8>) {
+        let head = format!(
+            "POST {target} HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        );
+        ask(addr, &[head.as_bytes(), body].concat())
+    }
+
+    /// The tour of an answer of /solve.
+    fn tour(answer: &[u8]) -> Solution {
+        let answer = String::from_utf8_lossy(answer);
+        let nodes = &answer[answer.find('[').unwrap() + 1..answer.rfind(']').unwrap()];
+        nodes.split(',').map(|node| node.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn solves_and_certifies_the_answer_sent_back() {
+        let (addr, _) = start("solve", 1 << 30, Duration::from_secs(30));
+        let (status, answer) = get(addr, "/solve?level=4");
+        assert_eq!(status, 200);
+        let (.., adj, _, _, _) = make_graph(4);
+        assert_eq!(id_seq(&tour(&answer), &adj), SequenceID::HamCycle);
+        let (status, certified) = post(addr, "/certify", &answer);
+        assert_eq!(status, 200);
+        assert!(String::from_utf8_lossy(&certified).contains("\"certified\":\"HamCycle\""));
+        let (_, certified) = post(addr, "/certify?level=4", b"1 2 3");
+        assert_eq!(
+            certified,
+            b"{\"error\":\"the tour does not have as many nodes as the discocube\"}"
+        );
+    }
+
+    #[test]
+    fn exports_every_format_as_it_is_written_to_a_file() {
+        let (addr, _) = start("export", 1 << 30, Duration::from_secs(30));
+        let tour = tour(&get(addr, "/solve?level=3").1);
+        let (_, _, verts, _, adj, _, _, _) = make_graph(3);
+        let name = "discocube_3";
+        let npz = npy::to_npz(&[
+            ("verts", npy::to_npy(&npy::verts_array(&verts))),
+            ("tour", npy::to_npy(&npy::tour_array(&tour))),
+            ("edges", npy::to_npy(&npy::edges_array(&adj))),
+        ])
+        .unwrap();
+        for (format, file) in [
+            ("hcp", tsplib::to_hcp(name, &adj).into_bytes()),
+            ("tour", tsplib::to_tour(name, &tour).into_bytes()),
+            ("col", formats::to_col(name, &adj).into_bytes()),
+            ("edges", formats::to_edges(&adj).into_bytes()),
+            (
+                "graphml",
+                formats::to_graphml(name, &verts, &adj).into_bytes(),
+            ),
+            (
+                "dot",
+                formats::to_dot(name, &verts, &adj, &tour, Projection::Isometric).into_bytes(),
+            ),
+            ("npz", npz),
+        ] {
+            assert_eq!(
+                get(addr, &format!("/export?level=3&format={format}")),
+                (200, file)
+            );
+        }
+        assert_eq!(get(addr, "/export?level=7&format=dot").0, 422);
+        assert_eq!(get(addr, "/export?level=3&format=svg").0, 400);
+    }
+
+    #[test]
+    fn weaves_a_level_once_for_requests_at_the_same_time() {
+        let (addr, service) = start("once", 1 << 30, Duration::from_secs(30));
+        let asking: Vec<_> = (0..4)
+            .map(|_| thread::spawn(move || get(addr, "/solve?level=12")))
+            .collect();
+        let answers: Vec<_> = asking.into_iter().map(|ask| ask.join().unwrap()).collect();
+        assert!(answers.iter().all(|answer| answer == &answers[0]));
+        assert_eq!(service.weaves.load(Ordering::Relaxed), 1);
+        get(addr, "/solve?level=12");
+        assert_eq!(service.weaves.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn weaves_again_over_a_cached_tour_that_does_not_certify() {
+        let (addr, service) = start("stale", 1 << 30, Duration::from_secs(30));
+        let (.., adj, _, _, _) = make_graph(3);
+        let path = service.cache.join("discocube_3.tour");
+        let wrong: Solution = (0..adj.len() as u32).collect();
+        fs::write(&path, tsplib::to_tour("discocube_3", &wrong)).unwrap();
+        let (status, answer) = get(addr, "/solve?level=3");
+        assert_eq!(status, 200);
+        assert_eq!(id_seq(&tour(&answer), &adj), SequenceID::HamCycle);
+        assert_eq!(service.weaves.load(Ordering::Relaxed), 1);
+        let cached = tsplib::from_tour(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(id_seq(&cached, &adj), SequenceID::HamCycle);
+    }
+
+    #[test]
+    fn refuses_a_body_beyond_the_budget_before_reading_it() {
+        let (addr, _) = start("budget", 1 << 20, Duration::from_secs(30));
+        let head = b"POST /certify HTTP/1.1\r\nContent-Length: 2000000\r\n\r\n";
+        assert_eq!(ask(addr, head).0, 413);
+        assert_eq!(get(addr, "/solve?level=40").0, 413);
+        assert_eq!(get(addr, "/solve?level=0").0, 400);
+        assert_eq!(get(addr, "/solve").0, 400);
+        assert_eq!(get(addr, "/nowhere").0, 404);
+        assert_eq!(post(addr, "/solve?level=1", b"").0, 405);
+        assert_eq!(get(addr, "/certify").0, 405);
+        let head = format!(
+            "GET /solve?level=1 HTTP/1.1\r\nX: {}\r\n\r\n",
+            "x".repeat(70 << 10)
+        );
+        assert_eq!(ask(addr, head.as_bytes()).0, 431);
+    }
+
+    #[test]
+    fn refuses_a_level_too_large_to_count_with_the_most_memory() {
+        let (addr, _) = start("huge", u64::MAX, Duration::from_secs(30));
+        assert_eq!(get(addr, "/solve?level=4000000000").0, 413);
+        assert_eq!(get(addr, "/export?level=4000000000&format=hcp").0, 413);
+        assert_eq!(post(addr, "/certify?level=4000000000", b"[1, 2]").0, 413);
+        assert_eq!(get(addr, "/solve?level=1").0, 200);
+    }
+
+    #[test]
+    fn answers_a_silent_client_once_the_timeout_runs_out() {
+        let (addr, _) = start("silent", 1 << 30, Duration::from_millis(200));
+        let start = Instant::now();
+        assert_eq!(ask(addr, b"GET /solve?level=1 HTTP/1.1\r\n").0, 408);
+        let head = b"POST /certify HTTP/1.1\r\nContent-Length: 100\r\n\r\n1 2";
+        assert_eq!(ask(addr, head).0, 408);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn reads_its_options_after_serve() {
+        let line = "hamcycle --port 8080 --cache c --workers 2 --memory 64";
+        let options = Options::parse(line.split(' ').map(String::from).collect());
+        assert_eq!(
+            options,
+            Ok(Options {
+                port: 8080,
+                cache: PathBuf::from("c"),
+                workers: 2,
+                memory: 64 << 20,
+            })
+        );
+    }
+
+    #[test]
+    fn refuses_options_it_does_not_take() {
+        let parse = |line: &str| Options::parse(line.split(' ').map(String::from).collect());
+        assert_eq!(
+            parse("hamcycle --workers 0"),
+            Err("--workers expects a positive integer")
+        );
+        assert_eq!(
+            parse("hamcycle --port"),
+            Err("a flag is given without its value")
+        );
+        assert_eq!(
+            parse("hamcycle 3"),
+            Err("serve only takes --port, --cache, --workers and --memory")
+        );
+    }
+}
@@ -0,0 +1,24 @@
+// Weaves the first levels in the wasm build and certifies every cycle, both with the certifier
+// compiled into it and by walking the coordinates, for `node web/check.mjs [hamcycle.wasm]`.
+import { readFile } from "node:fs/promises";
+import { load } from "./hamcycle.js";
+
+const path = process.argv[2] ?? new URL("hamcycle.wasm", import.meta.url);
+const hamcycle = await load(await readFile(path));
+let failed = false;
+for (let level = 1; level <= 8; level++) {
+  const { nodes, coordinates } = hamcycle.weave(level);
+  const verdict = hamcycle.certify(level, nodes);
+  // every step of the cycle, the last back to the first, goes to a neighbour two apart on one axis.
+  const steps = nodes.every((_, idx) => {
+    const [a, b] = [idx, (idx + 1) % nodes.length].map((at) => coordinates.subarray(3 * at, 3 * at + 3));
+    return Math.abs(a[0] - b[0]) + Math.abs(a[1] - b[1]) + Math.abs(a[2] - b[2]) === 2;
+  });
+  const swapped = nodes.slice();
+  [swapped[1], swapped[7]] = [swapped[7], swapped[1]];
+  const broken = hamcycle.certify(level, swapped);
+  const passed = verdict === "HamCycle" && steps && broken !== "HamCycle";
+  failed ||= !passed;
+  console.log(`| 🇳 ${String(level).padStart(4)} | ⭕️ ${String(nodes.length).padStart(10)} | 📌 ${verdict} | ${passed ? "OK" : "FAILED"} |`);
+}
+process.exit(failed ? 1 : 0);
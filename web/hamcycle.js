@@ -0,0 +1,69 @@
+// Loads hamcycle.wasm, built with the ffi feature, and weaves through the C interface it exports.
+// Works alike in the browser and in node.
+
+const OK = 0;
+const VERDICTS = ["Broken", "HamChain", "HamCycle"];
+
+export async function load(bytes) {
+  const { instance } = await WebAssembly.instantiate(bytes, {});
+  const ffi = instance.exports;
+  const check = (status, what) => {
+    if (status !== OK) throw new Error(`${what} failed with status ${status}`);
+  };
+  const handle = (make, what) => {
+    const slot = ffi.hamcycle_alloc(4);
+    try {
+      check(make(slot), what);
+      return new Uint32Array(ffi.memory.buffer, slot, 1)[0];
+    } finally {
+      ffi.hamcycle_dealloc(slot, 4);
+    }
+  };
+  const read = (Array, len, fill, what) => {
+    const bytes = len * Array.BYTES_PER_ELEMENT;
+    const buffer = ffi.hamcycle_alloc(bytes);
+    try {
+      check(fill(buffer, len), what);
+      return new Array(ffi.memory.buffer, buffer, len).slice();
+    } finally {
+      ffi.hamcycle_dealloc(buffer, bytes);
+    }
+  };
+
+  // the woven cycle of the level: its nodes in order and their coordinates, x, y and z of each.
+  function weave(level) {
+    const graph = handle((slot) => ffi.hamcycle_graph_new(level, slot), "making the graph");
+    try {
+      const tour = handle((slot) => ffi.hamcycle_solve(graph, slot), "weaving");
+      try {
+        const len = ffi.hamcycle_tour_len(tour);
+        const nodes = read(Uint32Array, len, (buffer, len) => ffi.hamcycle_tour_nodes(tour, buffer, len), "reading the tour");
+        const coordinates = read(Int16Array, 3 * len, (buffer, len) => ffi.hamcycle_tour_coordinates(graph, tour, buffer, len), "reading the coordinates");
+        return { nodes, coordinates };
+      } finally {
+        ffi.hamcycle_tour_free(tour);
+      }
+    } finally {
+      ffi.hamcycle_graph_free(graph);
+    }
+  }
+
+  // what the certifier makes of the nodes as a tour of the level's discocube.
+  function certify(level, nodes) {
+    const graph = handle((slot) => ffi.hamcycle_graph_new(level, slot), "making the graph");
+    const bytes = 4 * nodes.length;
+    const buffer = ffi.hamcycle_alloc(bytes);
+    const verdict = ffi.hamcycle_alloc(4);
+    try {
+      new Uint32Array(ffi.memory.buffer, buffer, nodes.length).set(nodes);
+      check(ffi.hamcycle_certify(graph, buffer, nodes.length, verdict), "certifying");
+      return VERDICTS[new Int32Array(ffi.memory.buffer, verdict, 1)[0]];
+    } finally {
+      ffi.hamcycle_dealloc(verdict, 4);
+      ffi.hamcycle_dealloc(buffer, bytes);
+      ffi.hamcycle_graph_free(graph);
+    }
+  }
+
+  return { weave, certify };
+}
@@ -0,0 +1,110 @@
+// Weaves the discocube of the level chosen in the browser and draws its cycle in WebGL, coloured
+// round the hue circle by the place of every vertex along the tour. Drag to turn it.
+import { load } from "./hamcycle.js";
+
+const canvas = document.getElementById("view");
+const status = document.getElementById("status");
+const gl = canvas.getContext("webgl");
+
+const program = link(
+  `attribute vec3 position;
+   attribute vec3 colour;
+   uniform mat4 transform;
+   varying vec3 shade;
+   void main() {
+     gl_Position = transform * vec4(position, 1.0);
+     shade = colour;
+   }`,
+  `precision mediump float;
+   varying vec3 shade;
+   void main() { gl_FragColor = vec4(shade, 1.0); }`,
+);
+const positions = gl.createBuffer();
+const colours = gl.createBuffer();
+let count = 0;
+let reach = 1;
+let [yaw, pitch] = [0.6, 0.5];
+
+function link(vertex, fragment) {
+  const program = gl.createProgram();
+  for (const [kind, source] of [[gl.VERTEX_SHADER, vertex], [gl.FRAGMENT_SHADER, fragment]]) {
+    const shader = gl.createShader(kind);
+    gl.shaderSource(shader, source);
+    gl.compileShader(shader);
+    gl.attachShader(program, shader);
+  }
+  gl.linkProgram(program);
+  return program;
+}
+
+// the colour of the hue given, from 0 to 1, at full saturation and value.
+function hue(h) {
+  const k = (n) => (n + h * 6) % 6;
+  return [5, 3, 1].map((n) => 1 - Math.max(0, Math.min(k(n), 4 - k(n), 1)));
+}
+
+function show({ coordinates }) {
+  count = coordinates.length / 3;
+  reach = Math.max(...coordinates.map(Math.abs));
+  const colour = new Float32Array(coordinates.length);
+  for (let idx = 0; idx < count; idx++) colour.set(hue(idx / count), 3 * idx);
+  gl.bindBuffer(gl.ARRAY_BUFFER, positions);
+  gl.bufferData(gl.ARRAY_BUFFER, Float32Array.from(coordinates), gl.STATIC_DRAW);
+  gl.bindBuffer(gl.ARRAY_BUFFER, colours);
+  gl.bufferData(gl.ARRAY_BUFFER, colour, gl.STATIC_DRAW);
+}
+
+// turns by the yaw and pitch, scales the graph into view and foreshortens it a little.
+function transform(aspect) {
+  const [cy, sy, cp, sp] = [Math.cos(yaw), Math.sin(yaw), Math.cos(pitch), Math.sin(pitch)];
+  const scale = 0.8 / reach;
+  const [across, up] = [scale / Math.max(aspect, 1), scale * Math.min(aspect, 1)];
+  const depth = 0.25;
+  // column-major: x' = R x scaled, w = 1 + depth * z'.
+  return new Float32Array([
+    cy * across, sy * sp * up, 0, -sy * cp * scale * depth,
+    0, cp * up, 0, sp * scale * depth,
+    sy * across, -cy * sp * up, 0, cy * cp * scale * depth,
+    0, 0, 0, 1,
+  ]);
+}
+
+function draw() {
+  const [width, height] = [canvas.clientWidth, canvas.clientHeight];
+  [canvas.width, canvas.height] = [width * devicePixelRatio, height * devicePixelRatio];
+  gl.viewport(0, 0, canvas.width, canvas.height);
+  gl.clearColor(0.07, 0.07, 0.07, 1);
+  gl.clear(gl.COLOR_BUFFER_BIT);
+  if (count) {
+    gl.useProgram(program);
+    for (const [name, buffer] of [["position", positions], ["colour", colours]]) {
+      const at = gl.getAttribLocation(program, name);
+      gl.bindBuffer(gl.ARRAY_BUFFER, buffer);
+      gl.enableVertexAttribArray(at);
+      gl.vertexAttribPointer(at, 3, gl.FLOAT, false, 0, 0);
+    }
+    gl.uniformMatrix4fv(gl.getUniformLocation(program, "transform"), false, transform(width / height));
+    gl.drawArrays(gl.LINE_LOOP, 0, count);
+  }
+  requestAnimationFrame(draw);
+}
+
+canvas.addEventListener("pointermove", (event) => {
+  if (event.buttons) {
+    yaw += event.movementX * 0.01;
+    pitch = Math.max(-1.5, Math.min(1.5, pitch + event.movementY * 0.01));
+  }
+});
+
+const hamcycle = await load(await (await fetch("hamcycle.wasm")).arrayBuffer());
+const weave = () => {
+  const level = Number(document.getElementById("level").value);
+  const start = performance.now();
+  const woven = hamcycle.weave(level);
+  const verdict = hamcycle.certify(level, woven.nodes);
+  show(woven);
+  status.textContent = `⭕️ ${woven.nodes.length} vertices, 📌 ${verdict}, 🕗 ${Math.round(performance.now() - start)} ms`;
+};
+document.getElementById("weave").addEventListener("click", weave);
+weave();
+requestAnimationFrame(draw);